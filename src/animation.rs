@@ -0,0 +1,180 @@
+// An Animation is just the timing half of animating: which frame should be showing
+// now. It doesn't know anything about sprites; the frame numbers it hands back are
+// meant for something like SpriteSheet::frame_at.
+
+use std::time::Duration;
+
+/// What an animation does when it gets to the end.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum AnimationMode {
+    /// Go back to the first frame and keep going, forever.
+    #[default]
+    Loop,
+    /// Stop on the last frame.
+    Once,
+}
+
+/// A list of frames, each shown for its own length of time, played by calling
+/// `advance` with however much time has passed (like the `dt` from `App::update`).
+#[derive(Clone, Debug)]
+pub struct Animation {
+    // Frame numbers and how long each is shown for
+    frames: Vec<(usize, Duration)>,
+    mode: AnimationMode,
+    // Which entry in frames we're on, and how long we've been on it
+    current: usize,
+    elapsed: Duration,
+    finished: bool,
+}
+
+impl Animation {
+    /// An animation that shows each `(frame, duration)` in order. With no frames at
+    /// all, it's always on frame 0 (and already finished, if it's `Once`).
+    pub fn new(frames: Vec<(usize, Duration)>, mode: AnimationMode) -> Self {
+        let finished = mode == AnimationMode::Once && frames.is_empty();
+        Self { frames, mode, current: 0, elapsed: Duration::ZERO, finished }
+    }
+
+    /// An animation where every frame is shown for the same amount of time.
+    pub fn uniform(frames: impl IntoIterator<Item = usize>, frame_time: Duration, mode: AnimationMode) -> Self {
+        Self::new(frames.into_iter().map(|f| (f, frame_time)).collect(), mode)
+    }
+
+    /// Move the animation along by `dt`. If that's longer than the current frame
+    /// has left, we go on to the next one, and the next, as many as it covers.
+    pub fn advance(&mut self, dt: Duration) {
+        if self.finished || self.frames.is_empty() { return }
+        self.elapsed += dt;
+
+        if self.mode == AnimationMode::Loop {
+            // Going all the way around brings us back to the same spot, so whole
+            // loops can be skipped; that way a huge dt doesn't mean a huge loop
+            // here. (If the whole thing takes no time, it can't go anywhere.)
+            let total: Duration = self.frames.iter().map(|&(_, d)| d).sum();
+            if total.is_zero() { return }
+            if self.elapsed >= total {
+                self.elapsed = Duration::from_nanos((self.elapsed.as_nanos() % total.as_nanos()) as u64)
+            }
+        }
+
+        while self.elapsed >= self.frames[self.current].1 {
+            if self.current + 1 == self.frames.len() && self.mode == AnimationMode::Once {
+                self.finished = true;
+                self.elapsed = self.frames[self.current].1;
+                return
+            }
+            self.elapsed -= self.frames[self.current].1;
+            self.current = (self.current + 1) % self.frames.len()
+        }
+    }
+
+    /// The frame number that should be showing now.
+    pub fn current_frame(&self) -> usize {
+        self.frames.get(self.current).map_or(0, |&(frame, _)| frame)
+    }
+
+    /// Whether a `Once` animation has shown its last frame for that frame's whole
+    /// duration. A looping animation never finishes.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Start again from the first frame.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.elapsed = Duration::ZERO;
+        self.finished = self.mode == AnimationMode::Once && self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn loops_wrap_around() {
+        let mut anim = Animation::uniform([3, 4, 5], MS * 100, AnimationMode::Loop);
+        assert_eq!(anim.current_frame(), 3);
+        anim.advance(MS * 250);
+        assert_eq!(anim.current_frame(), 5);
+        // Off the end and back to the start, 50 ms into it
+        anim.advance(MS * 100);
+        assert_eq!(anim.current_frame(), 3);
+
+        // A year is a whole number of loops, so this lands one frame on, and the
+        // whole loops are skipped rather than stepped through
+        anim.advance(Duration::from_secs(365 * 24 * 60 * 60) + MS * 100);
+        assert_eq!(anim.current_frame(), 4);
+        assert!(!anim.is_finished())
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut anim = Animation::uniform([1, 2], MS * 100, AnimationMode::Once);
+        anim.advance(MS * 150);
+        assert_eq!(anim.current_frame(), 2);
+        // It's only finished once the last frame's had its whole time
+        anim.advance(MS * 49);
+        assert!(!anim.is_finished());
+        anim.advance(MS);
+        assert!(anim.is_finished());
+        anim.advance(Duration::from_secs(10));
+        assert_eq!(anim.current_frame(), 2);
+
+        // Overshooting the end in one go clamps to the last frame too
+        let mut anim = Animation::uniform([1, 2], MS * 100, AnimationMode::Once);
+        anim.advance(Duration::from_secs(10));
+        assert!(anim.is_finished());
+        assert_eq!(anim.current_frame(), 2);
+    }
+
+    #[test]
+    fn zero_duration_frames() {
+        // Frames with no time are passed straight over
+        let mut anim = Animation::new(vec![(7, Duration::ZERO), (8, MS * 100), (9, Duration::ZERO)], AnimationMode::Loop);
+        anim.advance(Duration::ZERO);
+        assert_eq!(anim.current_frame(), 8);
+        anim.advance(MS * 100);
+        assert_eq!(anim.current_frame(), 8);
+
+        // Unless it's the last frame of a Once, which it finishes on
+        let mut anim = Animation::new(vec![(7, MS * 100), (8, Duration::ZERO)], AnimationMode::Once);
+        anim.advance(MS * 100);
+        assert!(anim.is_finished());
+        assert_eq!(anim.current_frame(), 8);
+
+        // A loop that takes no time at all stays where it is
+        let mut anim = Animation::new(vec![(1, Duration::ZERO), (2, Duration::ZERO)], AnimationMode::Loop);
+        anim.advance(Duration::from_secs(1));
+        assert_eq!(anim.current_frame(), 1);
+
+        // And no frames is frame 0, finished already if it's a Once
+        assert!(Animation::new(vec![], AnimationMode::Once).is_finished());
+        let mut empty = Animation::new(vec![], AnimationMode::Loop);
+        empty.advance(Duration::from_secs(1));
+        assert!(!empty.is_finished());
+        assert_eq!(empty.current_frame(), 0);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut anim = Animation::uniform([1, 2, 3], MS * 100, AnimationMode::Once);
+        anim.advance(Duration::from_secs(1));
+        assert!(anim.is_finished());
+        anim.reset();
+        assert!(!anim.is_finished());
+        assert_eq!(anim.current_frame(), 1);
+        // With the whole of the first frame to go again
+        anim.advance(MS * 99);
+        assert_eq!(anim.current_frame(), 1);
+        anim.advance(MS);
+        assert_eq!(anim.current_frame(), 2);
+
+        let mut looping = Animation::uniform([1, 2], MS * 100, AnimationMode::Loop);
+        looping.advance(MS * 150);
+        looping.reset();
+        assert_eq!(looping.current_frame(), 1);
+    }
+}
@@ -0,0 +1,673 @@
+// All the winit / Pixels plumbing lives here, so that an app only has to say what
+// happens each tick and what to draw.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use pixels::wgpu::SurfaceError;
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::error::EventLoopError;
+use winit::event::{Event, Ime, StartCause, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoopWindowTarget};
+use winit::window::{Icon, Window};
+use crate::{Canvas, Color, Context, Error, FrameStats, Input, PresentMode, RenderTexture, ResolutionPolicy, ScalingMode, Surface, UpdateTiming, WindowConfig, WindowKey};
+#[cfg(feature = "audio")]
+use crate::audio::Audio;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+use crate::post::PostRenderer;
+use crate::scaling::{self, Scaler};
+use crate::timing::{FixedStep, FrameLimiter};
+
+// With ResolutionPolicy::MatchWindow, the buffer's only made again once the window
+// has kept the same size for this long, so dragging the edge of the window doesn't
+// reallocate it for every pixel of the drag
+const RESIZE_SETTLE: Duration = Duration::from_millis(150);
+
+/// The game (or whatever it is). `run_app` calls `update` on a timer and `draw`
+/// whenever the window needs redrawing.
+pub trait App {
+    /// Advance the state of the world one tick. Updates happen at a fixed rate
+    /// (`WindowConfig::tick_rate_hz`), so `dt` is always the same duration, one over
+    /// the tick rate; if frames are slow, there will be several updates in a row to
+    /// catch up.
+    fn update(&mut self, ctx: &mut Context, dt: Duration);
+
+    /// Draw the current state into the pixel buffer. This happens as often as the
+    /// display allows, which has nothing to do with how often `update` happens, so
+    /// usually we're partway between one update and the next: `alpha` is how far,
+    /// from 0.0 (just updated) up to but never quite 1.0 (about to update again),
+    /// even when a stall has made it drop updates. To make motion smooth
+    /// at any frame rate, keep the previous state around and draw things at
+    /// `previous + (current - previous) * alpha`.
+    fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, alpha: f32);
+
+    /// Draw one of the other windows, the ones from `Context::open_window`; `window`
+    /// says which. This is just like `draw`, with a canvas the size of that window's
+    /// pixel buffer. Apps with only the one window don't need it, so by default it
+    /// draws nothing.
+    fn draw_window(&mut self, _ctx: &Context, _window: WindowKey, _canvas: &mut Canvas, _alpha: f32) {}
+}
+
+/// Open a window described by `config` and run `app` in it until the window closes
+/// (or the app calls `Context::quit`), or something goes wrong that we can't get
+/// past. If the app opens more windows with `Context::open_window`, it runs until
+/// they've all been closed; closing just the main one leaves the rest running.
+///
+/// If drawing a frame fails because the window's surface is `SurfaceError::Lost` or
+/// `SurfaceError::Outdated`, which can happen after the machine sleeps or the
+/// graphics card changes, the surface is reconfigured and the frame tried again;
+/// only if that fails too does `run_app` return the error. `SurfaceError::Timeout`
+/// and `SurfaceError::OutOfMemory` aren't retried.
+///
+/// Problems it can carry on past, like that retry, a present mode falling back to
+/// Fifo, or no audio device, go to the `log` crate (as debug messages and warnings)
+/// rather than being printed, so hook up a logger like `env_logger` to see them.
+pub fn run_app<A: App + 'static>(mut app: A, config: WindowConfig) -> Result<(), Error> {
+    // Updates and redraws are separate. We redraw as fast as the display will let us (with
+    // vsync, rendering the Pixels blocks until the next vsync, so that's the refresh rate)
+    // and then, each time around the loop (or after each present, with UpdateTiming::Present),
+    // run however many updates are needed to keep the world moving at tick_rate_hz. That way
+    // a slow frame doesn't slow the world down, and a fast display doesn't speed it up.
+    let mut timestep = FixedStep::new(config.tick_rate_hz);
+    // If there's a frame rate cap, that also waits each time around. It goes before
+    // the updates, so the time spent waiting just counts toward the next update.
+    let mut limiter = FrameLimiter::new(config.max_fps);
+    // Or the updates can go by when frames are presented instead, as long as there's
+    // a main window to present them
+    let on_present = config.update_timing == UpdateTiming::Present;
+
+    // A window needs an event loop
+    let event_loop = winit::event_loop::EventLoop::new()?;
+
+    // The main window is the one from the config; the app can open more from update,
+    // and those have keys so it can tell them apart. Everything's looked up by winit's
+    // id, since that's what comes with the events.
+    let main = OpenWindow::new(&event_loop, &config, None)?;
+    let mut windows = HashMap::new();
+
+    // The Context needs to see the Pixels too, to do things like screenshots
+    let mut ctx = Context {
+        pixels: Some(main.pixels.clone()),
+        pixel_size: main.pixel_size,
+        window: Some(main.window.clone()),
+        present_mode: main.present_mode,
+        scaling: config.scaling,
+        pixel_aspect: config.pixel_aspect,
+        safe_area_inset: config.safe_area_inset,
+        tick: timestep.step(),
+        ..Context::default()
+    };
+    windows.insert(main.window.id(), main);
+
+    // The audio output has to last as long as the loop does
+    #[cfg(feature = "audio")]
+    let audio = Audio::new();
+    #[cfg(feature = "audio")]
+    {
+        ctx.audio = audio.as_ref().map(Audio::handle)
+    }
+
+    // The timings for the frame in progress; the context has the last finished one
+    let mut stats = FrameStats::default();
+
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = Gamepads::new();
+
+    // Whether the main window's hidden, as far as we know. Not every platform says.
+    let mut occluded = false;
+    let pause_when_unfocused = config.pause_when_unfocused;
+    let paused = move |ctx: &Context, occluded: bool| pause_when_unfocused && (ctx.unfocused || occluded);
+
+    // Errors inside the event loop can't just be returned from it, so they're kept
+    // here while the loop shuts down
+    let mut failure = None;
+    let failed = &mut failure;
+
+    event_loop.run(move |event, target| {
+        match event {
+            Event::WindowEvent { window_id, event } => {
+                let Some(open) = windows.get_mut(&window_id) else { return };
+                let is_main = open.key.is_none();
+                match event {
+                    // Clicking the little x closes that window, and when there aren't
+                    // any left, we're done. Without the main window, the context has
+                    // nothing to point at any more.
+                    WindowEvent::CloseRequested => {
+                        if let Some(key) = open.key {
+                            ctx.windows.retain(|&k| k != key)
+                        } else {
+                            ctx.window = None;
+                            ctx.pixels = None;
+                            occluded = false;
+                            ctx.set_mouse_window_pos(None)
+                        }
+                        windows.remove(&window_id);
+                        if windows.is_empty() {
+                            target.exit()
+                        }
+                    }
+
+                    // Redraw if it's redrawing time
+                    WindowEvent::RedrawRequested => {
+                        // First have the app redraw stuff into pixels' rgba buffer,
+                        // then have pixels draw itself into our scaled offset buffer:
+                        let draw_start = Instant::now();
+                        if is_main {
+                            ctx.frames.frame(draw_start);
+                            // The app might have resized the pixel buffer since the
+                            // last frame
+                            if open.pixel_size != ctx.pixel_size {
+                                open.buffer_resized(ctx.pixel_size)
+                            }
+                        }
+                        let (width, height) = open.pixel_size;
+                        let alpha = timestep.alpha();
+                        let mut draw = |canvas: &mut Canvas| match open.key {
+                            None => {
+                                app.draw(&ctx, canvas, alpha);
+                                ctx.widgets.draw(canvas)
+                            }
+                            Some(key) => app.draw_window(&ctx, key, canvas, alpha),
+                        };
+                        draw_frame(open.pixels.borrow_mut().frame_mut(), (width, height), open.back.as_mut(), &mut draw);
+                        let present_start = Instant::now();
+                        stats.draw_time += present_start - draw_start;
+                        if let Err(err) = open.render() {
+                            *failed = Some(err);
+                            target.exit();
+                            return
+                        }
+                        stats.present_time += present_start.elapsed();
+
+                        // The main window's frame is the one that counts as a frame
+                        if is_main {
+                            ctx.frame_stats = stats;
+                            stats = FrameStats::default();
+
+                            // Updating right after a present, the time between updates
+                            // is the time between presents
+                            if on_present && !paused(&ctx, occluded) && !run_updates(&mut app, &mut ctx, &mut timestep, &mut stats) {
+                                target.exit()
+                            }
+                        }
+                    }
+
+                    // Update that the mouse moved if it did. The mouse is only tracked
+                    // over the main window.
+                    WindowEvent::CursorMoved { position: pos, .. } if is_main => {
+                        // The context works out which pixel that is
+                        ctx.set_mouse_window_pos(Some((pos.x, pos.y)))
+                    }
+
+                    // winit doesn't tell us where the mouse went, just that it's gone
+                    WindowEvent::CursorLeft { .. } if is_main => {
+                        ctx.set_mouse_window_pos(None)
+                    }
+
+                    // Mouse buttons get saved up for the next update, like keys
+                    WindowEvent::MouseInput { state, button, .. } if is_main => {
+                        ctx.input.handle_mouse_button(button, state)
+                    }
+
+                    // So does scrolling
+                    WindowEvent::MouseWheel { delta, .. } if is_main => {
+                        ctx.input.handle_scroll(delta)
+                    }
+
+                    // Files dragged onto the main window get saved up too, one event
+                    // per file
+                    WindowEvent::HoveredFile(_) if is_main => {
+                        ctx.input.handle_file_hovered(true)
+                    }
+
+                    WindowEvent::HoveredFileCancelled if is_main => {
+                        ctx.input.handle_file_hovered(false)
+                    }
+
+                    WindowEvent::DroppedFile(path) if is_main => {
+                        ctx.input.handle_file_dropped(&path)
+                    }
+
+                    // Keyboard events get saved up for the next update. They come to
+                    // whichever window has focus, but it's the same keyboard.
+                    WindowEvent::KeyboardInput { event, .. } => {
+                        ctx.input.handle_key(&event)
+                    }
+
+                    // Shift, control and so on are their own events, on top of the keys
+                    WindowEvent::ModifiersChanged(modifiers) => {
+                        ctx.input.set_modifiers(modifiers.state())
+                    }
+
+                    // If there's an input method (for typing in Japanese, say), finished
+                    // text comes from it instead of from the keys
+                    WindowEvent::Ime(Ime::Commit(text)) => {
+                        ctx.input.handle_text(&text)
+                    }
+
+                    // Losing focus means we stop hearing about keys, so let go of them all
+                    // now rather than leave them stuck down. Losing focus or the main
+                    // window being hidden can also pause us. (Going from one of our
+                    // windows to another loses focus and then gets it right back.)
+                    event @ (WindowEvent::Focused(_) | WindowEvent::Occluded(_)) => {
+                        let was_paused = paused(&ctx, occluded);
+                        match event {
+                            WindowEvent::Focused(focused) => {
+                                ctx.unfocused = !focused;
+                                if !focused {
+                                    ctx.input.release_all()
+                                }
+                            }
+                            WindowEvent::Occluded(hidden) if is_main => occluded = hidden,
+                            _ => {}
+                        }
+
+                        // Coming back from a pause, the time we were away doesn't count,
+                        // and whatever's on the screen is stale
+                        if was_paused && !paused(&ctx, occluded) {
+                            timestep.reset(Instant::now());
+                            target.set_control_flow(ControlFlow::Poll);
+                            for open in windows.values() {
+                                open.window.request_redraw()
+                            }
+                        }
+                    }
+
+                    // Resize the texture when the window resizes (this will also handle rescaling
+                    // the Pixels instance). Going fullscreen is just a big resize, as far as
+                    // we're concerned.
+                    WindowEvent::Resized(new_size) => {
+                        if let Err(err) = open.resize(new_size.width, new_size.height) {
+                            *failed = Some(err);
+                            target.exit();
+                            return
+                        }
+                        // The mouse is over a different pixel now, even if it didn't move
+                        if is_main {
+                            ctx.update_mouse_pixel_pos()
+                        }
+                        if open.resolution_policy == ResolutionPolicy::MatchWindow {
+                            open.resized_at = Some(Instant::now())
+                        }
+                    }
+
+                    // Drop other events
+                    _ => {}
+                }
+            }
+
+            // Start the clock on init. We don't want to wait for anything between frames,
+            // just go around again.
+            Event::NewEvents(StartCause::Init) => {
+                timestep.reset(Instant::now());
+                target.set_control_flow(ControlFlow::Poll);
+            }
+
+            // Once all the events for this time around are handled, catch the world up
+            // to now and ask for another frame
+            Event::AboutToWait => {
+                // Paused, there's nothing to do until the window comes back (we'll
+                // still redraw if the OS asks us to)
+                if paused(&ctx, occluded) {
+                    target.set_control_flow(ControlFlow::Wait);
+                    return
+                }
+                if let Some(limiter) = &mut limiter {
+                    limiter.wait()
+                }
+                // Windows whose buffers follow their size, that have stopped changing
+                // size, get new buffers
+                for open in windows.values_mut() {
+                    if let Err(err) = open.match_buffer(&mut ctx) {
+                        *failed = Some(err);
+                        target.exit();
+                        return
+                    }
+                }
+                #[cfg(feature = "gamepad")]
+                if let Some(gamepads) = &mut gamepads {
+                    gamepads.poll(&mut ctx.input)
+                }
+                // Unless they go by presents, now's when the updates happen
+                let by_timer = !on_present || ctx.window.is_none();
+                if by_timer && !run_updates(&mut app, &mut ctx, &mut timestep, &mut stats) {
+                    target.exit();
+                    return
+                }
+
+                // Windows the app opened or closed during those updates. Opening goes
+                // first, so one that was opened and closed again is just gone.
+                for (key, config) in ctx.opening.drain(..) {
+                    match OpenWindow::new(target, &config, Some(key)) {
+                        Ok(open) => { windows.insert(open.window.id(), open); }
+                        Err(err) => {
+                            *failed = Some(err);
+                            target.exit();
+                            return
+                        }
+                    }
+                }
+                for key in ctx.closing.drain(..) {
+                    windows.retain(|_, open| open.key != Some(key))
+                }
+                if windows.is_empty() {
+                    target.exit();
+                    return
+                }
+
+                for open in windows.values() {
+                    open.window.request_redraw()
+                }
+            }
+
+            // Drop other events
+            _ => {}
+        }
+    })?;
+    failure.map_or(Ok(()), Err)
+}
+
+// Run however many updates are due by now, adding the time they take to the frame's
+// stats. False if the app quit, and the loop should stop.
+fn run_updates<A: App>(app: &mut A, ctx: &mut Context, timestep: &mut FixedStep, stats: &mut FrameStats) -> bool {
+    let update_start = Instant::now();
+    for _ in 0..timestep.advance(update_start) {
+        ctx.widgets.clear();
+        app.update(ctx, timestep.step());
+        // Presses and releases were all seen by the first update
+        ctx.input.end_tick();
+        if ctx.quit { return false }
+    }
+    stats.update_time += update_start.elapsed();
+    true
+}
+
+/// Run `app` for `frames` frames with no window at all, drawing into textures
+/// instead, and hand back what each frame looked like. For tests: every frame is
+/// exactly one update, with the usual `dt`, and one draw, with an `alpha` of zero,
+/// so the same app always draws the same frames. The drawing is the same as in a
+/// window (it's a `Canvas` either way), and like a window's pixel buffer, each frame
+/// starts out with whatever the last one left there.
+///
+/// Only `pixel_size` and `tick_rate_hz` from the config matter here. Nothing comes
+/// in through `Context::input`, windows from `Context::open_window` are closed
+/// straight away, and the things a context does to a window do nothing. If the
+/// app calls `Context::quit`, it stops there, like it would in a window: the
+/// update that quit doesn't get drawn.
+pub fn render_headless<A: App>(config: WindowConfig, frames: usize, app: A) -> Vec<RenderTexture> {
+    render_headless_with_input(config, frames, app, |_, _| {})
+}
+
+// render_headless, but before each frame's update, `inject` gets that frame's number
+// and the Input, to feed it whatever events the test wants the app to have seen by
+// then. The edges get cleared after each update, the same as in a window.
+pub(crate) fn render_headless_with_input<A: App>(config: WindowConfig, frames: usize, mut app: A, mut inject: impl FnMut(usize, &mut Input)) -> Vec<RenderTexture> {
+    let (width, height) = config.pixel_size;
+    let step = FixedStep::new(config.tick_rate_hz).step();
+    let mut ctx = Context {
+        pixel_size: config.pixel_size,
+        scaling: config.scaling,
+        safe_area_inset: config.safe_area_inset,
+        tick: step,
+        ..Context::default()
+    };
+    let mut texture = RenderTexture::new(width, height);
+
+    let mut rendered = Vec::with_capacity(frames);
+    for frame in 0..frames {
+        ctx.widgets.clear();
+        inject(frame, &mut ctx.input);
+        app.update(&mut ctx, step);
+        ctx.input.end_tick();
+        ctx.windows.clear();
+        ctx.opening.clear();
+        ctx.closing.clear();
+        if ctx.quit { break }
+        if (texture.width(), texture.height()) != ctx.pixel_size {
+            texture = RenderTexture::new(ctx.pixel_size.0, ctx.pixel_size.1)
+        }
+        let mut canvas = texture.canvas();
+        app.draw(&ctx, &mut canvas, 0.0);
+        ctx.widgets.draw(&mut canvas);
+        rendered.push(texture.clone())
+    }
+    rendered
+}
+
+// Draw a frame into `frame`: straight in, or if there's a back buffer, into that and
+// then copied over in one go, so `frame` never has half a frame in it
+fn draw_frame(frame: &mut [u8], (width, height): (u32, u32), back: Option<&mut RenderTexture>, draw: impl FnOnce(&mut Canvas)) {
+    match back {
+        Some(back) => {
+            draw(&mut back.canvas());
+            frame.copy_from_slice(back.pixels())
+        }
+        None => draw(&mut Canvas::new(frame, width, height)),
+    }
+}
+
+// Everything that goes with one window: the window, the Pixels drawing into it, and
+// whatever else it takes to get the pixel buffer onto it the way its config says.
+struct OpenWindow {
+    // None for the main window, which the app doesn't need a key for
+    key: Option<WindowKey>,
+    window: Rc<Window>,
+    // Shared with the Context, for the main window
+    pixels: Rc<RefCell<Pixels>>,
+    pixel_size: (u32, u32),
+    present_mode: PresentMode,
+    scaling: ScalingMode,
+    pixel_aspect: f32,
+    clear_color: Color,
+    post: Option<PostRenderer>,
+    scaler: Option<Scaler>,
+    // The back buffer, if the app wants one; it's the same size as the frame
+    back: Option<RenderTexture>,
+    resolution_policy: ResolutionPolicy,
+    // When the window last changed size, if its buffer hasn't caught up yet
+    resized_at: Option<Instant>,
+}
+
+impl OpenWindow {
+    fn new(target: &EventLoopWindowTarget<()>, config: &WindowConfig, key: Option<WindowKey>) -> Result<Self, Error> {
+        // Sort out the icon first, so a bad one fails before anything's on the screen
+        let icon = match &config.icon {
+            Some(sprite) => Some(Icon::from_rgba(sprite.pixels().to_vec(), sprite.width(), sprite.height())?),
+            None => None,
+        };
+
+        // The window itself. We set a title, size, and a minimum size to restrict resizing.
+        // Resizing up is fine, pixels will scale; resizing down is problematic if we ever
+        // get smaller than the Pixels itself. (Unless the buffer follows the window, in
+        // which case it can be any size.)
+        let mut builder = winit::window::WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(LogicalSize { width: config.window_size.0, height: config.window_size.1 })
+            .with_window_icon(icon);
+        if config.resolution_policy == ResolutionPolicy::Fixed {
+            builder = builder.with_min_inner_size(LogicalSize { width: config.pixel_size.0, height: config.pixel_size.1 })
+        }
+        let window = Rc::new(builder.build(target).map_err(EventLoopError::from)?);
+        let pixel_size = match config.resolution_policy {
+            ResolutionPolicy::Fixed => config.pixel_size,
+            ResolutionPolicy::MatchWindow => {
+                let PhysicalSize { width, height } = window.inner_size();
+                scaling::matched_buffer_size((width, height), window.scale_factor())
+            }
+        };
+
+        // The Pixels instance. Pixels keeps its wgpu surface to itself, so there's no asking
+        // it which present modes it can do, and making a surface of our own to ask takes
+        // unsafe code, which this crate doesn't have. So we just try: if the mode isn't
+        // supported wgpu panics, and if that happens we try again with Fifo, which it has to
+        // support. (wgpu will still print the panic message, and with panic = "abort" this
+        // can't help; WindowConfig::present_mode says so.)
+        let (pixels, present_mode) =
+            match panic::catch_unwind(AssertUnwindSafe(|| build_pixels(&window, config, pixel_size, config.present_mode))) {
+                Ok(pixels) => (pixels?, config.present_mode),
+                Err(_) if config.present_mode != PresentMode::Fifo => {
+                    log::warn!("Present mode {:?} isn't supported, using Fifo", config.present_mode);
+                    (build_pixels(&window, config, pixel_size, PresentMode::Fifo)?, PresentMode::Fifo)
+                }
+                Err(panic) => panic::resume_unwind(panic),
+            };
+
+        // If there's a post effect, it needs its own texture the size of the window
+        let PhysicalSize { width, height } = window.inner_size();
+        let post = PostRenderer::new(&pixels, config.post_effect, width, height);
+        // And if Pixels can't do the scaling we want, we do it
+        let scaler = Scaler::new(&pixels, config.scaling, pixel_size, config.pixel_aspect, config.clear_color, width, height);
+        let back = config.double_buffered.then(|| RenderTexture::new(pixel_size.0, pixel_size.1));
+
+        Ok(Self {
+            key,
+            window,
+            pixels: Rc::new(RefCell::new(pixels)),
+            pixel_size,
+            present_mode,
+            scaling: config.scaling,
+            pixel_aspect: config.pixel_aspect,
+            clear_color: config.clear_color,
+            post,
+            scaler,
+            back,
+            resolution_policy: config.resolution_policy,
+            resized_at: None,
+        })
+    }
+
+    // If this window's buffer follows its size, and the size has settled since it
+    // last changed, make the buffer match. The main window's buffer belongs to the
+    // context as much as to us, so the context does that one, and we catch up at the
+    // next redraw like after resize_pixel_buffer.
+    fn match_buffer(&mut self, ctx: &mut Context) -> Result<(), Error> {
+        match self.resized_at {
+            Some(at) if at.elapsed() >= RESIZE_SETTLE => self.resized_at = None,
+            _ => return Ok(())
+        }
+        let PhysicalSize { width, height } = self.window.inner_size();
+        let (w, h) = scaling::matched_buffer_size((width, height), self.window.scale_factor());
+        if (w, h) == self.pixel_size { return Ok(()) }
+        if self.key.is_none() {
+            ctx.set_buffer_size(w, h)
+        } else {
+            {
+                let mut pixels = self.pixels.borrow_mut();
+                pixels.resize_buffer(w, h)?;
+                pixels.frame_mut().fill(0)
+            }
+            self.buffer_resized((w, h));
+            Ok(())
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        let mut pixels = self.pixels.borrow_mut();
+        pixels.resize_surface(width, height)?;
+        if let Some(post) = &mut self.post {
+            post.resize(&pixels, width, height)
+        }
+        if let Some(scaler) = &mut self.scaler {
+            scaler.resize(width, height)
+        }
+        Ok(())
+    }
+
+    // Pixels has already been told the buffer's a new size; everything else that
+    // depends on that has to catch up. The scaler's bound to the old texture, so it
+    // has to be made again.
+    fn buffer_resized(&mut self, pixel_size: (u32, u32)) {
+        self.pixel_size = pixel_size;
+        if let Some(back) = &mut self.back {
+            *back = RenderTexture::new(pixel_size.0, pixel_size.1)
+        }
+        if self.scaler.is_some() {
+            let PhysicalSize { width, height } = self.window.inner_size();
+            self.scaler = Scaler::new(&self.pixels.borrow(), self.scaling, pixel_size, self.pixel_aspect, self.clear_color, width, height)
+        }
+    }
+
+    // Get what's in the pixel buffer onto the window. This always uploads the whole
+    // buffer: Pixels does its own write_texture of all of it inside render_with,
+    // before our closure runs, and there's no way to tell it not to (or to present
+    // without going through render_with, since the surface is private). So a
+    // dirty-rect-only upload would mean uploading the changed part as well, not
+    // instead, and there's nothing to gain from the canvas's dirty rect here. That
+    // goes for the Scaler too: it could keep a texture of its own and write only
+    // the dirty part to it, but Pixels' full upload would still happen first.
+    fn render(&self) -> Result<(), Error> {
+        let mut pixels = self.pixels.borrow_mut();
+        let render = |pixels: &Pixels| match (&self.post, &self.scaler) {
+            (None, None) => pixels.render(),
+            (post, scaler) => pixels.render_with(|encoder, target, context| {
+                match post {
+                    Some(post) => post.render(encoder, target, context, scaler.as_ref()),
+                    None => scaling::render(scaler.as_ref(), encoder, target, context),
+                }
+                Ok(())
+            }),
+        };
+
+        // A lost or outdated surface (waking from sleep, or switching GPUs) just
+        // needs configuring again, at whatever size the window is now, and then
+        // one more try. Anything else, or failing twice, stops the app.
+        match render(&pixels) {
+            Err(pixels::Error::Surface(err @ (SurfaceError::Lost | SurfaceError::Outdated))) => {
+                log::debug!("Surface {:?}, reconfiguring", err);
+                let size = self.window.inner_size();
+                pixels.resize_surface(size.width, size.height)?;
+                Ok(render(&pixels)?)
+            }
+            result => Ok(result?),
+        }
+    }
+}
+
+// The Pixels instance. We need a backing surface texture the physical size of the window
+// (meaning, the real actual physical size, post-hidpi-scaling) and then we can set stuff
+// on it with a PixelsBuilder:
+fn build_pixels(window: &Window, config: &WindowConfig, pixel_size: (u32, u32), present_mode: PresentMode) -> Result<Pixels, pixels::Error> {
+    let PhysicalSize { width, height } = window.inner_size();
+    let surface_texture = SurfaceTexture::new(width, height, window);
+    PixelsBuilder::new(pixel_size.0, pixel_size.1, surface_texture)
+        .clear_color(config.clear_color.into())
+        .present_mode(present_mode.into())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_buffered_frame_is_all_or_nothing() {
+        // A draw that dies halfway through: with a back buffer, the frame still has
+        // all of the last one in it; without, it's got half of each
+        let half_then_panic = |canvas: &mut Canvas| {
+            canvas.fill_rect(0, 0, 4, 2, Color::RED);
+            panic!("halfway")
+        };
+        for double_buffered in [true, false] {
+            let mut frame = vec![0x11; 4 * 4 * 4];
+            let mut back = double_buffered.then(|| RenderTexture::new(4, 4));
+            let result = panic::catch_unwind(AssertUnwindSafe(|| draw_frame(&mut frame, (4, 4), back.as_mut(), half_then_panic)));
+            assert!(result.is_err());
+            assert_eq!(frame.iter().all(|&b| b == 0x11), double_buffered);
+        }
+    }
+
+    #[test]
+    fn double_buffered_frames_carry_over() {
+        let mut frame = vec![0; 4 * 4 * 4];
+        let mut back = RenderTexture::new(4, 4);
+        draw_frame(&mut frame, (4, 4), Some(&mut back), |canvas| canvas.set_pixel(0, 0, Color::RED));
+        draw_frame(&mut frame, (4, 4), Some(&mut back), |canvas| canvas.set_pixel(3, 3, Color::BLUE));
+        assert_eq!(frame, back.pixels());
+        assert_eq!(&frame[..4], &Color::RED.as_bytes());
+        assert_eq!(&frame[60..], &Color::BLUE.as_bytes());
+    }
+}
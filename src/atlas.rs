@@ -0,0 +1,178 @@
+// Packing loose sprites into one big one, so an app can load a pile of separate
+// images and then only hold one buffer. The packing is shelves: sprites go in
+// tallest first, left to right in rows, and a row's as tall as the first (tallest)
+// thing in it. It wastes some space over the short ones, but it's simple and it's
+// quick, and for a game's worth of sprites that's the right trade.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use crate::Sprite;
+
+/// Collects named sprites to be packed together by `build`.
+#[derive(Clone, Debug)]
+pub struct AtlasBuilder {
+    max_width: u32,
+    max_height: u32,
+    sprites: Vec<(String, Sprite)>,
+}
+
+/// The packed sprite, and where everything went in it.
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    sprite: Sprite,
+    rects: HashMap<String, (u32, u32, u32, u32)>,
+}
+
+/// Things that can go wrong packing an atlas.
+#[derive(Debug)]
+pub enum AtlasError {
+    /// There's not enough room in the biggest atlas allowed; this is the first
+    /// sprite that doesn't fit
+    DoesntFit { name: String },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::DoesntFit { name } => write!(f, "no room in the atlas for sprite {:?}", name),
+        }
+    }
+}
+
+impl Error for AtlasError {}
+
+impl AtlasBuilder {
+    /// An empty builder for an atlas that can't get any bigger than `max_width` by
+    /// `max_height`.
+    pub fn new(max_width: u32, max_height: u32) -> Self {
+        Self { max_width, max_height, sprites: vec![] }
+    }
+
+    /// Add a sprite to be packed, under `name`. Adding another one with the same
+    /// name replaces it.
+    pub fn add(&mut self, name: impl Into<String>, sprite: Sprite) {
+        let name = name.into();
+        match self.sprites.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, old)) => *old = sprite,
+            None => self.sprites.push((name, sprite)),
+        }
+    }
+
+    /// Pack everything into one sprite, only as big as it needs to be. Whatever's
+    /// not covered by a sprite is transparent.
+    pub fn build(&self) -> Result<Atlas, AtlasError> {
+        // Tallest first; sort_by_key is stable, so same-height ones stay in the order
+        // they were added, and the same sprites always pack the same way
+        let mut order: Vec<_> = self.sprites.iter().collect();
+        order.sort_by_key(|(_, sprite)| std::cmp::Reverse(sprite.height()));
+
+        let mut rects = HashMap::new();
+        let (mut x, mut y, mut shelf) = (0u32, 0u32, 0u32);
+        let (mut width, mut height) = (0u32, 0u32);
+        for (name, sprite) in &order {
+            let (w, h) = (sprite.width(), sprite.height());
+            // Off the end of this shelf, so start the next one
+            if x as u64 + w as u64 > self.max_width as u64 {
+                (x, y, shelf) = (0, y + shelf, 0);
+            }
+            if w > self.max_width || y as u64 + h as u64 > self.max_height as u64 {
+                return Err(AtlasError::DoesntFit { name: name.clone() })
+            }
+            rects.insert(name.clone(), (x, y, w, h));
+            shelf = shelf.max(h);
+            (width, height) = (width.max(x + w), height.max(y + h));
+            x += w
+        }
+
+        // Now everything has a place, copy it all there, a row at a time
+        let stride = width as usize * 4;
+        let mut data = vec![0u8; stride * height as usize];
+        for (name, sprite) in &order {
+            let (x, y, w, _) = rects[name];
+            let len = w as usize * 4;
+            for (row, src) in sprite.pixels().chunks_exact(len.max(1)).enumerate() {
+                let start = (y as usize + row) * stride + x as usize * 4;
+                data[start..start + len].copy_from_slice(src)
+            }
+        }
+        // data is exactly width * height pixels, so this can't fail
+        let sprite = Sprite::new(width, height, data).unwrap();
+        Ok(Atlas { sprite, rects })
+    }
+}
+
+impl Atlas {
+    /// Everything, packed together.
+    pub fn sprite(&self) -> &Sprite {
+        &self.sprite
+    }
+
+    /// Where the sprite that was added as `name` is in the atlas, as (x, y, width,
+    /// height), or `None` if there wasn't one.
+    pub fn rect(&self, name: &str) -> Option<(u32, u32, u32, u32)> {
+        self.rects.get(name).copied()
+    }
+
+    /// Every sprite's name and where it is, in no particular order.
+    pub fn rects(&self) -> impl Iterator<Item = (&str, (u32, u32, u32, u32))> {
+        self.rects.iter().map(|(name, &rect)| (name.as_str(), rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    // A w by h sprite all of one color
+    fn solid(w: u32, h: u32, color: Color) -> Sprite {
+        Sprite::from_fn(w, h, |_, _| color)
+    }
+
+    #[test]
+    fn three_sprites_without_overlap() {
+        let mut builder = AtlasBuilder::new(6, 10);
+        builder.add("a", solid(4, 3, Color::RED));
+        builder.add("b", solid(2, 5, Color::GREEN));
+        builder.add("c", solid(3, 2, Color::BLUE));
+        let atlas = builder.build().unwrap();
+
+        // Tallest first along a shelf, and c doesn't fit on it so starts the next
+        assert_eq!(atlas.rect("b"), Some((0, 0, 2, 5)));
+        assert_eq!(atlas.rect("a"), Some((2, 0, 4, 3)));
+        assert_eq!(atlas.rect("c"), Some((0, 5, 3, 2)));
+        assert_eq!(atlas.rect("d"), None);
+        assert_eq!((atlas.sprite().width(), atlas.sprite().height()), (6, 7));
+
+        let rects: Vec<_> = atlas.rects().map(|(_, rect)| rect).collect();
+        for (i, &(x, y, w, h)) in rects.iter().enumerate() {
+            assert!(x + w <= 6 && y + h <= 7);
+            for &(ox, oy, ow, oh) in &rects[i + 1..] {
+                assert!(x + w <= ox || ox + ow <= x || y + h <= oy || oy + oh <= y, "{:?} overlaps {:?}", (x, y, w, h), (ox, oy, ow, oh))
+            }
+        }
+
+        // Each one's pixels made it to its place, and the gaps are transparent
+        for (name, color) in [("a", Color::RED), ("b", Color::GREEN), ("c", Color::BLUE)] {
+            let (x, y, w, h) = atlas.rect(name).unwrap();
+            assert_eq!(atlas.sprite().sub_sprite(x, y, w, h), solid(w, h, color), "{}", name)
+        }
+        assert_eq!(atlas.sprite().get_pixel(5, 4), Some(Color::TRANSPARENT))
+    }
+
+    #[test]
+    fn too_big_for_the_atlas() {
+        let mut builder = AtlasBuilder::new(4, 4);
+        builder.add("wide", solid(5, 1, Color::RED));
+        assert!(matches!(builder.build(), Err(AtlasError::DoesntFit { name }) if name == "wide"));
+
+        let mut builder = AtlasBuilder::new(4, 4);
+        builder.add("one", solid(3, 3, Color::RED));
+        builder.add("two", solid(3, 2, Color::RED));
+        assert!(matches!(builder.build(), Err(AtlasError::DoesntFit { name }) if name == "two"));
+        // Replacing it with something smaller makes room
+        builder.add("two", solid(1, 1, Color::RED));
+        assert_eq!(builder.build().unwrap().rect("two"), Some((3, 0, 1, 1)))
+    }
+}
@@ -0,0 +1,134 @@
+// Sound effects, through rodio. A Sound is decoded all the way into samples up
+// front, so playing one is just handing the mixer something to read from; the
+// samples are shared, so playing the same sound twice at once costs nothing extra,
+// and the two just get mixed together.
+//
+// The output stream has to stay alive for anything to be heard, so the runner owns
+// it, and the Context gets a handle to it.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+
+/// A sound effect, decoded and ready to play with `Context::play_sound`. Cloning one
+/// is cheap, since the samples are shared.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    samples: Arc<[i16]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// Things that can go wrong making a sound.
+#[derive(Debug)]
+pub enum SoundError {
+    /// The sound couldn't be decoded: it's corrupt, or not a WAV or an OGG
+    Decode(DecoderError),
+}
+
+impl fmt::Display for SoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoundError::Decode(err) => write!(f, "couldn't decode sound: {}", err),
+        }
+    }
+}
+
+impl Error for SoundError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SoundError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<DecoderError> for SoundError {
+    fn from(err: DecoderError) -> Self {
+        SoundError::Decode(err)
+    }
+}
+
+impl Sound {
+    /// Decode a WAV or OGG Vorbis file (the whole file, like from `include_bytes!`).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SoundError> {
+        let decoder = Decoder::new(Cursor::new(bytes.to_vec()))?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        Ok(Self { samples: decoder.collect(), channels, sample_rate })
+    }
+
+    /// How long the sound is.
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() as u64 / self.channels.max(1) as u64;
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+// One play of a sound: where we're up to in its samples
+struct Playing {
+    sound: Sound,
+    next: usize,
+}
+
+impl Iterator for Playing {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.sound.samples.get(self.next).copied();
+        self.next += 1;
+        sample
+    }
+}
+
+impl Source for Playing {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.sound.samples.len().saturating_sub(self.next))
+    }
+
+    fn channels(&self) -> u16 {
+        self.sound.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.sound.duration())
+    }
+}
+
+pub(crate) struct Audio {
+    // Never looked at, but dropping it stops the sound
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl Audio {
+    /// Open the default output device. If there isn't one (or it won't open) the
+    /// app just doesn't make any noise.
+    pub(crate) fn new() -> Option<Self> {
+        match OutputStream::try_default() {
+            Ok((_stream, handle)) => Some(Self { _stream, handle }),
+            Err(err) => {
+                log::warn!("No audio: {}", err);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn handle(&self) -> OutputStreamHandle {
+        self.handle.clone()
+    }
+}
+
+// Start a sound playing on top of whatever else is
+pub(crate) fn play(handle: &OutputStreamHandle, sound: &Sound) {
+    let playing = Playing { sound: sound.clone(), next: 0 };
+    if let Err(err) = handle.play_raw(playing.convert_samples()) {
+        log::warn!("Couldn't play sound: {}", err)
+    }
+}
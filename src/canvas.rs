@@ -0,0 +1,1439 @@
+// A Canvas is a thin wrapper around the RGBA frame that Pixels hands us, so that
+// drawing code can think in terms of (x, y) instead of byte offsets. It only
+// borrows the frame, so it's meant to be made fresh every time we draw:
+// constructing one is just a couple of integers and a slice.
+//
+// The shapes all come from Surface; what's here is the part that's specific to a
+// real frame: clipping, the camera, and copying images onto it.
+
+use std::time::Duration;
+use crate::{BlendMode, Color, RenderTexture, Sprite, Surface, TileMap, Vec2f};
+use crate::color::blend_over;
+
+// The frame graph's colors: frames that made it in time, ones that didn't, and the
+// line showing where that is
+const GRAPH_OK: Color = Color::rgb(0x40, 0xc0, 0x40);
+const GRAPH_SLOW: Color = Color::rgb(0xe0, 0x40, 0x30);
+const GRAPH_TARGET: Color = Color::rgb(0xe0, 0xe0, 0xe0);
+
+// The safe area guides: something nobody's likely to be using for anything else
+const SAFE_AREA: Color = Color::rgb(0xff, 0x40, 0xff);
+
+/// A drawable view of an RGBA framebuffer, `width` by `height` pixels.
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+    // The part of the frame we're allowed to draw on: left, top, right, bottom,
+    // with the right and bottom exclusive. Always within the frame.
+    clip: (i32, i32, i32, i32),
+    // Where the top-left of the canvas is in the world; subtracted from everything
+    // we're asked to draw
+    camera: (i32, i32),
+    // The camera as it was asked for, fractions and all; camera is this rounded down
+    camera_f: Vec2f,
+    // Where (0, 0) is, before the camera moves it
+    origin: Origin,
+    // The bounding box of everything drawn since the last take_dirty_rect, in the
+    // same form as clip
+    dirty: Option<(usize, usize, usize, usize)>,
+    // How drawing combines with what's there
+    blend: BlendMode,
+    // Clip, camera, origin and blend mode as they were at each push_state
+    saved: Vec<DrawState>,
+}
+
+#[derive(Copy, Clone)]
+struct DrawState {
+    clip: (i32, i32, i32, i32),
+    camera: (i32, i32),
+    camera_f: Vec2f,
+    origin: Origin,
+    blend: BlendMode,
+}
+
+/// Where (0, 0) is on a canvas.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Origin {
+    /// The top-left pixel, with y going down
+    #[default]
+    TopLeft,
+    /// The middle of the canvas, with y still going down. With an even width or
+    /// height, there's no middle pixel, so it's the one just right of or below it.
+    Center,
+}
+
+impl<'a> Canvas<'a> {
+    /// Wrap a frame (like `pixels.frame_mut()`) that's `width` by `height` pixels.
+    /// Panics if the slice isn't exactly that many RGBA pixels long, because
+    /// nothing after this would make sense.
+    pub fn new(frame: &'a mut [u8], width: u32, height: u32) -> Self {
+        assert_eq!(frame.len(), width as usize * height as usize * 4, "Frame doesn't match canvas size");
+        Self { frame, width, height, clip: (0, 0, width as i32, height as i32), camera: (0, 0), camera_f: Vec2f::ZERO, origin: Origin::TopLeft, dirty: None, blend: BlendMode::Replace, saved: vec![] }
+    }
+
+    /// Restrict all drawing from now on to a rectangle, given as (x, y, width,
+    /// height): anything outside it is left alone, the same as anything off the
+    /// edge of the canvas. `None` goes back to drawing on the whole canvas.
+    /// `clear` ignores this, since it's about the whole frame.
+    pub fn set_clip(&mut self, rect: Option<(i32, i32, u32, u32)>) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        self.clip = match rect {
+            None => (0, 0, w as i32, h as i32),
+            Some((x, y, cw, ch)) => {
+                let left = (x as i64).clamp(0, w);
+                let top = (y as i64).clamp(0, h);
+                let right = (x as i64 + cw as i64).clamp(left, w);
+                let bottom = (y as i64 + ch as i64).clamp(top, h);
+                (left as i32, top as i32, right as i32, bottom as i32)
+            }
+        }
+    }
+
+    /// Draw in world coordinates from now on: (x, y) in the world is the top-left
+    /// of the canvas, so everything drawn is shifted left by `x` and up by `y`. For
+    /// scrolling around a world bigger than the screen. `set_camera(0, 0)` goes back
+    /// to drawing in canvas coordinates. The clip rectangle stays where it is on the
+    /// canvas; it doesn't move with the camera.
+    pub fn set_camera(&mut self, x: i32, y: i32) {
+        self.camera = (x, y);
+        self.camera_f = Vec2f::new(x as f32, y as f32)
+    }
+
+    pub fn camera(&self) -> (i32, i32) {
+        self.camera
+    }
+
+    /// Like `set_camera`, for a camera that moves by fractions of a pixel, for smooth
+    /// scrolling. Drawing still happens on whole pixels, so the camera is rounded
+    /// down (always down, even below zero, so nothing jumps as it crosses it) and
+    /// `camera` says what to. Keep the camera's position as a float yourself and
+    /// pass the whole thing in every frame: moving it 0.3 a frame, it'll move a
+    /// pixel every third or fourth frame.
+    pub fn set_camera_f(&mut self, x: f32, y: f32) {
+        self.camera_f = Vec2f::new(x, y);
+        self.camera = self.camera_f.floor().into()
+    }
+
+    /// The camera exactly as it was set, before rounding.
+    pub fn camera_f(&self) -> Vec2f {
+        self.camera_f
+    }
+
+    /// Where (0, 0) is for everything drawn from now on: the top-left of the canvas,
+    /// which is how it starts, or the middle of it. With `Center` on a 320x240
+    /// canvas, a pixel at (0, 0) lands on (160, 120), and (-160, -120) is the top
+    /// left. The camera moves things on from there, so the camera's position is
+    /// what's in the middle of the screen. The clip rectangle, `take_dirty_rect`, and
+    /// `Context::mouse_pos` all stay in top-left pixel coordinates whatever this is.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin
+    }
+
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// How everything drawn from now on combines with what's already on the canvas:
+    /// all the shapes and text, and `set_pixel`. It starts out as `Replace`. This
+    /// doesn't change `clear`, or the blits, which each say how they combine.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend = mode
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend
+    }
+
+    /// Remember the clip rectangle, camera, origin and blend mode as they are now, so they
+    /// can be put back with `pop_state`. Pushes nest, like a stack, so a bit of UI can
+    /// push, set things how it likes, draw, and pop, without caring what whoever
+    /// called it had set.
+    pub fn push_state(&mut self) {
+        self.saved.push(DrawState { clip: self.clip, camera: self.camera, camera_f: self.camera_f, origin: self.origin, blend: self.blend })
+    }
+
+    /// Put back the clip rectangle, camera, origin and blend mode from the last
+    /// `push_state`. Popping more than was pushed is a mistake, but a harmless one:
+    /// it does nothing.
+    pub fn pop_state(&mut self) {
+        if let Some(state) = self.saved.pop() {
+            DrawState { clip: self.clip, camera: self.camera, camera_f: self.camera_f, origin: self.origin, blend: self.blend } = state
+        }
+    }
+
+    /// Draw one pixel blended in with `mode`, whatever the canvas's blend mode is.
+    /// Otherwise the same as `set_pixel`: the camera and clip rectangle apply.
+    pub fn set_pixel_blended(&mut self, x: i32, y: i32, color: Color, mode: BlendMode) {
+        let (x, y) = self.to_canvas(x, y);
+        let (left, top, right, bottom) = self.clip;
+        if x >= left && x < right && y >= top && y < bottom {
+            let (x, y) = (x as usize, y as usize);
+            self.mark_dirty(x, y, x + 1, y + 1);
+            let i = (y * self.width as usize + x) * 4;
+            blend_into(&mut self.frame[i..i + 4], color, mode)
+        }
+    }
+
+    /// The smallest rectangle, as (x, y, width, height), containing every pixel
+    /// that's been drawn on since the canvas was made or this was last called, or
+    /// `None` if nothing has. Calling this starts tracking again from nothing.
+    ///
+    /// This is only a hint, for scenes that are mostly static and want to redraw
+    /// less: the rectangle can be bigger than what really changed (a sprite with
+    /// transparent edges counts as all of it), but never smaller. It doesn't make
+    /// presenting any cheaper: Pixels uploads the whole buffer to the GPU every
+    /// frame, and gives us no way to upload only part of it, so there's no
+    /// `WindowConfig` option for that.
+    pub fn take_dirty_rect(&mut self) -> Option<(i32, i32, u32, u32)> {
+        self.dirty.take().map(|(x0, y0, x1, y1)| (x0 as i32, y0 as i32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+
+    /// Copy a sprite onto the canvas with its top-left corner at (x, y). Pixels with
+    /// zero alpha are skipped, so they show whatever was there already; everything
+    /// else is copied as-is. Any part hanging off the canvas is left out.
+    pub fn blit(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        let opaque = sprite.is_opaque();
+        self.blit_rows(sprite.pixels(), sprite.width(), sprite.height(), x, y, |dst_row, src_row| {
+            if opaque {
+                // Nothing to skip, so it's just a row at a time
+                dst_row.copy_from_slice(src_row)
+            } else {
+                for (d, s) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                    if s[3] != 0 {
+                        d.copy_from_slice(s)
+                    }
+                }
+            }
+        })
+    }
+
+    /// Copy a `RenderTexture` onto the canvas with its top-left corner at (x, y),
+    /// the same way `blit` copies a sprite: pixels with zero alpha are skipped and
+    /// the rest are copied as-is.
+    pub fn blit_texture(&mut self, texture: &RenderTexture, x: i32, y: i32) {
+        self.blit_rows(texture.pixels(), texture.width(), texture.height(), x, y, |dst_row, src_row| {
+            for (d, s) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                if s[3] != 0 {
+                    d.copy_from_slice(s)
+                }
+            }
+        })
+    }
+
+    /// Like `blit`, but partly-transparent pixels are mixed with what's already on
+    /// the canvas according to their alpha, instead of replacing it. This is what
+    /// you want for sprites with soft edges; it's slower than `blit`.
+    pub fn blit_blend(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        self.blit_rows(sprite.pixels(), sprite.width(), sprite.height(), x, y, |dst_row, src_row| {
+            for (d, s) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                blend_over(d, s)
+            }
+        })
+    }
+
+    /// Like `blit`, but with every pixel of the sprite multiplied by `tint` first,
+    /// channel by channel (`sprite * tint / 255`), alpha included: a white tint
+    /// changes nothing, red makes a white sprite red, and a tint with half alpha
+    /// makes the sprite half as opaque. Pixels that end up with zero alpha are
+    /// skipped, and the rest copied, the same as `blit` does.
+    pub fn blit_tinted(&mut self, sprite: &Sprite, x: i32, y: i32, tint: Color) {
+        let tint = tint.as_bytes();
+        self.blit_rows(sprite.pixels(), sprite.width(), sprite.height(), x, y, |dst_row, src_row| {
+            for (d, s) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                let a = (s[3] as u32 * tint[3] as u32 / 255) as u8;
+                if a == 0 { continue }
+                for c in 0..3 {
+                    d[c] = (s[c] as u32 * tint[c] as u32 / 255) as u8
+                }
+                d[3] = a
+            }
+        })
+    }
+
+    /// Like `blit`, but every sprite pixel becomes a `scale` by `scale` block, for
+    /// drawing pixel art bigger without blurring it. Scale 0 draws nothing.
+    pub fn blit_scaled(&mut self, sprite: &Sprite, x: i32, y: i32, scale: u32) {
+        match scale {
+            0 => return,
+            1 => return self.blit(sprite, x, y),
+            _ => {}
+        }
+        let (dw, dh) = (sprite.width().saturating_mul(scale), sprite.height().saturating_mul(scale));
+        let (x, y) = self.to_canvas(x, y);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, dw, dh) else { return };
+        let (scale, sw, w) = (scale as i64, sprite.width() as usize, self.width as usize);
+        self.mark_dirty(x0, y0, x1, y1);
+        let src = sprite.pixels();
+
+        for dy in y0..y1 {
+            let sy = ((dy as i64 - y as i64) / scale) as usize;
+            for dx in x0..x1 {
+                let sx = ((dx as i64 - x as i64) / scale) as usize;
+                let from = (sy * sw + sx) * 4;
+                if src[from + 3] != 0 {
+                    let to = (dy * w + dx) * 4;
+                    self.frame[to..to + 4].copy_from_slice(&src[from..from + 4])
+                }
+            }
+        }
+    }
+
+    /// Like `blit`, but mirrored left-to-right (`flip_h`) and/or top-to-bottom
+    /// (`flip_v`), so one sprite can face both ways.
+    pub fn blit_flipped(&mut self, sprite: &Sprite, x: i32, y: i32, flip_h: bool, flip_v: bool) {
+        if !flip_h && !flip_v {
+            return self.blit(sprite, x, y)
+        }
+        let (x, y) = self.to_canvas(x, y);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, sprite.width(), sprite.height()) else { return };
+        let (sw, sh, w) = (sprite.width() as usize, sprite.height() as usize, self.width as usize);
+        self.mark_dirty(x0, y0, x1, y1);
+        let src = sprite.pixels();
+
+        for dy in y0..y1 {
+            let sy = (dy as i64 - y as i64) as usize;
+            let sy = if flip_v { sh - 1 - sy } else { sy };
+            for dx in x0..x1 {
+                let sx = (dx as i64 - x as i64) as usize;
+                let sx = if flip_h { sw - 1 - sx } else { sx };
+                let from = (sy * sw + sx) * 4;
+                if src[from + 3] != 0 {
+                    let to = (dy * w + dx) * 4;
+                    self.frame[to..to + 4].copy_from_slice(&src[from..from + 4])
+                }
+            }
+        }
+    }
+
+    /// Draw a sprite stretched to fill `dst_rect` (x, y, width, height) without
+    /// stretching its edges, which is how dialog frames and buttons get drawn at any
+    /// size from one small image. `border` is how wide the edges are on each side,
+    /// as (left, top, right, bottom): the corners are drawn as they are, the edges
+    /// are stretched along their length, and the middle is stretched both ways.
+    /// Transparent pixels are skipped, like `blit`. If the destination's too small
+    /// for the borders, the left and top ones win; if the borders leave no middle
+    /// in the sprite, the middle of the destination is left alone.
+    pub fn blit_nine_slice(&mut self, sprite: &Sprite, dst_rect: (i32, i32, u32, u32), border: (u32, u32, u32, u32)) {
+        let (x, y, dw, dh) = dst_rect;
+        let (left, top, right, bottom) = border;
+        let (x, y) = self.to_canvas(x, y);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, dw, dh) else { return };
+        self.mark_dirty(x0, y0, x1, y1);
+        let (src, w) = (sprite.pixels(), self.width as usize);
+
+        // Every row needs the same columns, so they're only worked out once
+        let cols: Vec<_> = (x0..x1)
+            .map(|dx| nine_slice_source(dx as i64 - x as i64, dw, sprite.width(), left, right))
+            .collect();
+        for dy in y0..y1 {
+            let Some(sy) = nine_slice_source(dy as i64 - y as i64, dh, sprite.height(), top, bottom) else { continue };
+            for (dx, sx) in (x0..x1).zip(&cols) {
+                let Some(sx) = sx else { continue };
+                let from = (sy * sprite.width() as usize + sx) * 4;
+                if src[from + 3] != 0 {
+                    let to = (dy * w + dx) * 4;
+                    self.frame[to..to + 4].copy_from_slice(&src[from..from + 4])
+                }
+            }
+        }
+    }
+
+    /// Draw a sprite turned `angle` radians clockwise about its middle, with the
+    /// middle at (cx, cy); at an angle of zero that's the same as
+    /// `blit(sprite, cx - width / 2, cy - height / 2)`. Every pixel covered is
+    /// copied from whichever sprite pixel lands on it (no smoothing, so it stays
+    /// crisp), and like `blit`, transparent pixels are skipped.
+    pub fn blit_rotated(&mut self, sprite: &Sprite, cx: i32, cy: i32, angle: f64) {
+        let (sw, sh) = (sprite.width() as f64, sprite.height() as f64);
+        if sprite.width() == 0 || sprite.height() == 0 || !angle.is_finite() { return }
+        let (cx, cy) = self.to_canvas(cx, cy);
+        // The pivot, on the canvas, where the sprite's middle would be unrotated
+        let px = cx as f64 - (sprite.width() / 2) as f64 + sw / 2.0;
+        let py = cy as f64 - (sprite.height() / 2) as f64 + sh / 2.0;
+        let (sin, cos) = angle.sin_cos();
+
+        // The box around the turned corners is all that could be covered
+        let corners = [(-sw / 2.0, -sh / 2.0), (sw / 2.0, -sh / 2.0), (-sw / 2.0, sh / 2.0), (sw / 2.0, sh / 2.0)]
+            .map(|(x, y)| (px + x * cos - y * sin, py + x * sin + y * cos));
+        let left = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).floor();
+        let top = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).floor();
+        let right = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).ceil();
+        let bottom = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).ceil();
+        let clamp = |v: f64| v.clamp(i32::MIN as f64, i32::MAX as f64);
+        let (x, y) = (clamp(left) as i32, clamp(top) as i32);
+        let (w, h) = (clamp(right - left).max(0.0) as u32, clamp(bottom - top).max(0.0) as u32);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, w, h) else { return };
+        self.mark_dirty(x0, y0, x1, y1);
+        let (src, w) = (sprite.pixels(), self.width as usize);
+
+        // Go over the canvas pixels, and turn each one back the other way to find
+        // which sprite pixel it comes from. A sprite whose width and height aren't
+        // both odd or both even, turned a right angle, does land on edges; the
+        // nudge makes sure those all go the same way, despite rounding error in
+        // sin and cos, so no row or column gets read twice (or skipped).
+        const NUDGE: f64 = 1e-9;
+        for dy in y0..y1 {
+            let ry = dy as f64 + 0.5 - py;
+            for dx in x0..x1 {
+                let rx = dx as f64 + 0.5 - px;
+                let sx = (rx * cos + ry * sin + sw / 2.0 + NUDGE).floor();
+                let sy = (-rx * sin + ry * cos + sh / 2.0 + NUDGE).floor();
+                if sx < 0.0 || sy < 0.0 || sx >= sw || sy >= sh { continue }
+                let from = (sy as usize * sprite.width() as usize + sx as usize) * 4;
+                if src[from + 3] != 0 {
+                    let to = (dy * w + dx) * 4;
+                    self.frame[to..to + 4].copy_from_slice(&src[from..from + 4])
+                }
+            }
+        }
+    }
+
+    /// Draw a tile map, with the map's top-left corner `camera_x` pixels left of
+    /// and `camera_y` pixels above the canvas's (the canvas's own camera moves it
+    /// too). Only the tiles that can be seen are looked at, so a huge map costs no
+    /// more than a small one. Empty tiles are skipped, and tiles are blitted like
+    /// sprites, so transparent parts of them are too. For smooth scrolling, leave
+    /// these at zero and use `set_camera_f`: the tiles and where they go both come
+    /// from the rounded-down camera, so they never disagree.
+    pub fn draw_tilemap(&mut self, map: &TileMap, camera_x: i32, camera_y: i32) {
+        let (tw, th) = map.tile_size();
+        if tw == 0 || th == 0 { return }
+
+        // Which tiles are under the visible part of the canvas, in map pixels
+        let (left, top, right, bottom) = self.bounds();
+        let (left, top) = (left as i64 + camera_x as i64, top as i64 + camera_y as i64);
+        let (right, bottom) = (right as i64 + camera_x as i64, bottom as i64 + camera_y as i64);
+        let (tw, th) = (tw as i64, th as i64);
+        let col0 = left.div_euclid(tw).max(0);
+        let row0 = top.div_euclid(th).max(0);
+        let col1 = (right + tw - 1).div_euclid(tw).min(map.width() as i64);
+        let row1 = (bottom + th - 1).div_euclid(th).min(map.height() as i64);
+
+        for row in row0..row1 {
+            for col in col0..col1 {
+                let Some(tile) = map.get(col as u32, row as u32) else { continue };
+                if tile == TileMap::EMPTY { continue }
+                let Some(sprite) = map.atlas().frame_at(tile as usize) else { continue };
+                let x = (col * tw - camera_x as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                let y = (row * th - camera_y as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                self.blit(sprite, x, y)
+            }
+        }
+    }
+
+    /// Blur what's already on the canvas in a rectangle, (x, y, width, height), by
+    /// averaging each pixel with the ones up to `radius` away: across, then down,
+    /// which comes out the same as averaging the whole square around it. Only the
+    /// part of the rectangle inside the clip changes, and nothing from outside it is
+    /// blurred in; past its edges, the edge pixels count again instead, so edges
+    /// don't fade toward black. Blurring a radius of 0 does nothing.
+    pub fn box_blur(&mut self, rect: (i32, i32, u32, u32), radius: u32) {
+        if radius == 0 { return }
+        let (x, y, w, h) = rect;
+        let (x, y) = self.to_canvas(x, y);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, w, h) else { return };
+        self.mark_dirty(x0, y0, x1, y1);
+        let (rw, rh, fw) = (x1 - x0, y1 - y0, self.width as usize);
+
+        // The blur reads pixels it's already written, so it works on a copy of the
+        // region, which goes back into the frame once it's done
+        let mut region: Vec<[u8; 4]> = Vec::with_capacity(rw * rh);
+        for row in y0..y1 {
+            let bytes = &self.frame[(row * fw + x0) * 4..(row * fw + x1) * 4];
+            region.extend(bytes.chunks_exact(4).map(|px| [px[0], px[1], px[2], px[3]]))
+        }
+        let mut line = vec![];
+        for row in 0..rh {
+            blur_line(&mut region, row * rw, 1, rw, radius as usize, &mut line)
+        }
+        for col in 0..rw {
+            blur_line(&mut region, col, rw, rh, radius as usize, &mut line)
+        }
+        for (row, pixels) in (y0..y1).zip(region.chunks_exact(rw)) {
+            let bytes = &mut self.frame[(row * fw + x0) * 4..(row * fw + x1) * 4];
+            for (dst, src) in bytes.chunks_exact_mut(4).zip(pixels) {
+                dst.copy_from_slice(src)
+            }
+        }
+    }
+
+    /// Copy what's already in a rectangle of the canvas, `src` as (x, y, width,
+    /// height), so its top-left corner goes to `dst`: for scrolling what's been
+    /// drawn instead of drawing it again, or shaking the screen. The two can
+    /// overlap, and it comes out as though the whole rectangle were picked up
+    /// before any of it was put down. The pixels are copied as they are, whatever
+    /// the blend mode. Whatever part of `src` is off the canvas isn't copied, and
+    /// only the part of the destination inside the clip rectangle changes.
+    pub fn copy_region(&mut self, src: (i32, i32, u32, u32), dst: (i32, i32)) {
+        let (sx, sy, w, h) = src;
+        let (sx, sy) = self.to_canvas(sx, sy);
+        let (dx, dy) = self.to_canvas(dst.0, dst.1);
+        // How far everything moves, which is the same for whatever's left of the
+        // rectangle once it's been cut down to fit
+        let (mx, my) = (dx as i64 - sx as i64, dy as i64 - sy as i64);
+
+        // Cut the source down to the canvas, then move it and cut it down to the clip
+        let (fw, fh) = (self.width as i64, self.height as i64);
+        let (x0, y0) = ((sx as i64).max(0), (sy as i64).max(0));
+        let (x1, y1) = ((sx as i64 + w as i64).min(fw), (sy as i64 + h as i64).min(fh));
+        let (left, top, right, bottom) = self.clip;
+        let (x0, y0) = ((x0 + mx).max(left as i64), (y0 + my).max(top as i64));
+        let (x1, y1) = ((x1 + mx).min(right as i64), (y1 + my).min(bottom as i64));
+        if x0 >= x1 || y0 >= y1 { return }
+        self.mark_dirty(x0 as usize, y0 as usize, x1 as usize, y1 as usize);
+
+        // Rows moving down get copied from the bottom up, so none is overwritten
+        // before it's been copied; within a row, copy_within takes care of it
+        let len = (x1 - x0) as usize * 4;
+        let copy_row = |frame: &mut [u8], row: i64| {
+            let to = ((row * fw + x0) * 4) as usize;
+            let from = (((row - my) * fw + x0 - mx) * 4) as usize;
+            frame.copy_within(from..from + len, to)
+        };
+        if my > 0 {
+            (y0..y1).rev().for_each(|row| copy_row(self.frame, row))
+        } else {
+            (y0..y1).for_each(|row| copy_row(self.frame, row))
+        }
+    }
+
+    /// Draw a line every `spacing` pixels across the canvas, both ways, for level
+    /// editors and such. The lines are where x or y is a multiple of `spacing` in the
+    /// world, so they scroll with the camera. A faint grid is a low-alpha color with
+    /// the blend mode set to `Alpha`. A spacing of zero draws nothing.
+    pub fn draw_grid(&mut self, spacing: u32, color: Color) {
+        if spacing == 0 { return }
+        let (left, top, right, bottom) = self.bounds();
+        let (w, h) = (right - left, bottom - top);
+        for x in grid_lines(left, right, spacing) {
+            self.vline(x, top, h, color)
+        }
+        for y in grid_lines(top, bottom, spacing) {
+            self.hline(left, y, w, color)
+        }
+    }
+
+    /// Fill the canvas with squares `cell` pixels across, alternating between `a`
+    /// and `b`, like the backdrop image editors put behind transparent things. The
+    /// square with its corner at the world's (0, 0) is `a`, and like `draw_grid` it
+    /// all scrolls with the camera. A cell size of zero draws nothing.
+    pub fn draw_checkerboard(&mut self, cell: u32, a: Color, b: Color) {
+        if cell == 0 { return }
+        let (left, top, right, bottom) = self.bounds();
+        let size = cell as i64;
+        let (col0, row0) = ((left as i64).div_euclid(size), (top as i64).div_euclid(size));
+        let (col1, row1) = ((right as i64 - 1).div_euclid(size), (bottom as i64 - 1).div_euclid(size));
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                let color = if (row + col).rem_euclid(2) == 0 { a } else { b };
+                // The squares at the edges only need the part that's showing
+                let x = (col * size).max(left as i64) as i32;
+                let y = (row * size).max(top as i64) as i32;
+                let w = ((col + 1) * size).min(right as i64) as i32 - x;
+                let h = ((row + 1) * size).min(bottom as i64) as i32 - y;
+                self.fill_rect(x, y, w as u32, h as u32, color)
+            }
+        }
+    }
+
+    /// Outline the safe area (from `Context::safe_area`) and draw a little cross at
+    /// the middle of the canvas, to check a layout stays inside it. These go on the
+    /// canvas as such, whatever the camera, origin or clip rectangle are.
+    pub fn draw_safe_area_guides(&mut self, safe_area: (i32, i32, u32, u32)) {
+        self.push_state();
+        self.set_camera(0, 0);
+        self.set_origin(Origin::TopLeft);
+        self.set_clip(None);
+        self.set_blend_mode(BlendMode::Replace);
+        let (x, y, w, h) = safe_area;
+        self.draw_rect(x, y, w, h, SAFE_AREA);
+        let (cx, cy) = ((self.width / 2) as i32, (self.height / 2) as i32);
+        self.hline(cx - 2, cy, 5, SAFE_AREA);
+        self.vline(cx, cy - 2, 5, SAFE_AREA);
+        self.pop_state()
+    }
+
+    /// Draw a text caret: a bar one pixel wide and `height` tall, with its top at
+    /// (x, y). To put it after some text drawn at (tx, ty), that's
+    /// `tx + measure_text(text).0` across; see `Caret` for making it blink.
+    pub fn draw_caret(&mut self, x: i32, y: i32, height: u32, color: Color) {
+        self.vline(x, y, height.min(i32::MAX as u32) as i32, color)
+    }
+
+    /// Draw a smooth line from (x0, y0) to (x1, y1), ends included, for diagrams and
+    /// such where `draw_line`'s stairsteps look wrong. It's Xiaolin Wu's: along the
+    /// line, each step covers two pixels across it, each by however much of the line
+    /// falls on it, and they're alpha blended by that much (whatever the canvas's
+    /// blend mode). The ends are on pixel centers, so they're fully covered, and a
+    /// line that's exactly horizontal, vertical or diagonal comes out the same as
+    /// `draw_line` with an opaque color.
+    pub fn draw_line_aa(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let (left, top, right, bottom) = self.bounds();
+        if (x0 < left && x1 < left) || (y0 < top && y1 < top) || (x0 >= right && x1 >= right) || (y0 >= bottom && y1 >= bottom) {
+            return
+        }
+
+        // Walk along whichever way the line's longer, a pixel at a time. Only the
+        // part of that walk that's on the canvas matters.
+        let steep = (y1 as i64 - y0 as i64).abs() > (x1 as i64 - x0 as i64).abs();
+        let (a0, b0, a1, b1) = if steep { (y0, x0, y1, x1) } else { (x0, y0, x1, y1) };
+        let ((a0, b0), (a1, b1)) = if a0 <= a1 { ((a0, b0), (a1, b1)) } else { ((a1, b1), (a0, b0)) };
+        let gradient = if a0 == a1 { 0.0 } else { (b1 as f64 - b0 as f64) / (a1 as f64 - a0 as f64) };
+        let (low, high) = if steep { (top, bottom) } else { (left, right) };
+
+        for a in a0.max(low)..=a1.min(high - 1) {
+            let b = b0 as f64 + gradient * (a as f64 - a0 as f64);
+            let (near, coverage) = (b.floor(), b - b.floor());
+            for (b, coverage) in [(near, 1.0 - coverage), (near + 1.0, coverage)] {
+                let alpha = (color.a as f64 * coverage).round() as u8;
+                if alpha == 0 { continue }
+                let b = b.clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+                let (x, y) = if steep { (b, a) } else { (a, b) };
+                self.set_pixel_blended(x, y, Color { a: alpha, ..color }, BlendMode::Alpha)
+            }
+        }
+    }
+
+    /// Plot how long recent frames took, as bars in a (x, y, width, height)
+    /// rectangle, one pixel wide each, oldest on the left and newest at the right
+    /// edge; if there are more than fit, the oldest are left off. The rectangle goes
+    /// up to twice `target`, with a line across the middle at `target` itself, and
+    /// bars over it are drawn red instead of green (longer frames just fill the
+    /// height). Only the bars and the line are drawn, so fill the rectangle first
+    /// for a background. `Context::frame_times` is the history to give it.
+    pub fn draw_frame_graph(&mut self, rect: (i32, i32, u32, u32), history: &[Duration], target: Duration) {
+        let (x, y, w, h) = rect;
+        if w == 0 || h == 0 { return }
+        let full = target.saturating_mul(2).max(Duration::from_nanos(1)).as_secs_f64();
+        let bottom = y as i64 + h as i64;
+        let shown = &history[history.len().saturating_sub(w as usize)..];
+        let left = x as i64 + w as i64 - shown.len() as i64;
+
+        for (i, &time) in shown.iter().enumerate() {
+            let len = ((time.as_secs_f64() / full * h as f64).round() as i64).clamp(0, h as i64);
+            let color = if time > target { GRAPH_SLOW } else { GRAPH_OK };
+            let bar_x = (left + i as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+            let bar_y = (bottom - len).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+            self.vline(bar_x, bar_y, len as i32, color)
+        }
+        let line_y = (bottom - h as i64 / 2 - 1).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.hline(x, line_y, w.min(i32::MAX as u32) as i32, GRAPH_TARGET)
+    }
+
+    // The clipping part of blitting: work out which part of the image (RGBA rows,
+    // src_width by src_height) is visible, and hand f each visible row of the frame
+    // along with the image row that goes there.
+    fn blit_rows(&mut self, src: &[u8], src_width: u32, src_height: u32, x: i32, y: i32, mut f: impl FnMut(&mut [u8], &[u8])) {
+        let (x, y) = self.to_canvas(x, y);
+        let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, src_width, src_height) else { return };
+        // Where in the image the visible part starts
+        let (sx, sy) = ((x0 as i64 - x as i64) as usize, (y0 as i64 - y as i64) as usize);
+        let (sw, w, len) = (src_width as usize, self.width as usize, (x1 - x0) * 4);
+        self.mark_dirty(x0, y0, x1, y1);
+
+        for row in 0..(y1 - y0) {
+            let from = ((sy + row) * sw + sx) * 4;
+            let to = ((y0 + row) * w + x0) * 4;
+            f(&mut self.frame[to..to + len], &src[from..from + len])
+        }
+    }
+
+    // Turn world coordinates into canvas coordinates. Anything far enough off the
+    // canvas for this to overflow is nowhere near being drawn anyway.
+    fn to_canvas(&self, x: i32, y: i32) -> (i32, i32) {
+        let (ox, oy) = self.offset();
+        (x.saturating_sub(ox), y.saturating_sub(oy))
+    }
+
+    // Where the canvas's top-left pixel is in the world: the camera, less however
+    // far the origin is from the top-left
+    fn offset(&self) -> (i32, i32) {
+        let (cx, cy) = self.camera;
+        match self.origin {
+            Origin::TopLeft => (cx, cy),
+            Origin::Center => (cx.saturating_sub((self.width / 2) as i32), cy.saturating_sub((self.height / 2) as i32)),
+        }
+    }
+
+    // Intersect a rectangle with the clip rectangle (and so the canvas), returning
+    // the visible part as (left, top, right, bottom) with the right and bottom edges
+    // exclusive. None if there's nothing left.
+    fn clip_rect(&self, x: i32, y: i32, w: u32, h: u32) -> Option<(usize, usize, usize, usize)> {
+        let (left, top, right, bottom) = self.clip;
+        let x0 = (x as i64).max(left as i64);
+        let y0 = (y as i64).max(top as i64);
+        let x1 = (x as i64 + w as i64).min(right as i64);
+        let y1 = (y as i64 + h as i64).min(bottom as i64);
+        if x0 < x1 && y0 < y1 {
+            Some((x0 as usize, y0 as usize, x1 as usize, y1 as usize))
+        } else {
+            None
+        }
+    }
+
+    // Grow the dirty rectangle to cover (x0, y0) up to (x1, y1), exclusive
+    fn mark_dirty(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        self.dirty = Some(match self.dirty {
+            None => (x0, y0, x1, y1),
+            Some((a, b, c, d)) => (a.min(x0), b.min(y0), c.max(x1), d.max(y1)),
+        })
+    }
+
+    // Fill the pixels from x0 up to (not including) x1 on row y, which the caller
+    // has already clipped to the canvas.
+    fn fill_span(&mut self, x0: usize, x1: usize, y: usize, color: Color) {
+        let row = y * self.width as usize;
+        let bytes = color.as_bytes();
+        self.mark_dirty(x0, y, x1, y + 1);
+        for px in self.frame[(row + x0) * 4..(row + x1) * 4].chunks_exact_mut(4) {
+            match self.blend {
+                BlendMode::Replace => px.copy_from_slice(&bytes),
+                mode => blend_into(px, color, mode),
+            }
+        }
+    }
+
+    // Byte offset of a pixel in the frame, if it's on the canvas at all
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            None
+        } else {
+            Some((y as usize * self.width as usize + x as usize) * 4)
+        }
+    }
+}
+
+impl Surface for Canvas<'_> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+        self.set_pixel_blended(x, y, color, self.blend)
+    }
+
+    fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+        let (x, y) = self.to_canvas(x, y);
+        self.index(x, y).map(|i| {
+            let mut px = [0u8; 4];
+            px.copy_from_slice(&self.frame[i..i + 4]);
+            px.into()
+        })
+    }
+
+    // The clip rectangle, moved to where it is in the world
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        let (left, top, right, bottom) = self.clip;
+        let (cx, cy) = self.offset();
+        (left.saturating_add(cx), top.saturating_add(cy), right.saturating_add(cx), bottom.saturating_add(cy))
+    }
+
+    fn fill_row(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+        let (x, y) = self.to_canvas(x0, y);
+        let len = (x1 as i64 - x0 as i64).clamp(0, u32::MAX as i64) as u32;
+        if let Some((x0, y0, x1, _)) = self.clip_rect(x, y, len, 1) {
+            self.fill_span(x0, x1, y0, color)
+        }
+    }
+
+    // Clearing ignores the clip rectangle, and it's quicker to do the whole frame
+    // as bytes
+    fn clear(&mut self, color: Color) {
+        let bytes = color.as_bytes();
+        self.mark_dirty(0, 0, self.width as usize, self.height as usize);
+        if bytes.iter().all(|&b| b == bytes[0]) {
+            // Black, white, and fully-transparent black are all a memset
+            self.frame.fill(bytes[0])
+        } else if !self.frame.is_empty() {
+            // Otherwise write one pixel and keep doubling the filled part, so it's a
+            // handful of big memcpys instead of a loop over every pixel
+            self.frame[..4].copy_from_slice(&bytes);
+            let mut filled = 4;
+            while filled < self.frame.len() {
+                let n = filled.min(self.frame.len() - filled);
+                self.frame.copy_within(..n, filled);
+                filled += n
+            }
+        }
+    }
+
+    // Quicker on the bytes too
+    fn flood_fill(&mut self, x: i32, y: i32, color: Color) {
+        let (x, y) = self.to_canvas(x, y);
+        let (left, top, right, bottom) = self.clip;
+        if x < left || x >= right || y < top || y >= bottom {
+            return
+        }
+        let (left, top, right, bottom) = (left as usize, top as usize, right as usize, bottom as usize);
+
+        let width = self.width as usize;
+        let i = (y as usize * width + x as usize) * 4;
+        let mut target = [0u8; 4];
+        target.copy_from_slice(&self.frame[i..i + 4]);
+        // If filling wouldn't change anything (which, blending, can happen even
+        // with a different color), the filled pixels would still be the target and
+        // we'd go around forever
+        if self.blend.blend(target.into(), color).as_bytes() == target {
+            return
+        }
+        let is_target = |frame: &[u8], x: usize, y: usize| {
+            let i = (y * width + x) * 4;
+            frame[i..i + 4] == target
+        };
+
+        // A scanline fill: pop a seed, fill the whole run of target pixels it's in,
+        // then push one seed for each run touching it in the rows above and below.
+        // Filled pixels stop being the target color, so nothing gets visited twice.
+        let mut stack = vec![(x as usize, y as usize)];
+        while let Some((x, y)) = stack.pop() {
+            if !is_target(self.frame, x, y) {
+                continue
+            }
+            let (mut x0, mut x1) = (x, x + 1);
+            while x0 > left && is_target(self.frame, x0 - 1, y) {
+                x0 -= 1
+            }
+            while x1 < right && is_target(self.frame, x1, y) {
+                x1 += 1
+            }
+            self.fill_span(x0, x1, y, color);
+
+            for row in [y.wrapping_sub(1), y + 1] {
+                if row < top || row >= bottom {
+                    continue
+                }
+                let mut in_run = false;
+                for col in x0..x1 {
+                    let here = is_target(self.frame, col, row);
+                    if here && !in_run {
+                        stack.push((col, row))
+                    }
+                    in_run = here
+                }
+            }
+        }
+    }
+}
+
+// Draw one color onto the RGBA pixel `dst` with a blend mode
+fn blend_into(dst: &mut [u8], color: Color, mode: BlendMode) {
+    let mut old = [0u8; 4];
+    old.copy_from_slice(dst);
+    dst.copy_from_slice(&mode.blend(old.into(), color).as_bytes())
+}
+
+// For nine-slicing: which column (or row) of a sprite `size` across goes at `pos` in a
+// destination `len` across, keeping `start` pixels at the start and `end` at the end
+// as they are and stretching the rest. None if there's no rest to stretch.
+fn nine_slice_source(pos: i64, len: u32, size: u32, start: u32, end: u32) -> Option<usize> {
+    let (len, size) = (len as i64, size as i64);
+    // The ends can't be any more than the whole sprite
+    let start = (start as i64).min(size);
+    let end = (end as i64).min(size - start);
+    if pos < start {
+        Some(pos as usize)
+    } else if pos >= len - end {
+        Some((size - (len - pos)) as usize)
+    } else if size - start - end == 0 {
+        None
+    } else {
+        Some((start + (pos - start) * (size - start - end) / (len - start - end)) as usize)
+    }
+}
+
+// One pass of box_blur: blur the `len` pixels in `pixels` starting at `start` and
+// `stride` apart, with `line` to keep the unblurred ones in. The window slides along
+// the line, so it's one add and one subtract per pixel however big the radius is.
+fn blur_line(pixels: &mut [[u8; 4]], start: usize, stride: usize, len: usize, radius: usize, line: &mut Vec<[u8; 4]>) {
+    line.clear();
+    line.extend((0..len).map(|i| pixels[start + i * stride]));
+    let last = len - 1;
+    let n = 2 * radius as u64 + 1;
+    for c in 0..4 {
+        let at = |i: usize| line[i.min(last)][c] as u64;
+        // The window around the first pixel: everything left of it is it again,
+        // and anything past the end is the last one again
+        let mut sum = at(0) * (radius as u64 + 1)
+            + (1..=radius.min(last)).map(at).sum::<u64>()
+            + at(last) * radius.saturating_sub(last) as u64;
+        for i in 0..len {
+            pixels[start + i * stride][c] = ((sum + n / 2) / n) as u8;
+            sum = sum + at(i + radius + 1) - at(i.saturating_sub(radius))
+        }
+    }
+}
+
+// The multiples of spacing from start up to (not including) end
+fn grid_lines(start: i32, end: i32, spacing: u32) -> impl Iterator<Item = i32> {
+    let spacing = spacing as i64;
+    let first = (start as i64 + spacing - 1).div_euclid(spacing) * spacing;
+    (first..end as i64).step_by(spacing as usize).map(|n| n as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A w by h frame that starts out transparent
+    fn frame(w: u32, h: u32) -> Vec<u8> {
+        vec![0; w as usize * h as usize * 4]
+    }
+
+    // Every pixel of a frame, as colors
+    fn pixels(frame: &[u8]) -> impl Iterator<Item = Color> + '_ {
+        frame.chunks_exact(4).map(|px| Color::rgba(px[0], px[1], px[2], px[3]))
+    }
+
+    // Something to draw, for tests that try a few of them
+    type Draw = Box<dyn Fn(&mut Canvas)>;
+
+    // Every pixel of a canvas that isn't `background`, and what it is
+    fn changed(canvas: &Canvas, background: Color) -> Vec<((i32, i32), Color)> {
+        let (w, h) = (canvas.width() as i32, canvas.height() as i32);
+        (0..h).flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter_map(|(x, y)| canvas.get_pixel(x, y).filter(|&c| c != background).map(|c| ((x, y), c)))
+            .collect()
+    }
+
+    // Every pixel in a (x, y, w, h) rectangle
+    fn rect_pixels((x, y, w, h): (i32, i32, u32, u32)) -> Vec<(i32, i32)> {
+        (y..y + h as i32).flat_map(|py| (x..x + w as i32).map(move |px| (px, py))).collect()
+    }
+
+    // Just where they are
+    fn changed_at(canvas: &Canvas, background: Color) -> Vec<(i32, i32)> {
+        changed(canvas, background).into_iter().map(|(pos, _)| pos).collect()
+    }
+
+    // A camera and a spacing, and the columns and rows the lines should be on
+    type GridCase = ((i32, i32), u32, &'static [i32], &'static [i32]);
+
+    #[test]
+    fn frame_graph_in_time_has_no_warnings() {
+        let mut buf = frame(20, 10);
+        let target = Duration::from_micros(16_667);
+        let history: Vec<_> = (0..30).map(|i| Duration::from_millis(i % 17)).collect();
+        Canvas::new(&mut buf, 20, 10).draw_frame_graph((0, 0, 20, 10), &history, target);
+        assert!(pixels(&buf).any(|c| c == GRAPH_OK));
+        assert!(pixels(&buf).all(|c| c != GRAPH_SLOW));
+    }
+
+    #[test]
+    fn frame_graph_slow_frames() {
+        let mut buf = frame(4, 10);
+        let history = [Duration::from_millis(12), Duration::from_millis(30)];
+        Canvas::new(&mut buf, 4, 10).draw_frame_graph((0, 0, 4, 10), &history, Duration::from_millis(20));
+        let canvas = Canvas::new(&mut buf, 4, 10);
+        // The newest is on the right: 30ms out of 40 is 8 pixels, 12ms is 3 (with the
+        // target line across the middle of both)
+        assert_eq!(canvas.get_pixel(3, 2), Some(GRAPH_SLOW));
+        assert_eq!(canvas.get_pixel(3, 1), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.get_pixel(2, 9), Some(GRAPH_OK));
+        assert_eq!(canvas.get_pixel(2, 6), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.get_pixel(0, 4), Some(GRAPH_TARGET));
+    }
+
+    #[test]
+    fn frame_graph_huge_target() {
+        let mut buf = frame(4, 4);
+        Canvas::new(&mut buf, 4, 4).draw_frame_graph((0, 0, 4, 4), &[Duration::MAX, Duration::ZERO], Duration::MAX);
+        assert!(pixels(&buf).all(|c| c != GRAPH_SLOW));
+    }
+
+    // Each draw on its own, and then all of them on the same canvas: every pixel
+    // that changed is inside the dirty rectangle
+    #[test]
+    fn dirty_rect_bounds_the_changes() {
+        let sprite = Sprite::new(2, 2, vec![0xff; 16]).unwrap();
+        let draws: Vec<Draw> = vec![
+            Box::new(|c| c.set_pixel(3, 4, Color::RED)),
+            Box::new(|c| c.fill_rect(-5, 10, 9, 3, Color::GREEN)),
+            Box::new(|c| c.draw_line(30, 1, 20, 9, Color::BLUE)),
+            Box::new(|c| c.draw_circle(30, 30, 4, Color::WHITE)),
+            Box::new(|c| c.draw_line_aa(2, 20, 13, 26, Color::YELLOW)),
+            Box::new(move |c| c.blit(&sprite, 15, 15)),
+            Box::new(|c| {
+                c.set_camera(-8, -8);
+                c.fill_rect(0, 0, 2, 2, Color::CYAN)
+            }),
+            Box::new(|c| c.copy_region((0, 8, 8, 8), (20, 20))),
+        ];
+
+        let check = |before: &[u8], after: &[u8], rect: Option<(i32, i32, u32, u32)>| {
+            for (i, (a, b)) in before.chunks_exact(4).zip(after.chunks_exact(4)).enumerate() {
+                if a == b { continue }
+                let (x, y) = ((i % 32) as i32, (i / 32) as i32);
+                let (rx, ry, rw, rh) = rect.expect("something changed");
+                assert!(x >= rx && y >= ry && x < rx + rw as i32 && y < ry + rh as i32, "({}, {}) outside {:?}", x, y, rect)
+            }
+        };
+
+        let mut buf = frame(32, 32);
+        for draw in &draws {
+            let before = buf.clone();
+            let mut canvas = Canvas::new(&mut buf, 32, 32);
+            draw(&mut canvas);
+            let rect = canvas.take_dirty_rect();
+            assert_eq!(canvas.take_dirty_rect(), None);
+            check(&before, &buf, rect)
+        }
+
+        let (before, mut buf) = (frame(32, 32), frame(32, 32));
+        let mut canvas = Canvas::new(&mut buf, 32, 32);
+        for draw in &draws {
+            draw(&mut canvas)
+        }
+        let rect = canvas.take_dirty_rect();
+        check(&before, &buf, rect)
+    }
+
+    #[test]
+    fn dirty_rect_starts_empty() {
+        let mut buf = frame(8, 8);
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        assert_eq!(canvas.take_dirty_rect(), None);
+        // Off the canvas changes nothing, so it isn't dirty either
+        canvas.fill_rect(-4, -4, 4, 4, Color::RED);
+        canvas.draw_line(20, 0, 20, 7, Color::RED);
+        assert_eq!(canvas.take_dirty_rect(), None);
+        canvas.set_pixel(5, 2, Color::RED);
+        canvas.set_pixel(1, 6, Color::RED);
+        assert_eq!(canvas.take_dirty_rect(), Some((1, 2, 5, 5)));
+    }
+
+    #[test]
+    fn blit_off_the_top_left() {
+        // Numbered pixels, with a transparent one in the middle
+        let numbered = Sprite::from_fn(3, 3, |x, y| match (x, y) {
+            (1, 1) => Color::TRANSPARENT,
+            _ => Color::rgb(x as u8 + 1, y as u8 + 1, 0),
+        });
+        let mut buf = frame(4, 4);
+        let mut canvas = Canvas::new(&mut buf, 4, 4);
+        canvas.clear(Color::GRAY);
+        canvas.blit(&numbered, -1, -1);
+        assert_eq!(changed(&canvas, Color::GRAY), vec![
+            ((1, 0), Color::rgb(3, 2, 0)),
+            ((0, 1), Color::rgb(2, 3, 0)),
+            ((1, 1), Color::rgb(3, 3, 0)),
+        ]);
+    }
+
+    #[test]
+    fn blend_half_red_over_blue() {
+        let red = Sprite::from_fn(3, 3, |_, _| Color::rgba(0xff, 0, 0, 0x80));
+        let mut buf = frame(3, 3);
+        let mut canvas = Canvas::new(&mut buf, 3, 3);
+        canvas.clear(Color::BLUE);
+        canvas.blit_blend(&red, 0, 0);
+        assert_eq!(canvas.get_pixel(1, 1), Some(Color::rgba(0x80, 0, 0x7f, 0xff)));
+
+        // Fully transparent changes nothing, fully opaque replaces
+        canvas.blit_blend(&Sprite::from_fn(1, 1, |_, _| Color::TRANSPARENT), 0, 0);
+        canvas.blit_blend(&Sprite::from_fn(1, 1, |_, _| Color::GREEN), 2, 2);
+        assert_eq!(canvas.get_pixel(0, 0), Some(Color::rgba(0x80, 0, 0x7f, 0xff)));
+        assert_eq!(canvas.get_pixel(2, 2), Some(Color::GREEN));
+    }
+
+    #[test]
+    fn clip_big_fill() {
+        let mut buf = frame(8, 8);
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        canvas.set_clip(Some((2, 3, 3, 2)));
+        canvas.fill_rect(-10, -10, 100, 100, Color::RED);
+        assert_eq!(changed_at(&canvas, Color::TRANSPARENT), rect_pixels((2, 3, 3, 2)));
+
+        // Everything else is held to it too
+        canvas.clear(Color::TRANSPARENT);
+        canvas.draw_line(0, 0, 7, 7, Color::RED);
+        canvas.blit(&Sprite::from_fn(8, 8, |_, _| Color::RED), 0, 0);
+        canvas.draw_text(0, 0, "#####", Color::RED);
+        canvas.set_pixel(0, 0, Color::RED);
+        assert!(changed_at(&canvas, Color::TRANSPARENT).iter().all(|p| rect_pixels((2, 3, 3, 2)).contains(p)));
+
+        // Hanging off the canvas, it's cut down to what's on it; None is all of it
+        canvas.clear(Color::TRANSPARENT);
+        canvas.set_clip(Some((6, -5, 10, 7)));
+        canvas.fill_rect(0, 0, 8, 8, Color::RED);
+        assert_eq!(changed_at(&canvas, Color::TRANSPARENT), rect_pixels((6, 0, 2, 2)));
+        canvas.set_clip(None);
+        canvas.fill_rect(0, 0, 8, 8, Color::RED);
+        assert_eq!(changed_at(&canvas, Color::TRANSPARENT), rect_pixels((0, 0, 8, 8)));
+    }
+
+    #[test]
+    fn blit_scaled_by_three() {
+        let corners = [Color::RED, Color::GREEN, Color::BLUE, Color::TRANSPARENT];
+        let small = Sprite::from_fn(2, 2, |x, y| corners[(y * 2 + x) as usize]);
+        let mut buf = frame(8, 8);
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        canvas.clear(Color::GRAY);
+        canvas.blit_scaled(&small, 1, 1, 3);
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = match (x, y) {
+                    // Each sprite pixel is a 3x3 block; the transparent one leaves the
+                    // gray showing, the same as outside the 6x6
+                    (1..=6, 1..=6) => match corners[((y - 1) / 3 * 2 + (x - 1) / 3) as usize] {
+                        Color::TRANSPARENT => Color::GRAY,
+                        c => c,
+                    },
+                    _ => Color::GRAY,
+                };
+                assert_eq!(canvas.get_pixel(x, y), Some(expected), "({}, {})", x, y)
+            }
+        }
+    }
+
+    #[test]
+    fn blit_flipped_reverses_rows() {
+        let strip = Sprite::from_fn(3, 2, |x, y| Color::rgb(x as u8 + 1, y as u8 + 1, 0xff));
+        let mut buf = frame(3, 2);
+        Canvas::new(&mut buf, 3, 2).blit_flipped(&strip, 0, 0, true, false);
+        // Each row's pixels backwards, but each pixel's bytes still in RGBA order
+        for (row, flipped) in strip.pixels().chunks_exact(12).zip(buf.chunks_exact(12)) {
+            let reversed: Vec<u8> = row.chunks_exact(4).rev().flatten().copied().collect();
+            assert_eq!(flipped, reversed.as_slice())
+        }
+
+        let mut buf = frame(3, 2);
+        Canvas::new(&mut buf, 3, 2).blit_flipped(&strip, 0, 0, false, true);
+        assert_eq!(&buf[..12], &strip.pixels()[12..]);
+        let mut buf = frame(3, 2);
+        Canvas::new(&mut buf, 3, 2).blit_flipped(&strip, 0, 0, false, false);
+        assert_eq!(buf.as_slice(), strip.pixels());
+    }
+
+    #[test]
+    fn camera_moves_the_drawing() {
+        for (cx, cy) in [(0, 0), (3, 1), (-2, -4), (10, 10)] {
+            let mut buf = frame(12, 12);
+            let mut canvas = Canvas::new(&mut buf, 12, 12);
+            canvas.set_camera(cx, cy);
+            canvas.fill_rect(5, 6, 3, 2, Color::RED);
+            assert_eq!(canvas.camera(), (cx, cy));
+            // Reading goes through the camera too, so look without it
+            canvas.set_camera(0, 0);
+            let expected: Vec<_> = rect_pixels((5 - cx, 6 - cy, 3, 2)).into_iter().filter(|&(x, y)| (0..12).contains(&x) && (0..12).contains(&y)).collect();
+            assert_eq!(changed_at(&canvas, Color::TRANSPARENT), expected, "camera at ({}, {})", cx, cy)
+        }
+    }
+
+    #[test]
+    fn camera_and_clip() {
+        // The clip stays put on the canvas while the camera moves
+        let mut buf = frame(8, 8);
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        canvas.set_clip(Some((0, 0, 4, 4)));
+        canvas.set_camera(2, 2);
+        canvas.fill_rect(0, 0, 8, 8, Color::RED);
+        canvas.set_camera(0, 0);
+        assert_eq!(changed_at(&canvas, Color::TRANSPARENT), rect_pixels((0, 0, 4, 4)));
+    }
+
+    #[test]
+    fn blit_rotated_right_angles() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        for n in [3, 4] {
+            let numbered = Sprite::from_fn(n, n, |x, y| Color::rgb(x as u8 + 1, y as u8 + 1, 0));
+            let (c, corner) = (5, 5 - (n / 2) as i32);
+            let draw = |f: &dyn Fn(&mut Canvas)| {
+                let mut buf = frame(10, 10);
+                f(&mut Canvas::new(&mut buf, 10, 10));
+                buf
+            };
+
+            assert_eq!(draw(&|canvas| canvas.blit_rotated(&numbered, c, c, 0.0)), draw(&|canvas| canvas.blit(&numbered, corner, corner)));
+            // Half a turn is flipping both ways
+            assert_eq!(draw(&|canvas| canvas.blit_rotated(&numbered, c, c, PI)), draw(&|canvas| canvas.blit_flipped(&numbered, corner, corner, true, true)));
+            // A quarter turn clockwise: the left column becomes the top row, bottom
+            // pixel first
+            let turned = Sprite::from_fn(n, n, |x, y| numbered.get_pixel(y, n - 1 - x).unwrap());
+            assert_eq!(draw(&|canvas| canvas.blit_rotated(&numbered, c, c, FRAC_PI_2)), draw(&|canvas| canvas.blit(&turned, corner, corner)));
+        }
+    }
+
+    #[test]
+    fn tilemap_at_a_camera_offset() {
+        use crate::SpriteSheet;
+        // Four 2x2 tiles, each a solid color, on a 3x3 map with a hole in it
+        let shades = [Color::RED, Color::GREEN, Color::BLUE, Color::WHITE];
+        let atlas = SpriteSheet::new(&Sprite::from_fn(8, 2, |x, _| shades[x as usize / 2]), 2, 2);
+        let mut map = TileMap::new(3, 3, 2, 2, atlas);
+        for (row, col) in (0..3).flat_map(|r| (0..3).map(move |c| (r, c))) {
+            map.set(col, row, ((col + row) % 4) as u16)
+        }
+        map.set(1, 1, TileMap::EMPTY);
+
+        for (cx, cy) in [(0, 0), (1, 3), (3, 3), (-3, -1), (10, 0)] {
+            let mut buf = frame(4, 4);
+            let mut canvas = Canvas::new(&mut buf, 4, 4);
+            canvas.draw_tilemap(&map, cx, cy);
+            // Each canvas pixel is one map pixel, and only the map's tiles are drawn
+            let expected: Vec<_> = (0..4).flat_map(|y| (0..4).map(move |x| (x, y))).filter_map(|(x, y)| {
+                let (mx, my) = (x + cx, y + cy);
+                if !(0..6).contains(&mx) || !(0..6).contains(&my) || (mx / 2, my / 2) == (1, 1) { return None }
+                Some(((x, y), shades[((mx / 2 + my / 2) % 4) as usize]))
+            }).collect();
+            assert_eq!(changed(&canvas, Color::TRANSPARENT), expected, "camera at ({}, {})", cx, cy)
+        }
+    }
+
+    #[test]
+    fn huge_tilemap_far_along() {
+        use crate::SpriteSheet;
+        // A million tiles across, seen from near the far end: only the last couple
+        // are on the canvas, and they land where they should
+        let atlas = SpriteSheet::new(&Sprite::from_fn(2, 2, |_, _| Color::RED), 2, 2);
+        let mut map = TileMap::new(1_000_000, 1, 2, 2, atlas);
+        map.tiles_mut().fill(0);
+        let mut buf = frame(8, 2);
+        let mut canvas = Canvas::new(&mut buf, 8, 2);
+        canvas.draw_tilemap(&map, 1_999_997, 0);
+        let lit: Vec<i32> = changed(&canvas, Color::TRANSPARENT).into_iter().filter(|&((_, y), _)| y == 0).map(|((x, _), _)| x).collect();
+        assert_eq!(lit, vec![0, 1, 2]);
+        assert_eq!(canvas.take_dirty_rect(), Some((0, 0, 3, 2)))
+    }
+
+    #[test]
+    fn grid_lines_for_a_spacing() {
+        let cases: [GridCase; 4] = [
+            ((0, 0), 4, &[0, 4, 8], &[0, 4]),
+            ((1, 0), 4, &[3, 7], &[0, 4]),
+            ((-2, 5), 3, &[2, 5, 8], &[1, 4]),
+            ((0, 0), 20, &[0], &[0]),
+        ];
+        for (camera, spacing, cols, rows) in cases {
+            let mut buf = frame(10, 7);
+            let mut canvas = Canvas::new(&mut buf, 10, 7);
+            canvas.set_camera(camera.0, camera.1);
+            canvas.draw_grid(spacing, Color::WHITE);
+            canvas.set_camera(0, 0);
+            let expected: Vec<_> = (0..7).flat_map(|y| (0..10).map(move |x| (x, y)))
+                .filter(|(x, y)| cols.contains(x) || rows.contains(y))
+                .collect();
+            assert_eq!(changed_at(&canvas, Color::TRANSPARENT), expected, "camera {:?}, spacing {}", camera, spacing)
+        }
+
+        let mut buf = frame(4, 4);
+        Canvas::new(&mut buf, 4, 4).draw_grid(0, Color::WHITE);
+        assert!(buf.iter().all(|&b| b == 0))
+    }
+
+    #[test]
+    fn nine_slice_corners_and_edges() {
+        let numbered = Sprite::from_fn(3, 3, |x, y| Color::rgb(x as u8 + 1, y as u8 + 1, 0));
+        // Which sprite column or row a destination one comes from, with one-pixel
+        // borders on a destination `len` long
+        let source = |d: i32, len: i32| if d == 0 { 0 } else if d == len - 1 { 2 } else { 1 };
+
+        for (w, h) in [(6, 5), (3, 3), (2, 2)] {
+            let mut buf = frame(8, 7);
+            let mut canvas = Canvas::new(&mut buf, 8, 7);
+            canvas.blit_nine_slice(&numbered, (1, 1, w, h), (1, 1, 1, 1));
+            let (w, h) = (w as i32, h as i32);
+            let expected: Vec<_> = (1..1 + h).flat_map(|y| (1..1 + w).map(move |x| (x, y)))
+                .map(|(x, y)| ((x, y), numbered.get_pixel(source(x - 1, w), source(y - 1, h)).unwrap()))
+                .collect();
+            assert_eq!(changed(&canvas, Color::TRANSPARENT), expected, "{}x{}", w, h)
+        }
+
+        // Too small for both borders: the left and top ones win
+        let mut buf = frame(2, 2);
+        let mut canvas = Canvas::new(&mut buf, 2, 2);
+        canvas.blit_nine_slice(&numbered, (0, 0, 1, 1), (1, 1, 1, 1));
+        assert_eq!(changed(&canvas, Color::TRANSPARENT), vec![((0, 0), numbered.get_pixel(0, 0).unwrap())])
+    }
+
+    #[test]
+    fn nested_push_and_pop() {
+        let mut buf = frame(8, 8);
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        // Fill everything and see which pixels the clip let through
+        let clipped_to = |canvas: &mut Canvas| {
+            canvas.clear(Color::TRANSPARENT);
+            canvas.fill_rect(0, 0, 8, 8, Color::WHITE);
+            changed_at(canvas, Color::TRANSPARENT)
+        };
+
+        canvas.set_clip(Some((1, 1, 6, 6)));
+        canvas.push_state();
+        canvas.set_clip(Some((2, 2, 2, 2)));
+        canvas.set_camera(3, 4);
+        canvas.set_blend_mode(BlendMode::Add);
+        canvas.push_state();
+        canvas.set_clip(None);
+        canvas.set_origin(Origin::Center);
+        canvas.set_camera_f(0.5, 0.5);
+
+        canvas.pop_state();
+        assert_eq!((canvas.camera(), canvas.blend_mode(), canvas.origin()), ((3, 4), BlendMode::Add, Origin::TopLeft));
+        canvas.set_camera(0, 0);
+        assert_eq!(clipped_to(&mut canvas), rect_pixels((2, 2, 2, 2)));
+
+        canvas.pop_state();
+        assert_eq!((canvas.camera(), canvas.blend_mode()), ((0, 0), BlendMode::default()));
+        assert_eq!(clipped_to(&mut canvas), rect_pixels((1, 1, 6, 6)));
+
+        // Nothing left to pop: nothing changes
+        canvas.pop_state();
+        assert_eq!(clipped_to(&mut canvas), rect_pixels((1, 1, 6, 6)));
+    }
+
+    #[test]
+    fn camera_f_moves_a_pixel_at_a_time() {
+        let mut buf = frame(4, 4);
+        let mut canvas = Canvas::new(&mut buf, 4, 4);
+        // 0.3 px a frame, the way a game would keep it
+        let mut x = 0.0f32;
+        let mut cameras = vec![];
+        for _ in 0..10 {
+            canvas.set_camera_f(x, -x);
+            cameras.push(canvas.camera());
+            x += 0.3
+        }
+        let xs: Vec<i32> = cameras.iter().map(|c| c.0).collect();
+        let ys: Vec<i32> = cameras.iter().map(|c| c.1).collect();
+        assert_eq!(xs, vec![0, 0, 0, 0, 1, 1, 1, 2, 2, 2]);
+        // Rounded down below zero too, so it doesn't stick at 0 crossing it
+        assert_eq!(ys, vec![0, -1, -1, -1, -2, -2, -2, -3, -3, -3]);
+        assert_eq!(canvas.camera_f(), Vec2f::new(x - 0.3, 0.3 - x));
+    }
+
+    #[test]
+    fn box_blur_spreads_a_pixel_evenly() {
+        let mut buf = frame(7, 7);
+        let mut canvas = Canvas::new(&mut buf, 7, 7);
+        canvas.clear(Color::BLACK);
+        canvas.set_pixel(3, 3, Color::WHITE);
+        canvas.box_blur((0, 0, 7, 7), 1);
+
+        // The 3x3 square around it all comes out the same gray, and nothing past it
+        // changes
+        let gray = canvas.get_pixel(3, 3).unwrap();
+        assert!(gray.r > 0 && gray.r < 0x40 && gray.r == gray.g && gray.g == gray.b && gray.a == 0xff, "{:?}", gray);
+        let lit = changed(&canvas, Color::BLACK);
+        let expected: Vec<_> = rect_pixels((2, 2, 3, 3)).into_iter().map(|p| (p, gray)).collect();
+        assert_eq!(lit, expected);
+
+        // Radius 0 does nothing
+        canvas.box_blur((0, 0, 7, 7), 0);
+        assert_eq!(changed(&canvas, Color::BLACK), expected)
+    }
+
+    #[test]
+    fn box_blur_is_symmetric() {
+        let mut buf = frame(9, 9);
+        let mut canvas = Canvas::new(&mut buf, 9, 9);
+        canvas.clear(Color::BLACK);
+        canvas.set_pixel(4, 4, Color::WHITE);
+        canvas.box_blur((0, 0, 9, 9), 2);
+        for (x, y) in rect_pixels((0, 0, 9, 9)) {
+            let c = canvas.get_pixel(x, y);
+            // Mirrored both ways, and across the diagonal
+            assert_eq!(c, canvas.get_pixel(8 - x, y));
+            assert_eq!(c, canvas.get_pixel(x, 8 - y));
+            assert_eq!(c, canvas.get_pixel(y, x));
+        }
+        assert_ne!(canvas.get_pixel(2, 2), Some(Color::BLACK));
+        assert_eq!(canvas.get_pixel(1, 4), Some(Color::BLACK))
+    }
+
+    #[test]
+    fn both_origins() {
+        // (origin, camera, where the rectangle should land)
+        for (origin, camera, rect) in [
+            (Origin::TopLeft, (0, 0), (0, 0, 1, 1)),
+            (Origin::Center, (0, 0), (3, 3, 3, 2)),
+            (Origin::Center, (1, -1), (2, 4, 3, 2)),
+        ] {
+            let mut buf = frame(10, 8);
+            let mut canvas = Canvas::new(&mut buf, 10, 8);
+            canvas.set_origin(origin);
+            canvas.set_camera(camera.0, camera.1);
+            canvas.fill_rect(-2, -1, 3, 2, Color::RED);
+            assert_eq!(canvas.take_dirty_rect(), Some(rect));
+            canvas.set_origin(Origin::TopLeft);
+            canvas.set_camera(0, 0);
+            assert_eq!(changed_at(&canvas, Color::TRANSPARENT), rect_pixels(rect), "{:?} at {:?}", origin, camera)
+        }
+    }
+
+    #[test]
+    fn copy_region_overlapping() {
+        let numbered = |x: i32, y: i32| Color::rgb(x as u8 + 1, y as u8 + 1, 0);
+        // Up two, down two, left one and diagonally, all overlapping themselves
+        for (src, dst) in [((1, 2, 4, 5), (1, 0)), ((1, 0, 4, 5), (1, 2)), ((1, 1, 4, 4), (0, 1)), ((0, 0, 4, 5), (1, 1))] {
+            let mut buf = frame(6, 8);
+            let mut canvas = Canvas::new(&mut buf, 6, 8);
+            for (x, y) in rect_pixels((0, 0, 6, 8)) {
+                canvas.set_pixel(x, y, numbered(x, y))
+            }
+            canvas.copy_region(src, dst);
+
+            // Everything in the destination came from where it was before the copy
+            let (mx, my) = (dst.0 - src.0, dst.1 - src.1);
+            let moved = rect_pixels((dst.0, dst.1, src.2, src.3));
+            for (x, y) in rect_pixels((0, 0, 6, 8)) {
+                let expected = if moved.contains(&(x, y)) { numbered(x - mx, y - my) } else { numbered(x, y) };
+                assert_eq!(canvas.get_pixel(x, y), Some(expected), "({}, {}) copying {:?} to {:?}", x, y, src, dst)
+            }
+        }
+    }
+
+    #[test]
+    fn aa_line_straight_and_shallow() {
+        // Horizontal, vertical and diagonal: nothing fractional, so the same as the
+        // crisp line
+        for (x0, y0, x1, y1) in [(1, 3, 8, 3), (4, 0, 4, 6), (1, 1, 6, 6), (8, 3, 1, 3)] {
+            let (mut aa, mut crisp) = (frame(10, 8), frame(10, 8));
+            Canvas::new(&mut aa, 10, 8).draw_line_aa(x0, y0, x1, y1, Color::WHITE);
+            Canvas::new(&mut crisp, 10, 8).draw_line(x0, y0, x1, y1, Color::WHITE);
+            assert!(aa == crisp, "({}, {}) to ({}, {})", x0, y0, x1, y1)
+        }
+
+        // A shallow diagonal has partly covered pixels either side of the line, and
+        // the ends are solid
+        let mut buf = frame(10, 8);
+        let mut canvas = Canvas::new(&mut buf, 10, 8);
+        canvas.clear(Color::BLACK);
+        canvas.draw_line_aa(0, 1, 9, 4, Color::WHITE);
+        assert_eq!((canvas.get_pixel(0, 1), canvas.get_pixel(9, 4)), (Some(Color::WHITE), Some(Color::WHITE)));
+        let changed = changed(&canvas, Color::BLACK);
+        let partly = changed.iter().filter(|(_, c)| *c != Color::WHITE).count();
+        assert!(partly >= 6, "{:?}", changed);
+        // Coverage across each column adds up to about one pixel's worth
+        for x in 1..9 {
+            let total: u32 = (0..8).map(|y| canvas.get_pixel(x, y).unwrap().r as u32).sum();
+            assert!((250..=260).contains(&total), "column {}: {}", x, total)
+        }
+    }
+
+    #[test]
+    fn blit_tinted_red_and_white() {
+        let white = Sprite::from_fn(3, 2, |x, _| if x == 1 { Color::TRANSPARENT } else { Color::WHITE });
+        let (mut tinted, mut plain) = (frame(6, 6), frame(6, 6));
+        let mut canvas = Canvas::new(&mut tinted, 6, 6);
+        canvas.blit_tinted(&white, -1, 2, Color::RED);
+        // Red where the sprite's white, the hole still a hole, and clipped like blit
+        let expected: Vec<_> = [(1, 2), (1, 3)].into_iter().map(|p| (p, Color::RED)).collect();
+        assert_eq!(changed(&canvas, Color::TRANSPARENT), expected);
+
+        // White changes nothing
+        Canvas::new(&mut tinted, 6, 6).blit_tinted(&white, 2, 2, Color::WHITE);
+        let mut canvas = Canvas::new(&mut plain, 6, 6);
+        canvas.blit_tinted(&white, -1, 2, Color::RED);
+        canvas.blit(&white, 2, 2);
+        assert!(tinted == plain);
+
+        // A tint with no alpha draws nothing at all
+        let mut buf = frame(6, 6);
+        Canvas::new(&mut buf, 6, 6).blit_tinted(&white, 0, 0, Color::rgba(0xff, 0xff, 0xff, 0));
+        assert!(buf.iter().all(|&b| b == 0))
+    }
+}
@@ -0,0 +1,97 @@
+// The blinking for a text cursor. Like Animation, it's only the timing: it says
+// whether the caret's showing, and Canvas::draw_caret draws it. Feeding it the dt
+// from App::update keeps it on the same clock as everything else, so it blinks at
+// the same speed however fast frames are being drawn.
+
+use std::time::Duration;
+
+/// Whether a text caret is showing, flipping every `interval`. Call `advance` every
+/// update and `reset` whenever something's typed, so the caret's solid while the
+/// typing's happening, the way text boxes usually are.
+#[derive(Clone, Debug)]
+pub struct Caret {
+    interval: Duration,
+    // How long it's been showing (or hidden) for
+    elapsed: Duration,
+    visible: bool,
+}
+
+impl Caret {
+    /// How long most desktops leave a caret on, or off, for.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(530);
+
+    /// A caret that starts out showing, and flips every `interval`. An interval of
+    /// zero never blinks: it just stays on.
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, elapsed: Duration::ZERO, visible: true }
+    }
+
+    /// Move the blink along by `dt`. If that covers more than one interval, it
+    /// flips once for each.
+    pub fn advance(&mut self, dt: Duration) {
+        if self.interval.is_zero() { return }
+        self.elapsed += dt;
+        while self.elapsed >= self.interval {
+            self.elapsed -= self.interval;
+            self.visible = !self.visible
+        }
+    }
+
+    /// Show the caret, and start the interval over.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.visible = true
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Default for Caret {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn toggles_every_interval() {
+        let mut caret = Caret::new(MS * 100);
+        // Ticking 30 ms at a time: off at 120, on at 210, off at 300
+        let shown: Vec<bool> = (0..10).map(|_| { caret.advance(MS * 30); caret.is_visible() }).collect();
+        assert_eq!(shown, vec![true, true, true, false, false, false, true, true, true, false]);
+
+        // One long update flips it once per interval covered: twice here, and the
+        // leftover counts toward the next
+        let mut caret = Caret::new(MS * 100);
+        caret.advance(MS * 250);
+        assert!(caret.is_visible());
+        caret.advance(MS * 50);
+        assert!(!caret.is_visible())
+    }
+
+    #[test]
+    fn reset_and_zero_interval() {
+        let mut caret = Caret::new(MS * 100);
+        caret.advance(MS * 150);
+        assert!(!caret.is_visible());
+        // Typing shows it, and it stays a whole interval
+        caret.reset();
+        assert!(caret.is_visible());
+        caret.advance(MS * 99);
+        assert!(caret.is_visible());
+        caret.advance(MS);
+        assert!(!caret.is_visible());
+
+        let mut steady = Caret::new(Duration::ZERO);
+        steady.advance(Duration::from_secs(10));
+        assert!(steady.is_visible());
+        assert_eq!(Caret::default().interval, Caret::DEFAULT_INTERVAL)
+    }
+}
@@ -0,0 +1,388 @@
+// Colors are four bytes, in the same order Pixels wants them in the frame: red,
+// green, blue, alpha. They're straight (not premultiplied) alpha, and alpha 0xff
+// means opaque.
+
+use std::error::Error;
+use std::fmt;
+
+/// An RGBA color, one byte per channel.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(0xff, 0xff, 0xff);
+    pub const GRAY: Color = Color::rgb(0x80, 0x80, 0x80);
+    pub const RED: Color = Color::rgb(0xff, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 0xff, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 0xff);
+    pub const YELLOW: Color = Color::rgb(0xff, 0xff, 0);
+    pub const CYAN: Color = Color::rgb(0, 0xff, 0xff);
+    pub const MAGENTA: Color = Color::rgb(0xff, 0, 0xff);
+
+    /// An opaque color
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xff }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parse a color written the way web pages do: `#RGB`, `#RGBA`, `#RRGGBB` or
+    /// `#RRGGBBAA`, in hex, either case, with or without the `#`. Without an alpha,
+    /// it's opaque; in the short forms each digit counts twice, so `#f80` is
+    /// `#ff8800`.
+    pub fn from_hex(hex: &str) -> Result<Color, ColorParseError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::NotHex(bad))
+        }
+        // All ASCII now, so every char is a byte
+        let digit = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap();
+        let pair = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+        match digits.len() {
+            3 | 4 => {
+                let short = |i: usize| digit(i) * 0x11;
+                let a = if digits.len() == 4 { short(3) } else { 0xff };
+                Ok(Color::rgba(short(0), short(1), short(2), a))
+            }
+            6 | 8 => {
+                let a = if digits.len() == 8 { pair(3) } else { 0xff };
+                Ok(Color::rgba(pair(0), pair(1), pair(2), a))
+            }
+            len => Err(ColorParseError::WrongLength(len)),
+        }
+    }
+
+    /// The color as `#RRGGBBAA`, in lowercase hex, which `from_hex` reads back.
+    pub fn to_hex_string(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+
+    /// The four bytes you'd write into the frame for this color
+    pub const fn as_bytes(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// The color `t` of the way from `a` to `b`, each channel (alpha too) on its
+    /// own: 0.0 is `a`, 1.0 is `b`, and anything outside that is clamped to it.
+    pub fn lerp(a: Color, b: Color, t: f32) -> Color {
+        let t = clamp_unit(t);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::rgba(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+    }
+
+    /// An opaque color from hue (in degrees), saturation and value (both 0 to 1).
+    /// Hue goes around the circle, so 360 is red again, and so is -360; saturation
+    /// and value outside 0..1 get clamped (and a NaN counts as 0).
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let (h, s, v) = (finite_or_zero(h).rem_euclid(360.0), clamp_unit(s), clamp_unit(v));
+
+        // Which sixth of the color wheel we're in, and how far through it
+        let sector = h / 60.0;
+        let frac = sector.fract();
+        let (p, q, t) = (v * (1.0 - s), v * (1.0 - s * frac), v * (1.0 - s * (1.0 - frac)));
+        let (r, g, b) = match sector as u32 {
+            0 => (v, t, p),
+            1 => (q, v, p),
+            2 => (p, v, t),
+            3 => (p, q, v),
+            4 => (t, p, v),
+            5 => (v, p, q),
+            // rem_euclid can round up to exactly 360.0, which is just red again
+            _ => (v, t, p),
+        };
+        Self::rgb(to_byte(r), to_byte(g), to_byte(b))
+    }
+
+    /// This color's hue (degrees, 0 up to 360), saturation, and value (both 0 to
+    /// 1), ignoring alpha. Grays don't really have a hue; they get 0.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        if delta == 0.0 {
+            return (0.0, 0.0, max)
+        }
+
+        let h = if max == r {
+            60.0 * ((g - b) / delta)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (h.rem_euclid(360.0), delta / max, max)
+    }
+}
+
+/// Why a string isn't a color, from `Color::from_hex`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColorParseError {
+    /// There weren't 3, 4, 6 or 8 digits (not counting the `#`); this is how many
+    /// there were
+    WrongLength(usize),
+    /// This character isn't a hex digit
+    NotHex(char),
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorParseError::WrongLength(len) => write!(f, "expected 3, 4, 6 or 8 hex digits, got {}", len),
+            ColorParseError::NotHex(c) => write!(f, "{:?} isn't a hex digit", c),
+        }
+    }
+}
+
+impl Error for ColorParseError {}
+
+/// How a color being drawn combines with the color already there.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum BlendMode {
+    /// The new color replaces the old one, alpha and all.
+    #[default]
+    Replace,
+    /// Normal transparency ("source over"): the new color is mixed in according to
+    /// its alpha.
+    Alpha,
+    /// The new color is added to the old, each channel stopping at 255. Brightens
+    /// things, for glows, fire, and light. Black adds nothing.
+    Add,
+    /// Each channel of the old color is scaled by the new one, `old * new / 255`.
+    /// Darkens things, for shadows and tinting. White changes nothing.
+    Multiply,
+}
+
+impl BlendMode {
+    /// What drawing `src` over `dst` in this mode comes out as. Except for
+    /// `Replace`, the new color's alpha says how much of the effect to apply, and
+    /// the alpha of the result is the same as `Alpha` would give.
+    pub fn blend(self, dst: Color, src: Color) -> Color {
+        let mut out = dst.as_bytes();
+        match self {
+            BlendMode::Replace => return src,
+            BlendMode::Alpha => {
+                blend_over(&mut out, &src.as_bytes());
+                return out.into()
+            }
+            BlendMode::Add | BlendMode::Multiply => {
+                let (s, a) = (src.as_bytes(), src.a as u32);
+                for c in 0..3 {
+                    let d = out[c] as u32;
+                    out[c] = if self == BlendMode::Add {
+                        (d + s[c] as u32 * a / 255).min(255)
+                    } else {
+                        (d * (255 - a) + d * s[c] as u32 / 255 * a) / 255
+                    } as u8
+                }
+                out[3] = (a + dst.a as u32 * (255 - a) / 255) as u8
+            }
+        }
+        out.into()
+    }
+}
+
+// Source-over compositing of one RGBA pixel onto another, in integer math: each
+// channel is (src * a + dst * (255 - a)) / 255, where a is the source alpha.
+pub(crate) fn blend_over(dst: &mut [u8], src: &[u8]) {
+    let a = src[3] as u32;
+    match a {
+        0 => {}
+        0xff => dst.copy_from_slice(src),
+        _ => {
+            for c in 0..3 {
+                dst[c] = ((src[c] as u32 * a + dst[c] as u32 * (255 - a)) / 255) as u8
+            }
+            dst[3] = (a + dst[3] as u32 * (255 - a) / 255) as u8
+        }
+    }
+}
+
+fn finite_or_zero(x: f32) -> f32 {
+    if x.is_finite() { x } else { 0.0 }
+}
+
+fn clamp_unit(x: f32) -> f32 {
+    if x.is_nan() { 0.0 } else { x.clamp(0.0, 1.0) }
+}
+
+fn to_byte(x: f32) -> u8 {
+    (x * 255.0).round() as u8
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        color.as_bytes()
+    }
+}
+
+// Pixels wants its clear color as a wgpu color, which is floats. They're linear,
+// though, and our bytes are sRGB (the pixel buffer is an sRGB texture, so that's how
+// the GPU reads it), so going straight from one to the other would make the border
+// around the buffer come out brighter than the same color drawn inside it.
+impl From<Color> for pixels::wgpu::Color {
+    fn from(color: Color) -> Self {
+        Self {
+            r: srgb_to_linear(color.r),
+            g: srgb_to_linear(color.g),
+            b: srgb_to_linear(color.b),
+            a: color.a as f64 / 255.0,
+        }
+    }
+}
+
+// The sRGB transfer function, backwards: a byte as stored to how bright it really
+// is, from 0 to 1. Alpha isn't a brightness, so it never goes through this.
+fn srgb_to_linear(byte: u8) -> f64 {
+    let c = byte as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Surface};
+
+    #[test]
+    fn hsv_primaries() {
+        for (color, hue) in [(Color::RED, 0.0), (Color::GREEN, 120.0), (Color::BLUE, 240.0), (Color::YELLOW, 60.0), (Color::CYAN, 180.0), (Color::MAGENTA, 300.0)] {
+            assert_eq!(color.to_hsv(), (hue, 1.0, 1.0));
+            assert_eq!(Color::from_hsv(hue, 1.0, 1.0), color);
+        }
+        // Hue goes around: a whole turn either way is the same color
+        assert_eq!(Color::from_hsv(360.0, 1.0, 1.0), Color::RED);
+        assert_eq!(Color::from_hsv(-240.0, 1.0, 1.0), Color::GREEN);
+    }
+
+    #[test]
+    fn hsv_grays() {
+        // No saturation, so no hue to speak of
+        assert_eq!(Color::GRAY.to_hsv(), (0.0, 0.0, 0x80 as f32 / 255.0));
+        assert_eq!(Color::BLACK.to_hsv(), (0.0, 0.0, 0.0));
+        assert_eq!(Color::WHITE.to_hsv(), (0.0, 0.0, 1.0));
+        for hue in [0.0, 90.0, 200.0] {
+            assert_eq!(Color::from_hsv(hue, 0.0, 0x80 as f32 / 255.0), Color::GRAY);
+        }
+        // Out of range and NaN are clamped, not garbage
+        assert_eq!(Color::from_hsv(f32::NAN, 2.0, f32::NAN), Color::BLACK);
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for r in (0..=255).step_by(17) {
+            for g in (0..=255).step_by(51) {
+                for b in (0..=255).step_by(85) {
+                    let color = Color::rgb(r, g, b);
+                    let (h, s, v) = color.to_hsv();
+                    assert_eq!(Color::from_hsv(h, s, v), color)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lerp_ends_and_middle() {
+        let (a, b) = (Color::rgba(10, 20, 30, 40), Color::rgba(30, 60, 90, 240));
+        assert_eq!(Color::lerp(a, b, 0.0), a);
+        assert_eq!(Color::lerp(a, b, 1.0), b);
+        assert_eq!(Color::lerp(a, b, 0.5), Color::rgba(20, 40, 60, 140));
+        // Backwards works, and past the ends is the ends
+        assert_eq!(Color::lerp(b, a, 0.5), Color::rgba(20, 40, 60, 140));
+        assert_eq!(Color::lerp(a, b, -3.0), a);
+        assert_eq!(Color::lerp(a, b, 7.0), b);
+        assert_eq!(Color::lerp(a, b, f32::NAN), a);
+        assert_eq!(Color::lerp(Color::BLACK, Color::WHITE, 0.25), Color::rgb(64, 64, 64));
+    }
+
+    #[test]
+    fn blend_gray_over_white() {
+        let gray = Color::rgb(0x80, 0x80, 0x80);
+        let faint = Color::rgba(0x80, 0x80, 0x80, 0x80);
+        let cases = [
+            (BlendMode::Replace, gray, faint),
+            (BlendMode::Alpha, gray, Color::rgb(191, 191, 191)),
+            (BlendMode::Add, Color::WHITE, Color::WHITE),
+            (BlendMode::Multiply, gray, Color::rgb(191, 191, 191)),
+        ];
+        for (mode, opaque, half) in cases {
+            assert_eq!(mode.blend(Color::WHITE, gray), opaque, "{:?}", mode);
+            assert_eq!(mode.blend(Color::WHITE, faint), half, "{:?} at half alpha", mode);
+        }
+        // Add and multiply the other way round: onto black, and with white
+        assert_eq!(BlendMode::Add.blend(Color::BLACK, gray), gray);
+        assert_eq!(BlendMode::Multiply.blend(gray, Color::WHITE), gray);
+    }
+
+    #[test]
+    fn blend_modes_on_a_canvas() {
+        // Drawing with a mode set does the same sum as BlendMode::blend
+        for mode in [BlendMode::Replace, BlendMode::Alpha, BlendMode::Add, BlendMode::Multiply] {
+            let mut frame = vec![0xff; 2 * 2 * 4];
+            let mut canvas = Canvas::new(&mut frame, 2, 2);
+            canvas.set_blend_mode(mode);
+            canvas.fill_rect(0, 0, 2, 1, Color::rgba(0x80, 0x80, 0x80, 0x80));
+            canvas.set_pixel(1, 1, Color::rgba(0x80, 0x80, 0x80, 0x80));
+            let expected = mode.blend(Color::WHITE, Color::rgba(0x80, 0x80, 0x80, 0x80)).as_bytes();
+            for px in [0, 1, 3] {
+                assert_eq!(frame[px * 4..px * 4 + 4], expected, "{:?}", mode)
+            }
+            assert_eq!(frame[8..12], [0xff; 4]);
+        }
+    }
+
+    #[test]
+    fn srgb_to_linear_known_values() {
+        // From the sRGB spec's formula; 10 is under the straight-line part's cutoff
+        for (byte, linear) in [(0, 0.0), (10, 0.003035), (64, 0.051269), (128, 0.215861), (188, 0.502886), (255, 1.0)] {
+            assert!((srgb_to_linear(byte) - linear).abs() < 1e-6, "{} came out {}", byte, srgb_to_linear(byte))
+        }
+        // The clear color's alpha isn't converted
+        let clear = pixels::wgpu::Color::from(Color::rgba(128, 0, 255, 128));
+        assert_eq!((clear.g, clear.b, clear.a), (0.0, 1.0, 128.0 / 255.0));
+        assert!((clear.r - 0.215861).abs() < 1e-6)
+    }
+
+    #[test]
+    fn from_hex_forms() {
+        let orange = Color::rgba(0xff, 0x88, 0x00, 0xff);
+        assert_eq!(Color::from_hex("#f80"), Ok(orange));
+        assert_eq!(Color::from_hex("f80"), Ok(orange));
+        assert_eq!(Color::from_hex("#F80C"), Ok(Color::rgba(0xff, 0x88, 0x00, 0xcc)));
+        assert_eq!(Color::from_hex("#ff8800"), Ok(orange));
+        assert_eq!(Color::from_hex("#Ff880040"), Ok(Color::rgba(0xff, 0x88, 0x00, 0x40)));
+        assert_eq!(Color::from_hex(&orange.to_hex_string()), Ok(orange))
+    }
+
+    #[test]
+    fn from_hex_malformed() {
+        assert_eq!(Color::from_hex(""), Err(ColorParseError::WrongLength(0)));
+        assert_eq!(Color::from_hex("#"), Err(ColorParseError::WrongLength(0)));
+        assert_eq!(Color::from_hex("#12345"), Err(ColorParseError::WrongLength(5)));
+        assert_eq!(Color::from_hex("#123456789"), Err(ColorParseError::WrongLength(9)));
+        assert_eq!(Color::from_hex("#12g"), Err(ColorParseError::NotHex('g')));
+        assert_eq!(Color::from_hex("##123"), Err(ColorParseError::NotHex('#')));
+        assert_eq!(Color::from_hex(" 123"), Err(ColorParseError::NotHex(' ')));
+        // Not ASCII, so slicing it up by bytes would have gone wrong
+        assert_eq!(Color::from_hex("#12é"), Err(ColorParseError::NotHex('é')))
+    }
+}
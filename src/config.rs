@@ -0,0 +1,220 @@
+// Everything run_app needs to know to set up the window and the Pixels instance.
+
+use crate::{Color, Sprite};
+
+/// How to build the window and the pixel buffer that goes in it.
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title: String,
+
+    /// The logical size of the window, for winit. The window will actually
+    /// technically be 4x as many pixels as this, because of hidpi.
+    pub window_size: (u32, u32),
+
+    /// The logical size of the Pixels instance. This will get scaled up evenly
+    /// to match the size of the window, which will get scaled again to match the
+    /// hidpi factor. Confused yet? The window can't be resized smaller than this,
+    /// because then the pixel buffer wouldn't fit in it.
+    pub pixel_size: (u32, u32),
+
+    /// Whether the pixel buffer stays `pixel_size`, or follows the window's size
+    /// around. See `ResolutionPolicy`.
+    pub resolution_policy: ResolutionPolicy,
+
+    /// The color of the border Pixels draws around the pixel buffer, when the
+    /// window's shape doesn't match it. This doesn't touch the buffer itself, but it
+    /// looks the same as the same color drawn in it.
+    pub clear_color: Color,
+
+    /// How the pixel buffer is scaled up to fill the window. See `ScalingMode`.
+    pub scaling: ScalingMode,
+
+    /// How wide each pixel is shown, compared to how tall, for looking like old
+    /// hardware with pixels that weren't square: 8.0 / 7.0 makes them a bit wide.
+    /// The buffer's still drawn on one square pixel at a time; this only stretches
+    /// the picture the same way on its way to the window, before the rest of
+    /// `scaling` fits it in. With `IntegerOnly`, it's the short side of each pixel
+    /// that gets a whole-number scale. `Context::mouse_pos` allows for it, so the
+    /// mouse is still over the right pixel. 1.0 is square (and anything that isn't
+    /// a positive number counts as that too).
+    pub pixel_aspect: f32,
+
+    /// How finished frames get to the screen. `Fifo` waits for vsync, so it never
+    /// tears and doesn't draw frames nobody will see, but it adds up to a frame of
+    /// latency; the others trade that away. See `PresentMode` for the details. If
+    /// the graphics card can't do the one asked for, we fall back to `Fifo`, which it
+    /// always can; `Context::present_mode` says which one we ended up with.
+    ///
+    /// The only way to find out is to try it, though, and wgpu panics if it can't.
+    /// We catch that, but its message still ends up on stderr, and in a build with
+    /// `panic = "abort"` there's no catching it: an unsupported mode ends the
+    /// program. If you build that way, stick to `Fifo` unless you know the machine
+    /// has the mode you want.
+    pub present_mode: PresentMode,
+
+    /// The most frames a second to draw, or `None` to draw as many as the present
+    /// mode allows. With `Fifo` that's the refresh rate already, but with
+    /// `Immediate` or `Mailbox` it can be thousands, which is a lot of wasted work;
+    /// a cap keeps their low latency without the spinning. It's the main config's
+    /// cap that counts, across all the windows. `Some(0)` is the same as `None`.
+    /// This doesn't change how often `App::update` runs.
+    pub max_fps: Option<u32>,
+
+    /// How many times a second `App::update` runs. This is independent of how often
+    /// frames are drawn, so the world moves at the same speed on any display.
+    pub tick_rate_hz: u32,
+
+    /// What decides when updates happen. See `UpdateTiming`.
+    pub update_timing: UpdateTiming,
+
+    /// Stop calling `App::update` while the window doesn't have focus, or can't be
+    /// seen at all (minimized, or covered up, on platforms that tell us). Time spent
+    /// paused doesn't count, so the world picks up where it left off.
+    pub pause_when_unfocused: bool,
+
+    /// How much of the pixel buffer to keep clear around the edges, for
+    /// `Context::safe_area`, as a fraction of its width (for the left and right) and
+    /// height (for the top and bottom). TVs can crop off the edges of the picture,
+    /// so important things like the score stay inside it. 0.05 is the usual "action
+    /// safe" margin; 0.1 is the more careful "title safe" one.
+    pub safe_area_inset: f32,
+
+    /// An effect the GPU applies to the picture after it's scaled up to the window.
+    /// It only changes what's on the screen: the pixel buffer (and so screenshots)
+    /// stays exactly what the app drew.
+    pub post_effect: PostEffect,
+
+    /// Have `App::draw` draw into a buffer of our own, which is copied into the
+    /// real pixel buffer all at once when it's done. The real one never has a
+    /// half-drawn frame in it, so a screenshot taken from `update` can't catch one.
+    /// The copy costs a little every frame, so it's off unless asked for. Either
+    /// way, whatever was drawn last frame is still there at the start of the next.
+    pub double_buffered: bool,
+
+    /// The window's icon, for the title bar and taskbar, or `None` for whatever the
+    /// platform usually shows. It gets scaled to fit wherever it's shown, and that
+    /// looks best from a square power-of-two size: 32x32 is a good all-rounder,
+    /// and 16x16, 64x64 or 256x256 are also common.
+    pub icon: Option<Sprite>,
+}
+
+/// The ways frames can be handed to the display. These are wgpu's present modes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum PresentMode {
+    /// Wait for vsync to show each frame. No tearing, low CPU use (rendering
+    /// blocks until the display is ready), but input can lag by up to a frame.
+    #[default]
+    Fifo,
+    /// Show each frame the moment it's done. Lowest latency, but it tears, and
+    /// without a frame limit the loop will draw as fast as the CPU can go.
+    Immediate,
+    /// Frames are drawn as fast as possible but only the newest one is shown at
+    /// each vsync. No tearing and low latency, but it burns CPU and GPU time on
+    /// frames that get thrown away. Not every platform has it.
+    Mailbox,
+}
+
+impl From<PresentMode> for pixels::wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => Self::Fifo,
+            PresentMode::Immediate => Self::Immediate,
+            PresentMode::Mailbox => Self::Mailbox,
+        }
+    }
+}
+
+/// How big the pixel buffer is, as the window changes size.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ResolutionPolicy {
+    /// Always `WindowConfig::pixel_size` (or whatever `Context::resize_pixel_buffer`
+    /// makes it), scaled up to fit the window. The window can't be made smaller
+    /// than the buffer.
+    #[default]
+    Fixed,
+    /// The window's logical size (its physical size over the hidpi scale factor,
+    /// rounded), so each pixel in the buffer is one pixel as far as the OS is
+    /// concerned, at any size; for editors more than games. `pixel_size` is
+    /// ignored. While the window's being dragged bigger or smaller, the buffer
+    /// waits until it's stopped for a moment before it's made again, and the same
+    /// as with `resize_pixel_buffer`, what was in it is gone, so draw every frame
+    /// from scratch. It's never less than 1 pixel, or more than 8192, a side. This
+    /// is meant for square pixels: with `pixel_aspect`, the buffer still matches
+    /// the window, and the scaling letterboxes it.
+    MatchWindow,
+}
+
+/// When the runner checks the clock to see how many updates are due. Either way
+/// there's one update for each `1 / tick_rate_hz` of real time that's gone by; this
+/// is about what instants that time is measured between.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum UpdateTiming {
+    /// Every time around the event loop, whenever that happens to be. It works
+    /// with any present mode and frame cap, and when nothing's being drawn, but the
+    /// loop and the display's refresh aren't in step: with a 60Hz tick on a 60Hz
+    /// display, the clock can land just either side of a step, so some frames get
+    /// two updates and the next none, which shows up as judder.
+    #[default]
+    Timer,
+    /// Just after each frame is presented on the main window. With `Fifo`,
+    /// presenting waits for vsync, so the time between presents is the display's
+    /// own frame time, and a tick rate that matches the refresh rate gets exactly
+    /// one update a frame. The catch is that updates only happen when frames do: if
+    /// the OS stops asking for redraws (some do for a minimized window, even without
+    /// `pause_when_unfocused`) the world stops too. Once the main window's closed,
+    /// it goes back to `Timer`.
+    Present,
+}
+
+/// How the pixel buffer gets fitted to the window. All of them keep pixels sharp
+/// (no blurring between them), but only `IntegerOnly` keeps them all the same size.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ScalingMode {
+    /// As big as it'll go while keeping its shape, with a border of the clear color
+    /// on two sides. Unless the window happens to be a multiple of the buffer's
+    /// size, some rows and columns of pixels will be one window pixel bigger than
+    /// others.
+    Fit,
+    /// The biggest whole-number scale that fits, with whatever's left over as a
+    /// border of the clear color. This is what Pixels does by itself, so it's
+    /// the cheapest, and it's the default.
+    #[default]
+    IntegerOnly,
+    /// Cover the whole window, even if that means squashing the pixels.
+    Stretch,
+}
+
+/// Post-processing for the scaled-up picture. See `WindowConfig::post_effect`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum PostEffect {
+    /// Show the pixels as they are.
+    #[default]
+    None,
+    /// Look like an old CRT: every other row of the window's (real, physical)
+    /// pixels is darkened. With `bleed`, each pixel also picks up a little of the
+    /// colors to either side, like a blurry old tube would.
+    Scanlines { bleed: bool },
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "The Thing".to_string(),
+            window_size: (640, 480),
+            pixel_size: (320, 240),
+            resolution_policy: ResolutionPolicy::default(),
+            clear_color: Color::rgb(26, 26, 38),
+            scaling: ScalingMode::default(),
+            pixel_aspect: 1.0,
+            present_mode: PresentMode::default(),
+            max_fps: None,
+            tick_rate_hz: 60,
+            update_timing: UpdateTiming::default(),
+            pause_when_unfocused: true,
+            safe_area_inset: 0.05,
+            post_effect: PostEffect::default(),
+            double_buffered: false,
+            icon: None,
+        }
+    }
+}
@@ -0,0 +1,461 @@
+// The Context is the app's handle on everything that isn't drawing: it gets passed
+// into update and draw so the app can ask about the world outside and tell the
+// runner to do things.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use pixels::{Pixels, TextureError};
+#[cfg(feature = "audio")]
+use rodio::OutputStreamHandle;
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::error::ExternalError;
+use winit::event::MouseButton;
+use winit::window::{CursorGrabMode, Fullscreen, Window};
+use crate::{Error, Input, PresentMode, Rng, ScalingMode, WindowConfig};
+#[cfg(feature = "audio")]
+use crate::audio::{self, Sound};
+use crate::scaling;
+use crate::ui::{self, Widgets};
+use crate::screenshot::{self, ScreenshotError};
+
+/// Passed to `App::update` every tick, and to `App::draw` every frame. Where it
+/// talks about the window, that's the main one, the one from `run_app`'s config.
+#[derive(Default)]
+pub struct Context {
+    pub(crate) input: Input,
+    pub(crate) frames: FrameCounter,
+    pub(crate) frame_stats: FrameStats,
+    pub(crate) quit: bool,
+    pub(crate) rng: Rng,
+    // How long one update is, for things that need to know the update rate
+    pub(crate) tick: Duration,
+    // The widgets asked for this update, for the runner to draw
+    pub(crate) widgets: Widgets,
+    // The runner shares the Pixels with us so we can get at the frame from update.
+    // While the app's drawing, the frame's borrowed by the canvas.
+    pub(crate) pixels: Option<Rc<RefCell<Pixels>>>,
+    pub(crate) pixel_size: (u32, u32),
+    pub(crate) window: Option<Rc<Window>>,
+    pub(crate) present_mode: PresentMode,
+    pub(crate) scaling: ScalingMode,
+    pub(crate) pixel_aspect: f32,
+    pub(crate) safe_area_inset: f32,
+    // Where the mouse is in the window, in physical pixels
+    pub(crate) mouse_window_pos: Option<(f64, f64)>,
+    pub(crate) cursor_hidden: bool,
+    pub(crate) unfocused: bool,
+    // The other windows: the ones the app has open (or has asked for), and what it's
+    // asked for since the runner last looked
+    pub(crate) windows: Vec<WindowKey>,
+    pub(crate) next_window: u32,
+    pub(crate) opening: Vec<(WindowKey, WindowConfig)>,
+    pub(crate) closing: Vec<WindowKey>,
+    // Where sounds go, if there's anywhere for them to go
+    #[cfg(feature = "audio")]
+    pub(crate) audio: Option<OutputStreamHandle>,
+}
+
+/// Which of the app's extra windows something is about, handed out by
+/// `Context::open_window`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct WindowKey(u32);
+
+impl Context {
+    /// The most time that one frame can count for. If more time than this has
+    /// passed since the last frame (because the window was being dragged, or the
+    /// machine went to sleep) we pretend it's only been this long, so that the world
+    /// doesn't jump ahead in one go. A quarter second is long enough to never be hit
+    /// by a merely slow frame.
+    pub const MAX_DT: Duration = Duration::from_millis(250);
+
+    /// The keyboard and mouse state as of this update.
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    /// The files dropped on the main window since the last update, like for
+    /// previewing a PNG with `Sprite::from_png_bytes`. This is the same as
+    /// `Input::dropped_files`.
+    pub fn dropped_files(&self) -> &[PathBuf] {
+        self.input.dropped_files()
+    }
+
+    /// Whether files are being dragged over the main window right now, for drawing a
+    /// highlight where they'd be dropped. The same as `Input::is_file_hovered`.
+    pub fn is_file_hovered(&self) -> bool {
+        self.input.is_file_hovered()
+    }
+
+    /// Which pixel of the pixel buffer the mouse is over, or `None` if it's outside
+    /// the window or over the border around the buffer. This is the same as
+    /// `Input::mouse_pixel_pos`, but signed, so it's easier to do math with.
+    pub fn mouse_pos(&self) -> Option<(i32, i32)> {
+        self.input.mouse_pixel_pos().map(|(x, y)| (x as i32, y as i32))
+    }
+
+    /// Where the mouse is in the window, in physical pixels from the top left, with
+    /// none of the scaling undone. Useful for lining things up with the window
+    /// rather than the pixel buffer. `None` if the mouse isn't over the window.
+    pub fn mouse_window_pos(&self) -> Option<(f64, f64)> {
+        self.mouse_window_pos
+    }
+
+    /// The part of the window the pixel buffer covers, as (x, y, width, height) in
+    /// physical pixels from the window's top left, for lining things up with the
+    /// scaled-up picture from outside it. This is the other way around from
+    /// `mouse_pos`, which goes from the window to the buffer. How big it is depends on
+    /// `WindowConfig::scaling`: with `IntegerOnly` it's a whole multiple of the
+    /// buffer's size, centered (and if the window's smaller than the buffer, it's the
+    /// buffer's size, hanging off the edges); `Fit` fills the window one way and is
+    /// centered the other; `Stretch` is the whole window. Pixels that aren't square
+    /// (`WindowConfig::pixel_aspect`) make it wider or taller. It's worked out from the
+    /// window's size as it is now, so it keeps up with resizing. With no window,
+    /// it's just the size of the buffer.
+    pub fn viewport_rect(&self) -> (i32, i32, u32, u32) {
+        match &self.window {
+            Some(window) => {
+                let size = window.inner_size();
+                scaling::viewport(self.scaling, self.pixel_size, self.pixel_aspect, (size.width, size.height))
+            }
+            None => (0, 0, self.pixel_size.0, self.pixel_size.1)
+        }
+    }
+
+    /// The part of the pixel buffer that's inside the margin from
+    /// `WindowConfig::safe_area_inset`, as (x, y, width, height) in pixels, for
+    /// keeping the UI away from the edges. `Canvas::draw_safe_area_guides` shows it.
+    pub fn safe_area(&self) -> (i32, i32, u32, u32) {
+        safe_area(self.pixel_size, self.safe_area_inset)
+    }
+
+    /// Which pixel of the pixel buffer is at a point in the window, given in
+    /// physical pixels from its top left (like `mouse_window_pos`), or `None` if the
+    /// point's outside the buffer: in the border, or off the window. This is the
+    /// same sum `mouse_pos` does, for points that aren't the mouse. With no window,
+    /// it's always `None`.
+    pub fn window_to_pixel(&self, physical: (f32, f32)) -> Option<(i32, i32)> {
+        self.pixel_at((physical.0 as f64, physical.1 as f64)).map(|(x, y)| (x as i32, y as i32))
+    }
+
+    /// The other way from `window_to_pixel`: where the middle of pixel (x, y) is in
+    /// the window, in physical pixels from its top left, allowing for the scaling and
+    /// the border, for putting things like OS tooltips next to something in the
+    /// buffer. Pixels off the buffer work too, and come out off the picture (or off
+    /// the window). Taking the result back through `window_to_pixel` gives the same
+    /// pixel. With no window, it's as if the window were the buffer's own size.
+    pub fn pixel_to_window(&self, pixel: (i32, i32)) -> (f32, f32) {
+        let size = match &self.window {
+            Some(window) => {
+                let PhysicalSize { width, height } = window.inner_size();
+                (width, height)
+            }
+            None => self.pixel_size,
+        };
+        let region = scaling::pixel_region(self.scaling, self.pixel_size, self.pixel_aspect, size);
+        scaling::pixel_to_window_pos(region, self.pixel_size, pixel)
+    }
+
+    /// Frames per second, averaged over the last half second or so, which is slow
+    /// enough to be readable if you draw it on the screen every frame.
+    pub fn fps(&self) -> f32 {
+        self.frames.smoothed()
+    }
+
+    /// Frames per second going by only how long the last frame took. This jumps
+    /// around a lot more than `fps`.
+    pub fn instant_fps(&self) -> f32 {
+        self.frames.instant()
+    }
+
+    /// How long each of the last 256 or so frames took, from the start of one to
+    /// the start of the next, oldest first. For `Canvas::draw_frame_graph`.
+    pub fn frame_times(&self) -> &[Duration] {
+        &self.frames.intervals
+    }
+
+    /// How long the last frame spent on each part of its work. It's the last one
+    /// that's completely done, so it doesn't change partway through a frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// A random number generator for the app to use. It always starts from
+    /// `Rng::DEFAULT_SEED`, so an app that only gets its randomness from here (and
+    /// gets the same input) does the same thing every run, which is handy for
+    /// `render_headless` tests; to get a different run each time, give it a new
+    /// one seeded from something like the time.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Save the pixel buffer, as it was last drawn, to a PNG file. This is the
+    /// buffer itself and not the window, so the image is always the buffer's size
+    /// no matter how the window's scaled. It can't be done from inside `draw`,
+    /// since the buffer's being drawn on then.
+    pub fn save_screenshot(&self, path: impl AsRef<Path>) -> Result<(), ScreenshotError> {
+        let pixels = self.pixels.as_ref().ok_or(ScreenshotError::Unavailable)?;
+        let pixels = pixels.try_borrow().map_err(|_| ScreenshotError::Unavailable)?;
+        let (width, height) = self.pixel_size;
+        screenshot::save_png(pixels.frame(), width, height, path.as_ref())
+    }
+
+    /// Change the size of the pixel buffer, from the next frame on; `draw` gets a
+    /// canvas the new size. Whatever was in the buffer is gone, so it starts out
+    /// transparent black, the same as at startup. The window can't be made smaller
+    /// than the buffer any more than before, and the scaling works just the same,
+    /// so a buffer twice the size scales up half as much. Neither side can be zero.
+    /// It can't be done from inside `draw`, since the buffer's being drawn on then.
+    pub fn resize_pixel_buffer(&mut self, w: u32, h: u32) -> Result<(), Error> {
+        self.set_buffer_size(w, h)?;
+        if let Some(window) = &self.window {
+            window.set_min_inner_size(Some(LogicalSize { width: w, height: h }))
+        }
+        Ok(())
+    }
+
+    // Resizing the buffer without touching the window, for when the window's the
+    // one that changed
+    pub(crate) fn set_buffer_size(&mut self, w: u32, h: u32) -> Result<(), Error> {
+        if w == 0 { return Err(TextureError::TextureWidth(w).into()) }
+        if h == 0 { return Err(TextureError::TextureHeight(h).into()) }
+        if let Some(pixels) = &self.pixels {
+            let mut pixels = pixels.borrow_mut();
+            pixels.resize_buffer(w, h)?;
+            pixels.frame_mut().fill(0)
+        }
+        self.pixel_size = (w, h);
+        self.update_mouse_pixel_pos();
+        Ok(())
+    }
+
+    /// Switch between borderless fullscreen (on whichever monitor the window's on)
+    /// and a normal window. The pixel buffer stays the same size either way; it just
+    /// gets scaled up to fit the screen, with a border if the shape doesn't match.
+    pub fn set_fullscreen(&mut self, on: bool) {
+        if let Some(window) = &self.window {
+            window.set_fullscreen(if on { Some(Fullscreen::Borderless(None)) } else { None })
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.as_ref().is_some_and(|w| w.fullscreen().is_some())
+    }
+
+    /// Show or hide the mouse cursor while it's over the window, say to draw one of
+    /// your own at `mouse_pos` instead. The mouse position still gets tracked
+    /// either way.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if let Some(window) = &self.window {
+            window.set_cursor_visible(visible)
+        }
+        self.cursor_hidden = !visible
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        !self.cursor_hidden
+    }
+
+    /// Keep the mouse from leaving the window (`Confined`), or pin it in place
+    /// (`Locked`), or let it go again (`None`). Not every platform can do every
+    /// mode: macOS can't confine and X11 can't lock, so if one fails it's worth
+    /// trying the other. While the mouse is locked it doesn't move, so neither
+    /// does `mouse_pos`.
+    pub fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        match &self.window {
+            Some(window) => window.set_cursor_grab(mode),
+            None => Ok(())
+        }
+    }
+
+    /// Whether the window has keyboard focus. Assumed to be true until we hear
+    /// otherwise.
+    pub fn is_focused(&self) -> bool {
+        !self.unfocused
+    }
+
+    /// The present mode we're actually using, which is the one in the config unless
+    /// the graphics card couldn't do it.
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Open another window, described by `config`, once this update is done. It gets
+    /// drawn by `App::draw_window`, which is passed the key this hands back. The
+    /// window has its own pixel buffer, scaling and so on, but the app's timing is
+    /// from the main config, so `tick_rate_hz` and `pause_when_unfocused` are
+    /// ignored here. Keys from any window go to `input`, but the mouse is only
+    /// tracked over the main window.
+    pub fn open_window(&mut self, config: WindowConfig) -> WindowKey {
+        let key = WindowKey(self.next_window);
+        self.next_window += 1;
+        self.windows.push(key);
+        self.opening.push((key, config));
+        key
+    }
+
+    /// Close a window from `open_window` once this update is done. If it's already
+    /// closed, this does nothing.
+    pub fn close_window(&mut self, key: WindowKey) {
+        if self.is_window_open(key) {
+            self.windows.retain(|&k| k != key);
+            self.closing.push(key)
+        }
+    }
+
+    /// Whether a window from `open_window` is still open, or about to be. The user
+    /// can close one whenever they like, so this is how to find out they did.
+    pub fn is_window_open(&self, key: WindowKey) -> bool {
+        self.windows.contains(&key)
+    }
+
+    /// Start playing a sound. It plays on top of anything else that's playing, even
+    /// the same sound, so it's fine to fire these off as things happen. If there's
+    /// no audio device, nothing happens.
+    #[cfg(feature = "audio")]
+    pub fn play_sound(&self, sound: &Sound) {
+        if let Some(handle) = &self.audio {
+            audio::play(handle, sound)
+        }
+    }
+
+    /// A button, for quick debug UIs: a box at `rect` (x, y, width, height, in
+    /// pixel buffer coordinates) with `label` in the middle, which lights up with
+    /// the mouse over it. Call it from `update`, every update the button should be
+    /// there, and it's true for the update the button's clicked in. It gets drawn
+    /// on top of everything the app draws, in the main window, whatever the camera
+    /// was set to.
+    pub fn button(&mut self, rect: (i32, i32, u32, u32), label: &str) -> bool {
+        let hovered = self.mouse_pos().is_some_and(|pos| ui::contains(rect, pos));
+        let pressed = hovered && self.input.is_mouse_down(MouseButton::Left);
+        self.widgets.button(rect, label, hovered, pressed);
+        hovered && self.input.was_mouse_clicked(MouseButton::Left)
+    }
+
+    /// Ask the runner to close all the windows and exit once this update is done.
+    pub fn quit(&mut self) {
+        self.quit = true
+    }
+
+    // The mouse moved to somewhere in the window (or left it)
+    pub(crate) fn set_mouse_window_pos(&mut self, pos: Option<(f64, f64)>) {
+        self.mouse_window_pos = pos;
+        self.update_mouse_pixel_pos()
+    }
+
+    // Work out which pixel the mouse is over. Remember that there are two layers of
+    // scaling going on here: the window position is in physical pixels, so it's been
+    // through the hidpi scaling as well as the pixel buffer's. To get a point in the
+    // window_size space (in other words, to remove the hidpi scaling only):
+    // pos.to_logical(window.scale_factor()). But we know where the buffer is on the
+    // window in physical pixels (it's the same sum Pixels does, when Pixels is the
+    // one scaling), so both layers come off at once. Resizing the window changes the
+    // answer without the mouse moving, so this gets called after that too.
+    pub(crate) fn update_mouse_pixel_pos(&mut self) {
+        let pixel_pos = self.mouse_window_pos.and_then(|pos| self.pixel_at(pos));
+        self.input.set_mouse_pos(pixel_pos)
+    }
+
+    // Which pixel is at a point in the window, in physical pixels. This mustn't
+    // touch self.pixels: it's borrowed for the whole of App::draw, which is a
+    // perfectly reasonable place to call window_to_pixel from.
+    fn pixel_at(&self, pos: (f64, f64)) -> Option<(u32, u32)> {
+        let size = self.window.as_ref()?.inner_size();
+        let region = scaling::pixel_region(self.scaling, self.pixel_size, self.pixel_aspect, (size.width, size.height));
+        scaling::window_pos_to_pixel(region, self.pixel_size, pos)
+    }
+}
+
+/// Where the time went in one frame, from `Context::frame_stats`. If a frame's
+/// slow, this says whether it's the app (`update_time` and `draw_time`) or the
+/// graphics card and vsync (`present_time`).
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct FrameStats {
+    /// Running `App::update`, for all the updates done that frame (there can be
+    /// none, or several)
+    pub update_time: Duration,
+    /// Running `App::draw` (and `App::draw_window`, if there are other windows)
+    pub draw_time: Duration,
+    /// Getting the frame onto the screen: uploading it, scaling it, any post effect,
+    /// and (with `PresentMode::Fifo`) waiting for vsync
+    pub present_time: Duration,
+}
+
+// A buffer this size, less `inset` of it on every side. Each margin is rounded to
+// the nearest pixel, and a margin of more than half leaves nothing in the middle.
+pub(crate) fn safe_area((width, height): (u32, u32), inset: f32) -> (i32, i32, u32, u32) {
+    let inset = if inset.is_finite() { inset.clamp(0.0, 0.5) } else { 0.0 };
+    let (mx, my) = ((width as f32 * inset).round() as u32, (height as f32 * inset).round() as u32);
+    let (mx, my) = (mx.min(width / 2), my.min(height / 2));
+    (mx as i32, my as i32, width - mx * 2, height - my * 2)
+}
+
+// How far back to look when averaging the frame rate
+const FPS_WINDOW: Duration = Duration::from_millis(500);
+
+// How many frame times to keep for frame_times; more than any graph's likely to be
+// wide, on a retro-sized buffer
+const FRAME_HISTORY: usize = 256;
+
+// Remembers when recent frames were drawn, so we can tell how fast that's happening
+#[derive(Default)]
+pub(crate) struct FrameCounter {
+    times: VecDeque<Instant>,
+    // How long each of the last few frames took, oldest first
+    intervals: Vec<Duration>,
+}
+
+impl FrameCounter {
+    pub(crate) fn frame(&mut self, now: Instant) {
+        if let Some(&last) = self.times.back() {
+            if self.intervals.len() == FRAME_HISTORY {
+                self.intervals.remove(0);
+            }
+            self.intervals.push(now - last)
+        }
+        self.times.push_back(now);
+        // Keep one frame from before the window, so there's a whole window of
+        // intervals to average
+        while self.times.len() > 2 && now - self.times[1] > FPS_WINDOW {
+            self.times.pop_front();
+        }
+    }
+
+    fn smoothed(&self) -> f32 {
+        match (self.times.front(), self.times.back()) {
+            (Some(&first), Some(&last)) if last > first => {
+                (self.times.len() - 1) as f32 / (last - first).as_secs_f32()
+            }
+            _ => 0.0
+        }
+    }
+
+    fn instant(&self) -> f32 {
+        let n = self.times.len();
+        if n < 2 { return 0.0 }
+        let last = self.times[n - 1] - self.times[n - 2];
+        if last.is_zero() { 0.0 } else { 1.0 / last.as_secs_f32() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_area_rect() {
+        assert_eq!(safe_area((320, 240), 0.0), (0, 0, 320, 240));
+        assert_eq!(safe_area((320, 240), 0.05), (16, 12, 288, 216));
+        // Each margin rounds on its own: 3.2 and 1.6
+        assert_eq!(safe_area((32, 16), 0.1), (3, 2, 26, 12));
+        assert_eq!(safe_area((320, 240), 0.5), (160, 120, 0, 0));
+        // Out of range, or not a number at all, is clamped or ignored
+        assert_eq!(safe_area((320, 240), 2.0), (160, 120, 0, 0));
+        assert_eq!(safe_area((320, 240), -1.0), (0, 0, 320, 240));
+        assert_eq!(safe_area((320, 240), f32::NAN), (0, 0, 320, 240));
+        // An odd size can't split evenly, so there's a pixel left in the middle
+        assert_eq!(safe_area((5, 3), 0.5), (2, 1, 1, 1));
+    }
+}
@@ -0,0 +1,129 @@
+// A DrawList is drawing put off until later: record what to draw, in whatever order
+// is convenient, with a layer number for each thing, and then draw the lot in layer
+// order. That way, say, a character's shadow can be added while walking the
+// character list and still come out under everything.
+
+use std::borrow::Cow;
+use crate::{Canvas, Color, Sprite, Surface};
+
+/// One thing to draw, with the same arguments as the `Surface` (or `Canvas`)
+/// method of the same name. Sprites and text are borrowed, not copied.
+#[derive(Clone, Debug)]
+pub enum DrawCommand<'a> {
+    Pixel { x: i32, y: i32, color: Color },
+    Line { x0: i32, y0: i32, x1: i32, y1: i32, color: Color },
+    Rect { x: i32, y: i32, w: u32, h: u32, color: Color },
+    FillRect { x: i32, y: i32, w: u32, h: u32, color: Color },
+    Circle { cx: i32, cy: i32, radius: u32, color: Color },
+    FillCircle { cx: i32, cy: i32, radius: u32, color: Color },
+    /// `Canvas::blit`
+    Sprite { sprite: &'a Sprite, x: i32, y: i32 },
+    /// `Canvas::blit_blend`
+    SpriteBlend { sprite: &'a Sprite, x: i32, y: i32 },
+    Text { x: i32, y: i32, text: Cow<'a, str>, color: Color },
+}
+
+impl DrawCommand<'_> {
+    fn draw(&self, canvas: &mut Canvas) {
+        match *self {
+            DrawCommand::Pixel { x, y, color } => canvas.set_pixel(x, y, color),
+            DrawCommand::Line { x0, y0, x1, y1, color } => canvas.draw_line(x0, y0, x1, y1, color),
+            DrawCommand::Rect { x, y, w, h, color } => canvas.draw_rect(x, y, w, h, color),
+            DrawCommand::FillRect { x, y, w, h, color } => canvas.fill_rect(x, y, w, h, color),
+            DrawCommand::Circle { cx, cy, radius, color } => canvas.draw_circle(cx, cy, radius, color),
+            DrawCommand::FillCircle { cx, cy, radius, color } => canvas.fill_circle(cx, cy, radius, color),
+            DrawCommand::Sprite { sprite, x, y } => canvas.blit(sprite, x, y),
+            DrawCommand::SpriteBlend { sprite, x, y } => canvas.blit_blend(sprite, x, y),
+            DrawCommand::Text { x, y, ref text, color } => canvas.draw_text(x, y, text, color),
+        }
+    }
+}
+
+/// Draw commands waiting to be drawn, each with a `z`: higher numbers are drawn
+/// later, so they end up on top. Things with the same `z` are drawn in the order
+/// they were added.
+#[derive(Clone, Debug, Default)]
+pub struct DrawList<'a> {
+    commands: Vec<(i32, DrawCommand<'a>)>,
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add something to draw, on layer `z`.
+    pub fn push(&mut self, z: i32, command: DrawCommand<'a>) {
+        self.commands.push((z, command))
+    }
+
+    /// Add some text to draw on layer `z`. The text can be borrowed, or a `String`
+    /// (like one from `format!`) that the list holds on to.
+    pub fn push_text(&mut self, z: i32, x: i32, y: i32, text: impl Into<Cow<'a, str>>, color: Color) {
+        self.push(z, DrawCommand::Text { x, y, text: text.into(), color })
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Forget everything without drawing it.
+    pub fn clear(&mut self) {
+        self.commands.clear()
+    }
+
+    /// Draw everything onto `canvas`, lowest `z` first, and empty the list. The
+    /// list keeps its memory, so reusing one each frame doesn't allocate.
+    pub fn flush_to(&mut self, canvas: &mut Canvas) {
+        // sort_by_key is stable, which is what keeps equal z's in order
+        self.commands.sort_by_key(|&(z, _)| z);
+        for (_, command) in self.commands.drain(..) {
+            command.draw(canvas)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A two-pixel bar starting at x
+    fn bar(x: i32, color: Color) -> DrawCommand<'static> {
+        DrawCommand::FillRect { x, y: 0, w: 2, h: 1, color }
+    }
+
+    // Draw a list of (z, command) in the order given, and read back the row
+    fn flush(commands: Vec<(i32, DrawCommand)>) -> Vec<Option<Color>> {
+        let mut buf = vec![0; 4 * 4];
+        let mut canvas = Canvas::new(&mut buf, 4, 1);
+        let mut list = DrawList::new();
+        for (z, command) in commands {
+            list.push(z, command)
+        }
+        list.flush_to(&mut canvas);
+        assert!(list.is_empty());
+        (0..4).map(|x| canvas.get_pixel(x, 0)).collect()
+    }
+
+    #[test]
+    fn z_order_whatever_the_insertion_order() {
+        // Three overlapping bars: red under green under blue, added every which way
+        let (red, green, blue) = ((0, bar(0, Color::RED)), (1, bar(1, Color::GREEN)), (2, bar(2, Color::BLUE)));
+        let expected = vec![Some(Color::RED), Some(Color::GREEN), Some(Color::BLUE), Some(Color::BLUE)];
+        for order in [[0, 1, 2], [2, 1, 0], [1, 2, 0], [2, 0, 1]] {
+            let all = [red.clone(), green.clone(), blue.clone()];
+            let commands = order.iter().map(|&i| all[i].clone()).collect();
+            assert_eq!(flush(commands), expected, "order {:?}", order)
+        }
+    }
+
+    #[test]
+    fn equal_z_keeps_insertion_order() {
+        let row = flush(vec![(5, bar(0, Color::RED)), (5, bar(1, Color::GREEN)), (-1, bar(2, Color::BLUE)), (5, bar(2, Color::WHITE))]);
+        assert_eq!(row, vec![Some(Color::RED), Some(Color::GREEN), Some(Color::WHITE), Some(Color::WHITE)])
+    }
+}
@@ -0,0 +1,68 @@
+// The one error type for everything that can go wrong running an app, so run_app
+// (and so main) can just use `?`.
+
+use std::fmt;
+use winit::error::EventLoopError;
+use winit::window::BadIcon;
+
+/// Things that can stop `run_app` from running, or stop it partway.
+#[derive(Debug)]
+pub enum Error {
+    /// winit couldn't make the event loop or the window, or the event loop failed
+    EventLoop(EventLoopError),
+    /// Pixels couldn't be set up (there's no usable graphics card, say) or
+    /// couldn't draw a frame. A surface that's `Lost` or `Outdated` only ends up
+    /// here if it's still that way after `run_app` reconfigures it and tries the
+    /// frame again; the first time is just logged.
+    Pixels(pixels::Error),
+    /// The window was resized to something the graphics card can't draw to
+    Resize(pixels::TextureError),
+    /// `WindowConfig::icon` isn't something the platform will take as an icon
+    Icon(BadIcon),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EventLoop(err) => write!(f, "event loop error: {}", err),
+            Error::Pixels(err) => write!(f, "pixels error: {}", err),
+            Error::Resize(err) => write!(f, "couldn't resize the window surface: {}", err),
+            Error::Icon(err) => write!(f, "bad window icon: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::EventLoop(err) => Some(err),
+            Error::Pixels(err) => Some(err),
+            Error::Resize(err) => Some(err),
+            Error::Icon(err) => Some(err),
+        }
+    }
+}
+
+impl From<EventLoopError> for Error {
+    fn from(err: EventLoopError) -> Self {
+        Error::EventLoop(err)
+    }
+}
+
+impl From<pixels::Error> for Error {
+    fn from(err: pixels::Error) -> Self {
+        Error::Pixels(err)
+    }
+}
+
+impl From<pixels::TextureError> for Error {
+    fn from(err: pixels::TextureError) -> Self {
+        Error::Resize(err)
+    }
+}
+
+impl From<BadIcon> for Error {
+    fn from(err: BadIcon) -> Self {
+        Error::Icon(err)
+    }
+}
@@ -0,0 +1,215 @@
+// A tiny built-in font, so there's some way to get words on the screen without
+// loading anything. It's the classic 5x7 LCD font: each glyph is five columns, one
+// byte per column, with the lowest bit being the top row. Only printable ASCII is
+// in here; anything else gets drawn as a filled box.
+
+/// How wide one glyph is, in pixels
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// How tall one glyph is, in pixels
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// How far apart the left edges of neighboring characters are: the glyph plus a
+/// column of space
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// How far apart the tops of successive lines are: the glyph plus a row of space
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+// What we draw for characters we don't have
+const FALLBACK: [u8; 5] = [0x7f; 5];
+
+// Glyphs for ' ' through '~'
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// The columns of the glyph for a character: bit 0 of each byte is the top row.
+/// Characters that aren't printable ASCII come back as a solid box.
+pub fn glyph(c: char) -> [u8; 5] {
+    match c {
+        ' '..='~' => GLYPHS[c as usize - ' ' as usize],
+        _ => FALLBACK,
+    }
+}
+
+/// How big `text` will be when drawn with `Canvas::draw_text`, as (width, height)
+/// in pixels. Each `\n` starts another line. The size is of the ink, so it doesn't
+/// include the space after the last column or below the last row.
+pub fn measure_text(text: &str) -> (u32, u32) {
+    if text.is_empty() { return (0, 0) }
+    let lines = text.split('\n');
+    let (count, longest) = lines.fold((0u32, 0u32), |(count, longest), line| {
+        (count + 1, longest.max(line.chars().count() as u32))
+    });
+    let width = if longest == 0 { 0 } else { longest * ADVANCE - 1 };
+    (width, count * LINE_HEIGHT - 1)
+}
+
+/// Break `text` into lines that each fit in `max_width_px` pixels when drawn at
+/// `scale` (as with `Canvas::draw_text_ex`). Lines break at spaces where they can;
+/// a word too long for a line on its own gets cut wherever it has to be. Each `\n`
+/// in the text still starts a new line. To draw the result, join it back up with
+/// `\n`s and hand it to `draw_text` or `draw_text_ex`.
+pub fn wrap_text(text: &str, max_width_px: u32, scale: u32) -> Vec<String> {
+    // How many characters fit on a line. The last one doesn't need its space after,
+    // hence the +1; and there's always room for one, or we'd never get anywhere.
+    let max_chars = match scale {
+        0 => usize::MAX,
+        _ => ((max_width_px / scale + 1) / ADVANCE).max(1) as usize,
+    };
+
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_len = 0;
+        for word in paragraph.split(' ').filter(|w| !w.is_empty()) {
+            let mut word_len = word.chars().count();
+            if line_len > 0 && line_len + 1 + word_len <= max_chars {
+                line.push(' ');
+                line.push_str(word);
+                line_len += 1 + word_len;
+                continue
+            }
+
+            // It starts a line of its own, and if it's too long for that, it gets
+            // chopped into as many full lines as it takes
+            if line_len > 0 {
+                lines.push(std::mem::take(&mut line))
+            }
+            let mut rest = word;
+            while word_len > max_chars {
+                let (cut, _) = rest.char_indices().nth(max_chars).unwrap();
+                lines.push(rest[..cut].to_string());
+                rest = &rest[cut..];
+                word_len -= max_chars
+            }
+            line.push_str(rest);
+            line_len = word_len
+        }
+        lines.push(line)
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_at_spaces() {
+        // Five characters to a 29 px line: 5 * 6 - 1
+        let lines = wrap_text("the quick brown fox", 29, 1);
+        assert_eq!(lines, vec!["the", "quick", "brown", "fox"]);
+        // Twice as big in twice the room is the same
+        assert_eq!(wrap_text("the quick brown fox", 58, 2), lines);
+        // Nine characters, 53 px
+        assert_eq!(wrap_text("the quick brown fox", 53, 1), vec!["the quick", "brown fox"]);
+        for line in &lines {
+            assert!(measure_text(line).0 <= 29, "{:?}", line)
+        }
+    }
+
+    #[test]
+    fn wrap_long_words_and_newlines() {
+        assert_eq!(wrap_text("abcdefghijkl", 29, 1), vec!["abcde", "fghij", "kl"]);
+        assert_eq!(wrap_text("ab\n\ncd  ef", 29, 1), vec!["ab", "", "cd ef"]);
+        // Too narrow for anything still gets one character a line
+        assert_eq!(wrap_text("abc", 0, 1).len(), 3);
+        assert_eq!(wrap_text("a b c d e f", 100, 0), vec!["a b c d e f"]);
+    }
+}
@@ -0,0 +1,47 @@
+// Gamepads come in through gilrs rather than winit, so they need polling: once each
+// time around the event loop, we drain gilrs' events into the Input, the same way
+// the window events go in. Only one pad is listened to for now, the first one that
+// was connected; if it goes away, we move on to another one if there is one.
+
+use gilrs::{Event, EventType, GamepadId, Gilrs};
+use crate::Input;
+
+pub(crate) struct Gamepads {
+    gilrs: Gilrs,
+    active: Option<GamepadId>,
+}
+
+impl Gamepads {
+    /// Start listening for gamepads. If gilrs can't (there's no support for this
+    /// platform, or no permission to read the devices) then there just aren't any.
+    pub(crate) fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => {
+                let active = gilrs.gamepads().next().map(|(id, _)| id);
+                Some(Self { gilrs, active })
+            }
+            Err(err) => {
+                log::warn!("No gamepad support: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Fold everything that's happened since the last call into `input`.
+    pub(crate) fn poll(&mut self, input: &mut Input) {
+        while let Some(Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                EventType::Connected if self.active.is_none() => self.active = Some(id),
+                EventType::Disconnected if self.active == Some(id) => {
+                    input.release_pad();
+                    self.active = self.gilrs.gamepads().map(|(id, _)| id).find(|&other| other != id)
+                }
+                _ if self.active != Some(id) => {}
+                EventType::ButtonPressed(button, _) => input.handle_pad_button(button, true),
+                EventType::ButtonReleased(button, _) => input.handle_pad_button(button, false),
+                EventType::AxisChanged(axis, value, _) => input.set_pad_axis(axis, value),
+                _ => {}
+            }
+        }
+    }
+}
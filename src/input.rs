@@ -0,0 +1,431 @@
+// Input collects the keyboard and mouse events that arrive between updates, so
+// update can ask "is this key held?" instead of handling events itself.
+//
+// There are two kinds of question: what's held down right now, which carries over
+// from tick to tick, and what changed since the last update (pressed / released),
+// which gets forgotten after every update so each edge is seen exactly once.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use winit::event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta};
+use winit::keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+#[cfg(feature = "gamepad")]
+use crate::{GamepadAxis, GamepadButton};
+
+// How far a stick has to move before it counts as moved at all. Sticks never quite
+// center, so without this a character would creep along on its own.
+#[cfg(feature = "gamepad")]
+const DEADZONE: f32 = 0.15;
+
+// Trackpads (and some mice) say how far to scroll in pixels, where wheels say how
+// many lines. Call it this many pixels to a line, which is about what browsers do.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// The state of the keyboard, mouse, and gamepad as of this update.
+#[derive(Default, Debug)]
+pub struct Input {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    // The same, by where the keys are rather than what they say
+    physical_down: HashSet<KeyCode>,
+    physical_pressed: HashSet<KeyCode>,
+    modifiers: ModifiersState,
+    mouse_pos: Option<(u32, u32)>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_clicked: HashSet<MouseButton>,
+    drags: HashMap<MouseButton, Drag>,
+    scroll: (f32, f32),
+    // Typing since the last update: the characters, and the editing keys
+    text: String,
+    backspaces: u32,
+    enter_typed: bool,
+    // Files dropped on the window since the last update, and whether some are
+    // being dragged over it now
+    dropped: Vec<PathBuf>,
+    file_hovered: bool,
+    #[cfg(feature = "gamepad")]
+    pad_buttons_down: HashSet<GamepadButton>,
+    #[cfg(feature = "gamepad")]
+    pad_buttons_pressed: HashSet<GamepadButton>,
+    #[cfg(feature = "gamepad")]
+    pad_axes: HashMap<GamepadAxis, f32>,
+}
+
+// A mouse button being held down: where it went down, and the last pixel the mouse
+// was over since (which sticks if the mouse goes off the buffer)
+#[derive(Copy, Clone, Debug)]
+struct Drag {
+    start: (u32, u32),
+    last: (u32, u32),
+}
+
+impl Input {
+    /// Whether the key is held down right now. Keys here are what the keyboard
+    /// layout says they are (`Key::Character("a")`, `NamedKey::Enter`), which is
+    /// right for anything the player thinks of by name: "press Q to quit", or a
+    /// shortcut. For keys picked for where they are, like WASD, see
+    /// `is_physical_key_down`.
+    pub fn is_key_down(&self, key: impl Into<Key>) -> bool {
+        self.keys_down.contains(&key.into())
+    }
+
+    /// Whether the key went down since the last update. Holding a key down doesn't
+    /// make this fire again when the OS starts repeating it.
+    pub fn was_key_pressed(&self, key: impl Into<Key>) -> bool {
+        self.keys_pressed.contains(&key.into())
+    }
+
+    /// Whether the key came up since the last update.
+    pub fn was_key_released(&self, key: impl Into<Key>) -> bool {
+        self.keys_released.contains(&key.into())
+    }
+
+    /// Whether the key in a particular place on the keyboard is held down right
+    /// now, whatever the layout says it is. `KeyCode`s are named for where the keys
+    /// are on a US keyboard, so `KeyCode::KeyW` is the key above S even on an
+    /// AZERTY keyboard, where it says Z. Use these for keys chosen for their
+    /// position (movement, mostly), and the logical keys for everything else.
+    pub fn is_physical_key_down(&self, code: KeyCode) -> bool {
+        self.physical_down.contains(&code)
+    }
+
+    /// Whether the key in a particular place on the keyboard went down since the
+    /// last update. See `is_physical_key_down`.
+    pub fn was_physical_key_pressed(&self, code: KeyCode) -> bool {
+        self.physical_pressed.contains(&code)
+    }
+
+    /// Which modifier keys are held right now. Either shift (or control, and so on)
+    /// counts, left or right.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Whether either control key is held, for shortcuts like ctrl-s.
+    pub fn ctrl(&self) -> bool {
+        self.modifiers.control_key()
+    }
+
+    pub fn shift(&self) -> bool {
+        self.modifiers.shift_key()
+    }
+
+    pub fn alt(&self) -> bool {
+        self.modifiers.alt_key()
+    }
+
+    /// Whether the logo key is held: the Windows key, or command on a Mac.
+    pub fn logo(&self) -> bool {
+        self.modifiers.super_key()
+    }
+
+    /// Where the mouse is, in pixel buffer coordinates. `None` if it's outside the
+    /// window, or over the border around the pixel buffer.
+    pub fn mouse_pixel_pos(&self) -> Option<(u32, u32)> {
+        self.mouse_pos
+    }
+
+    /// Whether the mouse button is held down right now.
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Whether the mouse button went down since the last update.
+    pub fn was_mouse_clicked(&self, button: MouseButton) -> bool {
+        self.buttons_clicked.contains(&button)
+    }
+
+    /// The pixel the mouse was over when `button` went down, as long as it's still
+    /// held. `None` if it isn't, or if it went down with the mouse outside the pixel
+    /// buffer.
+    pub fn drag_start(&self, button: MouseButton) -> Option<(i32, i32)> {
+        self.drags.get(&button).map(|drag| (drag.start.0 as i32, drag.start.1 as i32))
+    }
+
+    /// How far the mouse has moved, in pixels, since `button` went down, while it's
+    /// held: add it to where the thing being dragged started out. If the mouse goes
+    /// off the pixel buffer mid-drag, this stays where it last was on it. `None`
+    /// whenever `drag_start` is.
+    pub fn drag_delta(&self, button: MouseButton) -> Option<(i32, i32)> {
+        self.drags.get(&button).map(|Drag { start, last }| (last.0 as i32 - start.0 as i32, last.1 as i32 - start.1 as i32))
+    }
+
+    /// How far the mouse wheel (or trackpad) scrolled since the last update, as (x,
+    /// y). The units are a wheel's clicks ("lines"); trackpads report pixels, which
+    /// are counted as 20 to a line, so a two-finger swipe moves about as far as the
+    /// wheel would. Positive means the thing being scrolled should move right or
+    /// down: rolling the wheel away from you is positive y. A tilting wheel or a
+    /// sideways swipe is the x.
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.scroll
+    }
+
+    /// The characters typed since the last update, in order, the way the keyboard
+    /// layout says they come out (so shift-a is "A"). Unlike `was_key_pressed`, a
+    /// held key types again every time the OS repeats it, like in a text box.
+    /// Control characters aren't included; see `backspaces` and `enter_typed`.
+    pub fn text_this_frame(&self) -> &str {
+        &self.text
+    }
+
+    /// How many times backspace was typed since the last update, counting repeats
+    /// from holding it down, for deleting from the end of whatever's being typed.
+    pub fn backspaces(&self) -> u32 {
+        self.backspaces
+    }
+
+    /// Whether enter was typed since the last update, for finishing what's being
+    /// typed.
+    pub fn enter_typed(&self) -> bool {
+        self.enter_typed
+    }
+
+    /// The files dropped on the window since the last update, in the order they
+    /// arrived. Dropping several at once gives all of them.
+    pub fn dropped_files(&self) -> &[PathBuf] {
+        &self.dropped
+    }
+
+    /// Whether there are files being dragged over the window right now, not yet
+    /// dropped, for showing that it's somewhere they can go.
+    pub fn is_file_hovered(&self) -> bool {
+        self.file_hovered
+    }
+
+    /// Whether the gamepad button is held down right now.
+    #[cfg(feature = "gamepad")]
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.pad_buttons_down.contains(&button)
+    }
+
+    /// Whether the gamepad button went down since the last update.
+    #[cfg(feature = "gamepad")]
+    pub fn was_button_pressed(&self, button: GamepadButton) -> bool {
+        self.pad_buttons_pressed.contains(&button)
+    }
+
+    /// Where a gamepad stick (or other axis) is, from -1.0 to 1.0, with up and right
+    /// positive. Anything close to the middle is 0.0, and the rest is stretched to
+    /// still reach all the way to ±1.0, so there's no jump at the edge of the
+    /// deadzone. 0.0 if there's no gamepad.
+    #[cfg(feature = "gamepad")]
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.pad_axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() < DEADZONE {
+            0.0
+        } else {
+            value.signum() * ((value.abs() - DEADZONE) / (1.0 - DEADZONE)).min(1.0)
+        }
+    }
+
+    // Fold one keyboard event into the state
+    pub(crate) fn handle_key(&mut self, event: &KeyEvent) {
+        let key = event.logical_key.clone();
+        if event.state.is_pressed() {
+            // Typing counts repeats, so it's handled before throwing them away
+            match &key {
+                Key::Named(NamedKey::Backspace) => self.backspaces += 1,
+                Key::Named(NamedKey::Enter) => self.enter_typed = true,
+                _ => if let Some(text) = &event.text {
+                    self.handle_text(text)
+                }
+            }
+
+            // Repeats are the OS re-sending a key that's still held, not new presses
+            if !event.repeat && self.keys_down.insert(key.clone()) {
+                self.keys_pressed.insert(key);
+            }
+        } else if self.keys_down.remove(&key) {
+            self.keys_released.insert(key);
+        }
+
+        // Physical keys are tracked separately: the same key can come back as a
+        // different logical key if a modifier changed while it was down
+        if let PhysicalKey::Code(code) = event.physical_key {
+            if !event.state.is_pressed() {
+                self.physical_down.remove(&code);
+            } else if !event.repeat && self.physical_down.insert(code) {
+                self.physical_pressed.insert(code);
+            }
+        }
+    }
+
+    // Fold one mouse wheel event into the state
+    pub(crate) fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x, y),
+            MouseScrollDelta::PixelDelta(pos) => ((pos.x / PIXELS_PER_LINE) as f32, (pos.y / PIXELS_PER_LINE) as f32),
+        };
+        self.scroll.0 += x;
+        self.scroll.1 += y
+    }
+
+    // Typed text, from a key or from an input method. Control characters (tab,
+    // delete, and so on come through as text on some platforms) are dropped.
+    pub(crate) fn handle_text(&mut self, text: &str) {
+        self.text.extend(text.chars().filter(|c| !c.is_control()))
+    }
+
+    // The OS tells us about modifiers separately from the keys themselves, which is
+    // more reliable (it knows about ones that were held down before we had focus)
+    pub(crate) fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers
+    }
+
+    // Fold one mouse button event into the state
+    pub(crate) fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        if state.is_pressed() {
+            if self.buttons_down.insert(button) {
+                self.buttons_clicked.insert(button);
+                if let Some(pos) = self.mouse_pos {
+                    self.drags.insert(button, Drag { start: pos, last: pos });
+                }
+            }
+        } else {
+            self.buttons_down.remove(&button);
+            self.drags.remove(&button);
+        }
+    }
+
+    // Files being dragged over the window come one event per file, and so do
+    // dropped ones. Either dropping them or dragging them away ends the hover.
+    pub(crate) fn handle_file_hovered(&mut self, hovered: bool) {
+        self.file_hovered = hovered
+    }
+
+    pub(crate) fn handle_file_dropped(&mut self, path: &Path) {
+        self.dropped.push(path.to_path_buf());
+        self.file_hovered = false
+    }
+
+    // Fold one gamepad button event into the state
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn handle_pad_button(&mut self, button: GamepadButton, pressed: bool) {
+        if pressed {
+            if self.pad_buttons_down.insert(button) {
+                self.pad_buttons_pressed.insert(button);
+            }
+        } else {
+            self.pad_buttons_down.remove(&button);
+        }
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn set_pad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.pad_axes.insert(axis, value);
+    }
+
+    // The gamepad went away, so nothing on it is held any more
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn release_pad(&mut self) {
+        self.pad_buttons_down.clear();
+        self.pad_axes.clear();
+    }
+
+    // The Context does the conversion to pixel coordinates, since it has the Pixels
+    pub(crate) fn set_mouse_pos(&mut self, pos: Option<(u32, u32)>) {
+        self.mouse_pos = pos;
+        if let Some(pos) = pos {
+            for drag in self.drags.values_mut() {
+                drag.last = pos
+            }
+        }
+    }
+
+    // We won't hear about keys or buttons that come up while the window isn't
+    // focused, so treat losing focus as letting go of everything
+    pub(crate) fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.physical_down.clear();
+        self.modifiers = ModifiersState::empty();
+        self.buttons_down.clear();
+        self.drags.clear();
+    }
+
+    // Called after each update, so the edges only show up once
+    pub(crate) fn end_tick(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.physical_pressed.clear();
+        self.buttons_clicked.clear();
+        self.scroll = (0.0, 0.0);
+        self.text.clear();
+        self.backspaces = 0;
+        self.enter_typed = false;
+        self.dropped.clear();
+        #[cfg(feature = "gamepad")]
+        self.pad_buttons_pressed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use crate::app::render_headless_with_input;
+    use crate::{App, Canvas, Context, WindowConfig};
+    use super::*;
+
+    // Shift, ctrl, alt and logo, in each update
+    type Seen = Rc<RefCell<Vec<[bool; 4]>>>;
+
+    // An app that writes down which modifiers are held in each update
+    struct Modifiers(Seen);
+
+    impl App for Modifiers {
+        fn update(&mut self, ctx: &mut Context, _dt: Duration) {
+            let input = ctx.input();
+            self.0.borrow_mut().push([input.shift(), input.ctrl(), input.alt(), input.logo()])
+        }
+
+        fn draw(&mut self, _ctx: &Context, _canvas: &mut Canvas, _alpha: f32) {}
+    }
+
+    #[test]
+    fn shift_press_and_release() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let config = WindowConfig { pixel_size: (4, 4), ..WindowConfig::default() };
+        render_headless_with_input(config, 5, Modifiers(seen.clone()), |frame, input| match frame {
+            0 => input.set_modifiers(ModifiersState::SHIFT),
+            // Still held, with nothing new happening
+            1 => {}
+            2 => input.set_modifiers(ModifiersState::empty()),
+            3 => input.set_modifiers(ModifiersState::SHIFT | ModifiersState::CONTROL),
+            _ => input.release_all(),
+        });
+        let shift: Vec<_> = seen.borrow().iter().map(|m| m[0]).collect();
+        assert_eq!(shift, vec![true, true, false, true, false]);
+        assert_eq!(seen.borrow()[3], [true, true, false, false]);
+        assert_eq!(seen.borrow()[0], [true, false, false, false]);
+    }
+
+    #[test]
+    fn press_move_release_drag() {
+        let mut input = Input::default();
+        input.set_mouse_pos(Some((5, 5)));
+        assert_eq!(input.drag_delta(MouseButton::Left), None);
+
+        input.handle_mouse_button(MouseButton::Left, ElementState::Pressed);
+        assert_eq!(input.drag_start(MouseButton::Left), Some((5, 5)));
+        assert_eq!(input.drag_delta(MouseButton::Left), Some((0, 0)));
+        input.set_mouse_pos(Some((8, 3)));
+        assert_eq!(input.drag_delta(MouseButton::Left), Some((3, -2)));
+        // Only the held button is dragging
+        assert_eq!(input.drag_delta(MouseButton::Right), None);
+        // Off the buffer, it stays where it last was
+        input.set_mouse_pos(None);
+        assert_eq!(input.drag_delta(MouseButton::Left), Some((3, -2)));
+        input.set_mouse_pos(Some((1, 9)));
+        assert_eq!(input.drag_delta(MouseButton::Left), Some((-4, 4)));
+
+        input.handle_mouse_button(MouseButton::Left, ElementState::Released);
+        assert_eq!((input.drag_start(MouseButton::Left), input.drag_delta(MouseButton::Left)), (None, None));
+
+        // Pressed with the mouse off the buffer, there's nowhere to start from
+        input.set_mouse_pos(None);
+        input.handle_mouse_button(MouseButton::Left, ElementState::Pressed);
+        assert_eq!(input.drag_start(MouseButton::Left), None);
+    }
+}
@@ -0,0 +1,65 @@
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+
+// The bits of this that aren't specific to the demo live in here, so that using
+// the template doesn't mean copy-pasting the drawing code around.
+
+pub mod animation;
+pub mod app;
+pub mod atlas;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod canvas;
+pub mod caret;
+pub mod color;
+pub mod config;
+pub mod context;
+pub mod drawlist;
+pub mod error;
+pub mod font;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+pub mod input;
+pub mod palette;
+pub mod particles;
+mod post;
+pub mod recording;
+pub mod rng;
+mod scaling;
+pub mod screenshot;
+pub mod sprite;
+pub mod state;
+pub mod surface;
+pub mod texture;
+pub mod tilemap;
+pub mod vec2;
+mod timing;
+mod ui;
+
+pub use animation::{Animation, AnimationMode};
+pub use app::{App, render_headless, run_app};
+pub use atlas::{Atlas, AtlasBuilder, AtlasError};
+#[cfg(feature = "audio")]
+pub use audio::{Sound, SoundError};
+pub use canvas::{Canvas, Origin};
+pub use caret::Caret;
+pub use color::{BlendMode, Color, ColorParseError};
+pub use config::{PostEffect, PresentMode, ResolutionPolicy, ScalingMode, UpdateTiming, WindowConfig};
+pub use context::{Context, FrameStats, WindowKey};
+pub use drawlist::{DrawCommand, DrawList};
+pub use error::Error;
+pub use font::{measure_text, wrap_text};
+#[cfg(feature = "gamepad")]
+pub use gilrs::{Axis as GamepadAxis, Button as GamepadButton};
+pub use input::Input;
+pub use palette::PaletteCanvas;
+pub use particles::ParticleSystem;
+pub use recording::GifRecorder;
+pub use rng::Rng;
+pub use screenshot::ScreenshotError;
+pub use sprite::{Sprite, SpriteError, SpriteSheet};
+pub use state::{State, StateStack, Transition};
+pub use surface::Surface;
+pub use texture::RenderTexture;
+pub use tilemap::TileMap;
+pub use vec2::{Vec2f, Vec2i};
@@ -1,154 +1,138 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
-use std::time::{Duration, Instant};
-use pixels::{PixelsBuilder, SurfaceTexture, wgpu};
-use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::error::EventLoopError;
-use winit::event::{ElementState, Event, MouseButton, StartCause, WindowEvent};
-use winit::event_loop::{ControlFlow};
-
-// This is the logical size of the window, for winit. The window will actually
-// technically be 4x as many pixels as this, because of hidpi.
-const WIN_SIZE: (u32, u32) = (640, 480);
-
-// This is the logical size of the Pixels instance. This will get scaled up evenly
-// to match the size of the window, which will get scaled again to match the hidpi
-// factor. Confused yet?
-const PIX_SIZE: (u32, u32) = (320, 240);
-
-fn main() -> Result<(), EventLoopError> {
-    // We'll trigger an update and redraw this often. There's no real correct value here,
-    // it's just how often we want to update the game state (or whatever it is) but there
-    // is a wrong value: it turns out that specifying 15 milliseconds (about 60 hz) will
-    // drastically lengthen the time to draw a frame, due to vsync: rendering the Pixels
-    // will block until the next vsync, and our drawing will take nonzero time, so we'll
-    // end up always arriving late and waiting for the next redraw.
-    let timer_length = Duration::from_millis(20);
-
-    // winit now makes is track the mouse position ourselves...
-    let mut mouse_pos: (f64, f64) = (-1f64, -1f64);
-
-    // A window needs an event loop
-    let event_loop = winit::event_loop::EventLoop::new().expect("Failed to create event loop!");
-
-    // The window itself. We set a title, size, and a minimum size to restrict resizing.
-    // Resizing up is fine, pixels will scale; resizing down is problematic if we ever
-    // get smaller than the Pixels itself.
-    let window = winit::window::WindowBuilder::new()
-        .with_title("The Thing")
-        .with_inner_size(LogicalSize{ width: WIN_SIZE.0, height: WIN_SIZE.1 })
-        .with_min_inner_size(LogicalSize { width: PIX_SIZE.0, height: PIX_SIZE.1 })
-        .build(&event_loop)?;
+use std::time::Duration;
+use minimal_pixels::{App, Canvas, Color, Context, Error, GifRecorder, run_app, Sprite, Surface, WindowConfig, WindowKey};
+use winit::event::MouseButton;
+use winit::keyboard::NamedKey;
+use winit::window::CursorGrabMode;
+
+// The demo: all the window and event loop business is handled by run_app, so this is
+// just the state of the world (not much, yet), what to do with input, and how to draw it.
+#[derive(Default)]
+struct Demo {
+    // Whether we've got hold of the mouse
+    grabbed: bool,
+    // The little window with the frame timings in it, if it's open
+    inspector: Option<WindowKey>,
+    // F9 records the pixel buffer to a GIF
+    recorder: GifRecorder,
+    // The last PNG dropped on the window
+    preview: Option<Sprite>,
+}
 
-    // The Pixels instance. We need a backing surface texture the physical size of the window
-    // (meaning, the real actual physical size, post-hidpi-scaling) and then we can set stuff
-    // on it with a PixelsBuilder:
-    let mut pixels = {
-        let PhysicalSize { width, height } = window.inner_size();
-        let surface_texture = SurfaceTexture::new(width, height, &window);
-        PixelsBuilder::new(PIX_SIZE.0, PIX_SIZE.1, surface_texture)
-            .clear_color(wgpu::Color{ r: 0.1, g: 0.1, b: 0.15, a: 1.0 })
-            .build().expect("Failed to build pixels!")
-    };
+impl App for Demo {
+    fn update(&mut self, ctx: &mut Context, _dt: Duration) {
+        if ctx.input().was_key_pressed(NamedKey::Escape) {
+            ctx.quit()
+        }
 
-    event_loop.run(move |event, target| {
-        match event {
-            // Exit if we click the little x
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                window_id,
-            } if window_id == window.id() => { target.exit(); }
+        if ctx.input().alt() && ctx.input().was_key_pressed(NamedKey::Enter) {
+            let fullscreen = ctx.is_fullscreen();
+            ctx.set_fullscreen(!fullscreen)
+        }
 
-            // Redraw if it's redrawing time
-            Event::WindowEvent {
-                event: WindowEvent::RedrawRequested,
-                window_id,
-            } if window_id == window.id() => {
-                // First redraw stuff into pixels' rgba buffer,
-                // then have pixels draw itself into our scaled offset buffer:
-                draw(pixels.frame_mut());
-                pixels.render().unwrap()
+        if ctx.input().was_key_pressed(NamedKey::F12) {
+            match ctx.save_screenshot("screenshot.png") {
+                Ok(()) => println!("Saved screenshot.png"),
+                Err(err) => println!("Couldn't save screenshot: {}", err),
             }
+        }
 
-            // Start the timer on init
-            Event::NewEvents(StartCause::Init) => {
-                target.set_control_flow(ControlFlow::WaitUntil(Instant::now() + timer_length));
+        if ctx.input().was_key_pressed(NamedKey::F9) {
+            if self.recorder.is_recording() {
+                match self.recorder.finish("recording.gif") {
+                    Ok(()) => println!("Saved recording.gif"),
+                    Err(err) => println!("Couldn't save recording: {}", err),
+                }
+            } else {
+                println!("Recording...");
+                self.recorder.start()
             }
+        }
+        self.recorder.capture(ctx);
 
-            // When the timer fires, update the world, redraw thw window based on that,
-            // and restart the timer
-            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
-                update();
-                window.request_redraw();
-                target.set_control_flow(ControlFlow::WaitUntil(Instant::now() + timer_length));
-            }
+        // F1 swaps the OS cursor for one we draw ourselves, F2 keeps it in the window
+        if ctx.input().was_key_pressed(NamedKey::F1) {
+            let visible = ctx.is_cursor_visible();
+            ctx.set_cursor_visible(!visible)
+        }
 
-            // Update that the mouse moved if it did
-            Event::WindowEvent {
-                event: WindowEvent::CursorMoved { position: pos, device_id: _ },
-                window_id
-            } if window_id == window.id() => {
-                // Remember that there are two layers of scaling going on here, and this position
-                // is after both of them: pos is two f64s in physical pixel coordinates.
-                // To get a point in the WIN_SIZE space (in other words, to remove the hidpi
-                // scaling only): pos.to_logical(window.scale_factor());
-                // But it's probably more useful to store the raw physical point because
-                // pixels.window_pos_to_pixel can remove both layers of scaling at once:
-                mouse_pos = (pos.x, pos.y);
+        if ctx.input().was_key_pressed(NamedKey::F2) {
+            let mode = if self.grabbed { CursorGrabMode::None } else { CursorGrabMode::Confined };
+            // Some platforms can only lock the cursor, not confine it
+            let result = ctx.set_cursor_grab(mode).or_else(|_| match mode {
+                CursorGrabMode::Confined => ctx.set_cursor_grab(CursorGrabMode::Locked),
+                _ => Ok(())
+            });
+            match result {
+                Ok(()) => self.grabbed = !self.grabbed,
+                Err(err) => println!("Couldn't grab the cursor: {}", err),
             }
+        }
 
-            // Do something if the mouse was clicked
-            Event::WindowEvent {
-                window_id, event: WindowEvent::MouseInput { device_id: _, state: ElementState::Pressed, button: MouseButton::Left }
-            } if window_id == window.id() => {
-                println!("Mouse clicked:");
-                println!("\tPhysical: {}, {}", mouse_pos.0, mouse_pos.1);
-                if let Ok((px, py)) = pixels.window_pos_to_pixel((mouse_pos.0 as f32, mouse_pos.1 as f32)) {
-                    println!("\tPixels: {}, {}", px, py)
-                } else {
-                    println!("\tNot within Pixels space!")
-                }
+        // F3 opens (or closes) a second window with the frame timings in it
+        if ctx.input().was_key_pressed(NamedKey::F3) {
+            match self.inspector.filter(|&key| ctx.is_window_open(key)) {
+                Some(key) => { ctx.close_window(key); self.inspector = None }
+                None => self.inspector = Some(ctx.open_window(WindowConfig {
+                    title: "Frame timings".into(),
+                    window_size: (320, 80),
+                    pixel_size: (160, 40),
+                    ..WindowConfig::default()
+                })),
             }
+        }
 
-            // Handle keyboard events
-            Event::WindowEvent {
-                window_id, event: WindowEvent::KeyboardInput { event, .. }
-            } if window_id == window.id() => {
-                println!("{} {:?} ({}repeat)",
-                         if event.state.is_pressed() { "Pressed" } else { "Released" },
-                         event.logical_key,
-                         if event.repeat { "" } else { "not " })
+        // Drop a PNG on the window to see it
+        for path in ctx.dropped_files() {
+            match std::fs::read(path).map(|bytes| Sprite::from_png_bytes(&bytes)) {
+                Ok(Ok(sprite)) => self.preview = Some(sprite),
+                Ok(Err(err)) => println!("Couldn't load {}: {}", path.display(), err),
+                Err(err) => println!("Couldn't read {}: {}", path.display(), err),
             }
+        }
 
-            // Resize the texture when the window resizes (this will also handle rescaling
-            // the Pixels instance)
-            Event::WindowEvent {
-                window_id, event: WindowEvent::Resized(new_size)
-            } if window_id == window.id() => {
-                println!("Resized to {}, {}", new_size.width, new_size.height);
-                pixels.resize_surface(new_size.width, new_size.height).expect("Resize surface failure")
+        if ctx.input().was_mouse_clicked(MouseButton::Left) {
+            if let Some((px, py)) = ctx.mouse_pos() {
+                println!("Mouse clicked at {}, {}", px, py)
+            } else {
+                println!("Mouse clicked, not within Pixels space!")
             }
-
-            // Drop other events
-            _ => {}
         }
-    })
-}
+    }
 
-// Called to draw the window. It's just a big slice of RGBA bytes, PIX_SIZE in
-// dimensions.
-fn draw(frame: &mut [u8]) {
-    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-        let x = (i % PIX_SIZE.0 as usize) as i16;
-        let y = (i / PIX_SIZE.0 as usize) as i16;
+    // Called to draw the window. The canvas wraps the big slice of RGBA bytes, the
+    // size of the pixel buffer, so we can just draw things at (x, y) coordinates.
+    fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, _alpha: f32) {
+        canvas.clear(Color::BLACK);
+        canvas.fill_rect(51, 51, 49, 49, Color::rgba(0xff, 0xff, 0x50, 0xff));
+        canvas.draw_text(2, 2, &format!("{:.0} fps", ctx.fps()), Color::WHITE);
+        if let Some(preview) = &self.preview {
+            canvas.blit(preview, 110, 20)
+        }
+        if ctx.is_file_hovered() {
+            let (w, h) = (canvas.width(), canvas.height());
+            canvas.draw_rect(0, 0, w, h, Color::rgb(0xff, 0xff, 0x50))
+        }
 
-        if x > 50 && x < 100 && y > 50 && y < 100 {
-            pixel.copy_from_slice(&[0xff, 0xff, 0x50, 0xff])
+        // Our own cursor, when the OS one is hidden
+        if let (false, Some((x, y))) = (ctx.is_cursor_visible(), ctx.mouse_pos()) {
+            canvas.hline(x - 3, y, 7, Color::WHITE);
+            canvas.vline(x, y - 3, 7, Color::WHITE)
         }
     }
+
+    fn draw_window(&mut self, ctx: &Context, _window: WindowKey, canvas: &mut Canvas, _alpha: f32) {
+        let stats = ctx.frame_stats();
+        canvas.clear(Color::BLACK);
+        canvas.draw_text(2, 2, &format!("update {:?}", stats.update_time), Color::WHITE);
+        canvas.draw_text(2, 12, &format!("draw {:?}", stats.draw_time), Color::WHITE);
+        canvas.draw_text(2, 22, &format!("present {:?}", stats.present_time), Color::WHITE);
+        canvas.draw_frame_graph((100, 2, 58, 36), ctx.frame_times(), Duration::from_micros(16_667));
+    }
 }
 
-fn update() {
-    // Do nothing
-}
\ No newline at end of file
+fn main() -> Result<(), Error> {
+    run_app(Demo::default(), WindowConfig::default())
+}
@@ -0,0 +1,115 @@
+// Indexed color, the way old hardware did it: each pixel is one byte, which picks
+// one of 256 palette entries. Changing a palette entry changes every pixel that
+// uses it, which is how palette cycling (waterfalls, glowing lava) works for free.
+// Pixels still wants RGBA, so once a frame it all gets looked up into the real
+// frame with resolve_into.
+
+use crate::Color;
+
+/// A `width` by `height` buffer of palette indices, plus the palette.
+pub struct PaletteCanvas {
+    width: u32,
+    height: u32,
+    indices: Vec<u8>,
+    palette: [Color; 256],
+}
+
+impl PaletteCanvas {
+    /// Every pixel starts at index 0, and the palette starts as a ramp of grays
+    /// from black (0) to white (255).
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut palette = [Color::BLACK; 256];
+        for (i, entry) in palette.iter_mut().enumerate() {
+            *entry = Color::rgb(i as u8, i as u8, i as u8)
+        }
+        Self { width, height, indices: vec![0; width as usize * height as usize], palette }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Set one pixel to a palette index. Coordinates off the canvas are ignored.
+    pub fn set_index(&mut self, x: i32, y: i32, idx: u8) {
+        if let Some(i) = self.offset(x, y) {
+            self.indices[i] = idx
+        }
+    }
+
+    /// The palette index of one pixel, or `None` if it's off the canvas
+    pub fn get_index(&self, x: i32, y: i32) -> Option<u8> {
+        self.offset(x, y).map(|i| self.indices[i])
+    }
+
+    /// Set every pixel to the same index
+    pub fn clear(&mut self, idx: u8) {
+        self.indices.fill(idx)
+    }
+
+    pub fn set_palette_entry(&mut self, idx: u8, color: Color) {
+        self.palette[idx as usize] = color
+    }
+
+    pub fn palette_entry(&self, idx: u8) -> Color {
+        self.palette[idx as usize]
+    }
+
+    /// The whole palette, for changing several entries at once. Cycling a range of
+    /// colors is `palette_mut()[16..32].rotate_left(1)`.
+    pub fn palette_mut(&mut self) -> &mut [Color; 256] {
+        &mut self.palette
+    }
+
+    /// Look every pixel up in the palette and write the colors into an RGBA frame
+    /// the same size as this, like `pixels.frame_mut()`.
+    pub fn resolve_into(&self, frame: &mut [u8]) {
+        assert_eq!(frame.len(), self.indices.len() * 4, "Frame doesn't match palette canvas size");
+        for (px, &idx) in frame.chunks_exact_mut(4).zip(&self.indices) {
+            px.copy_from_slice(&self.palette[idx as usize].as_bytes())
+        }
+    }
+
+    fn offset(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            None
+        } else {
+            Some(y as usize * self.width as usize + x as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_looks_up_the_palette() {
+        let mut canvas = PaletteCanvas::new(3, 2);
+        canvas.set_palette_entry(1, Color::RED);
+        canvas.set_palette_entry(2, Color::rgba(1, 2, 3, 4));
+        canvas.set_index(1, 0, 1);
+        canvas.set_index(2, 1, 2);
+        canvas.set_index(0, 1, 200);
+        canvas.set_index(3, 0, 1);
+        canvas.set_index(-1, 0, 1);
+
+        let mut frame = vec![0u8; 3 * 2 * 4];
+        canvas.resolve_into(&mut frame);
+        let mut expected = vec![];
+        for color in [Color::BLACK, Color::RED, Color::BLACK, Color::rgb(200, 200, 200), Color::BLACK, Color::rgba(1, 2, 3, 4)] {
+            expected.extend_from_slice(&color.as_bytes())
+        }
+        assert_eq!(frame, expected);
+
+        // Cycling the palette changes the pixels without touching the indices
+        canvas.palette_mut()[1..3].rotate_left(1);
+        canvas.resolve_into(&mut frame);
+        assert_eq!(&frame[4..8], &[1, 2, 3, 4]);
+        assert_eq!(canvas.get_index(1, 0), Some(1));
+        assert_eq!(canvas.get_index(3, 0), None);
+    }
+}
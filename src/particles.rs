@@ -0,0 +1,172 @@
+// A ParticleSystem is a pile of little dots that fly off, fall, and disappear:
+// sparks, smoke, debris. Each one is just a position, a velocity, a color and a
+// clock, so they're cheap enough to have hundreds of. The storage is allocated once,
+// up front, and dead particles' slots are reused, so a fountain of sparks running
+// for an hour never allocates after the first frame.
+
+use std::time::Duration;
+use crate::{Color, Surface};
+
+#[derive(Copy, Clone, Debug)]
+struct Particle {
+    pos: (f32, f32),
+    velocity: (f32, f32),
+    color: Color,
+    age: Duration,
+    lifetime: Duration,
+}
+
+/// A set of particles, at most `max` of them alive at once.
+#[derive(Clone, Debug)]
+pub struct ParticleSystem {
+    // Only live particles are in here; a dead one is swapped out for the last one
+    particles: Vec<Particle>,
+    max: usize,
+    gravity: (f32, f32),
+    size: u32,
+}
+
+impl ParticleSystem {
+    /// An empty system with room for `max` particles. There's no gravity, and
+    /// particles are drawn one pixel big, until you say otherwise.
+    pub fn new(max: usize) -> Self {
+        Self { particles: Vec::with_capacity(max), max, gravity: (0.0, 0.0), size: 1 }
+    }
+
+    /// How much every particle speeds up each second, in pixels per second, as
+    /// (x, y). Positive y is down, so something like (0.0, 200.0) makes things fall.
+    pub fn set_gravity(&mut self, gravity: (f32, f32)) {
+        self.gravity = gravity
+    }
+
+    /// How big to draw each particle: a square this many pixels across, centered
+    /// on its position. Zero hides them all.
+    pub fn set_particle_size(&mut self, size: u32) {
+        self.size = size
+    }
+
+    /// Add a particle at `pos`, moving at `velocity` pixels per second, that lasts
+    /// for `lifetime`. If there are already `max` particles, the new one is dropped
+    /// (and this returns false) rather than replacing an old one.
+    pub fn spawn(&mut self, pos: (f32, f32), velocity: (f32, f32), color: Color, lifetime: Duration) -> bool {
+        if self.particles.len() >= self.max { return false }
+        self.particles.push(Particle { pos, velocity, color, age: Duration::ZERO, lifetime });
+        true
+    }
+
+    /// Move everything along by `dt`, like the `dt` from `App::update`: gravity
+    /// changes the velocities, the velocities change the positions, and the
+    /// particles that have lived out their lifetimes go away.
+    pub fn update(&mut self, dt: Duration) {
+        let secs = dt.as_secs_f32();
+        let mut i = 0;
+        while i < self.particles.len() {
+            let p = &mut self.particles[i];
+            p.age += dt;
+            if p.age >= p.lifetime {
+                // Order doesn't matter, so this is quicker than remove
+                self.particles.swap_remove(i);
+                continue
+            }
+            p.velocity.0 += self.gravity.0 * secs;
+            p.velocity.1 += self.gravity.1 * secs;
+            p.pos.0 += p.velocity.0 * secs;
+            p.pos.1 += p.velocity.1 * secs;
+            i += 1
+        }
+    }
+
+    /// Draw every live particle. Anything that's drifted off the surface just isn't
+    /// drawn.
+    pub fn draw<S: Surface>(&self, surface: &mut S) {
+        let offset = (self.size / 2) as f32;
+        for p in &self.particles {
+            let (x, y) = ((p.pos.0 - offset).floor() as i32, (p.pos.1 - offset).floor() as i32);
+            match self.size {
+                0 => return,
+                1 => surface.set_pixel(x, y, p.color),
+                size => surface.fill_rect(x, y, size, size, p.color),
+            }
+        }
+    }
+
+    /// How many particles are alive
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// The most particles that can be alive at once
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Get rid of every particle
+    pub fn clear(&mut self) {
+        self.particles.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Canvas;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn particles_expire() {
+        let mut system = ParticleSystem::new(10);
+        system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS * 100);
+        system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS * 250);
+        assert_eq!(system.len(), 2);
+        system.update(MS * 99);
+        assert_eq!(system.len(), 2);
+        // Gone as soon as its age reaches its lifetime
+        system.update(MS);
+        assert_eq!(system.len(), 1);
+        system.update(MS * 150);
+        assert!(system.is_empty())
+    }
+
+    #[test]
+    fn spawning_past_max_is_dropped() {
+        let mut system = ParticleSystem::new(2);
+        assert!(system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS));
+        assert!(system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS * 10));
+        assert!(!system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS * 10));
+        // A slot freed up by one expiring gets used again
+        system.update(MS);
+        assert!(system.spawn((0.0, 0.0), (0.0, 0.0), Color::WHITE, MS * 10));
+        assert_eq!((system.len(), system.max()), (2, 2))
+    }
+
+    #[test]
+    fn particles_move_and_fall() {
+        let mut system = ParticleSystem::new(1);
+        system.set_gravity((0.0, 10.0));
+        system.spawn((1.0, 1.0), (4.0, 0.0), Color::RED, Duration::from_secs(10));
+        // Half a second: 2 px right, and gravity has it moving down 5 px/s
+        system.update(MS * 500);
+        let mut buf = vec![0; 8 * 8 * 4];
+        let mut canvas = Canvas::new(&mut buf, 8, 8);
+        system.draw(&mut canvas);
+        assert_eq!(canvas.get_pixel(3, 3), Some(Color::RED));
+        assert_eq!(buf.chunks_exact(4).filter(|px| px[3] != 0).count(), 1)
+    }
+
+    #[test]
+    fn particle_size() {
+        let mut system = ParticleSystem::new(1);
+        system.spawn((4.0, 4.0), (0.0, 0.0), Color::RED, Duration::from_secs(1));
+        for (size, lit) in [(0, 0), (1, 1), (3, 9)] {
+            system.set_particle_size(size);
+            let mut buf = vec![0; 8 * 8 * 4];
+            system.draw(&mut Canvas::new(&mut buf, 8, 8));
+            assert_eq!(buf.chunks_exact(4).filter(|px| px[3] != 0).count(), lit, "size {}", size)
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Post-processing. Pixels normally scales the pixel buffer straight onto the window;
+// with an effect on, we have it scale into a texture of our own instead, the size of
+// the window, and then run a second pass that draws that onto the window with the
+// effect applied. The pixel buffer itself never changes.
+
+use pixels::{wgpu, Pixels, PixelsContext};
+use crate::PostEffect;
+use crate::scaling::{self, Scaler};
+
+pub(crate) struct PostRenderer {
+    // The scaled-up picture, before the effect
+    scaled: wgpu::Texture,
+    scaled_view: wgpu::TextureView,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl PostRenderer {
+    /// Set up the renderer for `effect` on a window `width` by `height` (physical)
+    /// pixels, or `None` if there's nothing to do.
+    pub(crate) fn new(pixels: &Pixels, effect: PostEffect, width: u32, height: u32) -> Option<Self> {
+        let entry_point = match effect {
+            PostEffect::None => return None,
+            PostEffect::Scanlines { bleed: false } => "fs_scanlines",
+            PostEffect::Scanlines { bleed: true } => "fs_scanlines_bleed",
+        };
+
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("scanlines.wgsl"));
+
+        // We don't need a sampler, the shader reads exact texels
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("post_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The triangle comes from the vertex index, so there's no vertex buffer
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("post_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState { module: &module, entry_point: "vs_main", buffers: &[] },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: pixels.surface_texture_format(),
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let (scaled, scaled_view, bind_group) =
+            create_target(device, &bind_group_layout, pixels.render_texture_format(), width, height);
+        Some(Self { scaled, scaled_view, bind_group_layout, bind_group, pipeline })
+    }
+
+    /// The window changed size, so the texture we scale into has to too.
+    pub(crate) fn resize(&mut self, pixels: &Pixels, width: u32, height: u32) {
+        (self.scaled, self.scaled_view, self.bind_group) =
+            create_target(pixels.device(), &self.bind_group_layout, self.scaled.format(), width, height);
+    }
+
+    /// This is the function for `Pixels::render_with`. The scaling into our texture
+    /// is done by `scaler`, if there is one, or by Pixels.
+    pub(crate) fn render(&self, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView, context: &PixelsContext, scaler: Option<&Scaler>) {
+        scaling::render(scaler, encoder, &self.scaled_view, context);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+// The texture the scaling renderer draws into, and the bind group that hands it to
+// the effect shader. A minimized window can be 0x0, which wgpu won't make a texture
+// for, so it's always at least 1x1.
+fn create_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::TextureView, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("post_scaled_texture"),
+        size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("post_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) }],
+    });
+    (texture, view, bind_group)
+}
@@ -0,0 +1,121 @@
+// Recording the pixel buffer to an animated GIF. Frames are grabbed from update,
+// the same way a screenshot is, so they're the buffer as the app drew it (no
+// scaling, no post effect), and they come at the update rate, which means the GIF
+// plays back at the same speed the app ran at. Everything's kept in memory until
+// it's written out, so there's a limit on how many frames that can be.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use crate::{Context, ScreenshotError};
+
+// GIF delays are in hundredths of a second, and a lot of viewers treat anything
+// under two of those as "as slow as we feel like", so frames never go faster
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// Records what's in the pixel buffer into an animated GIF: `start` it, call
+/// `capture` every update, and `finish` to write the file. A GIF can only have
+/// 256 colors in each frame, so busy frames get approximated.
+pub struct GifRecorder {
+    max_frames: usize,
+    recording: bool,
+    frames: Vec<Vec<u8>>,
+    // The size of the first frame; frames in a GIF are all the same size
+    size: (u32, u32),
+    // Keep one update in this many, so there's at least MIN_DELAY between frames
+    every: u32,
+    updates: u32,
+    delay: Duration,
+}
+
+impl GifRecorder {
+    /// How many frames a default recorder keeps: 20 seconds, at 50 a second.
+    pub const DEFAULT_MAX_FRAMES: usize = 1000;
+
+    /// A recorder that stops capturing after `max_frames` frames. Each frame is a
+    /// whole copy of the pixel buffer, so at 320x240 a thousand frames is about
+    /// 300MB.
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            max_frames,
+            recording: false,
+            frames: vec![],
+            size: (0, 0),
+            every: 1,
+            updates: 0,
+            delay: MIN_DELAY,
+        }
+    }
+
+    /// Start a new recording, throwing away any frames that are left from the last
+    /// one.
+    pub fn start(&mut self) {
+        self.frames.clear();
+        self.recording = true;
+        self.updates = 0
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// How many frames have been captured so far.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Grab the pixel buffer as it was last drawn, if we're recording. Call this
+    /// once every update. With a fast update rate, only some updates are kept,
+    /// since a GIF can't go faster than 50 frames a second. If the buffer's
+    /// changed size since the first frame, or it's not available, the frame is
+    /// skipped.
+    pub fn capture(&mut self, ctx: &Context) {
+        if !self.recording { return }
+        if self.updates == 0 && self.frames.is_empty() {
+            let tick = ctx.tick.max(Duration::from_nanos(1));
+            self.every = MIN_DELAY.as_nanos().div_ceil(tick.as_nanos()).max(1) as u32;
+            self.delay = tick * self.every;
+            self.size = ctx.pixel_size
+        }
+        self.updates += 1;
+        if !(self.updates - 1).is_multiple_of(self.every) || ctx.pixel_size != self.size { return }
+
+        if self.frames.len() == self.max_frames {
+            log::warn!("GIF recording is full at {} frames, so it's stopped capturing", self.max_frames);
+            self.recording = false;
+            return
+        }
+        let Some(pixels) = &ctx.pixels else { return };
+        let Ok(pixels) = pixels.try_borrow() else { return };
+        self.frames.push(pixels.frame().to_vec())
+    }
+
+    /// Stop recording and write what was captured to a GIF file, which loops
+    /// forever. The frames are dropped once they're written. With no frames, there's
+    /// nothing to write, so that's `ScreenshotError::Unavailable`.
+    pub fn finish(&mut self, path: impl AsRef<Path>) -> Result<(), ScreenshotError> {
+        self.recording = false;
+        if self.frames.is_empty() { return Err(ScreenshotError::Unavailable) }
+        let (width, height) = self.size;
+        let delay = Delay::from_saturating_duration(self.delay);
+
+        let file = BufWriter::new(File::create(path.as_ref())?);
+        let mut encoder = GifEncoder::new(file);
+        encoder.set_repeat(Repeat::Infinite)?;
+        for frame in self.frames.drain(..) {
+            // The frame's the right size for the image by construction
+            let image = RgbaImage::from_raw(width, height, frame).unwrap();
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?
+        }
+        Ok(())
+    }
+}
+
+impl Default for GifRecorder {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_FRAMES)
+    }
+}
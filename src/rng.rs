@@ -0,0 +1,107 @@
+// A small random number generator, so a demo can have some randomness without
+// pulling in a crate for it. It's PCG32 (the XSH-RR variant, from pcg-random.org):
+// 64 bits of state, 32-bit results, quick, and plenty random enough for games.
+// It's not for anything to do with security.
+//
+// The same seed always gives the same numbers, on any platform, which is what makes
+// things like render_headless tests repeatable.
+
+// PCG's multiplier, and the increment for the one stream we use (any odd number
+// would do)
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+/// A seeded random number generator. Every `Rng` made from the same seed hands out
+/// the same numbers in the same order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// The seed a default `Rng` (and so `Context::rng`) starts from.
+    pub const DEFAULT_SEED: u64 = 0x853c49e6748fea9b;
+
+    pub fn new(seed: u64) -> Self {
+        // The seed goes in between two steps, the way the reference version does
+        // it, so that similar seeds don't start off giving similar numbers
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Any `u32` at all, all equally likely.
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// A number from `min` up to, but not including, `max`, all equally likely. If
+    /// `max` isn't more than `min`, it's always `min`.
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min { return min }
+        let span = (max as i64 - min as i64) as u64;
+        // Scale a random u32 up to the span, and the top half is our number. Some
+        // numbers would come up once more often than the others that way, so the
+        // few results that cause that get thrown out and we try again (Lemire's
+        // method).
+        let reject = (1u64 << 32) % span;
+        loop {
+            let m = self.next_u32() as u64 * span;
+            if m & 0xffff_ffff >= reject {
+                return (min as i64 + (m >> 32) as i64) as i32
+            }
+        }
+    }
+
+    /// A number from 0.0 up to, but not including, 1.0.
+    pub fn next_f32(&mut self) -> f32 {
+        // An f32 has 24 bits of precision, so that's how many we use
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_seed_fixed_sequence() {
+        let mut rng = Rng::new(42);
+        let numbers: Vec<u32> = (0..5).map(|_| rng.next_u32()).collect();
+        assert_eq!(numbers, vec![0xc2f57bd6, 0x6b07c4a9, 0x72b7b29b, 0x44215383, 0xf5af5ead]);
+        let mut rng = Rng::default();
+        assert_eq!(rng.next_u32(), 0xff597e28);
+        assert_eq!(Rng::new(42), Rng::new(42));
+        assert_ne!(Rng::new(42), Rng::new(43))
+    }
+
+    #[test]
+    fn ranges_stay_in_range() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let n = rng.range(-2, 3);
+            assert!((-2..3).contains(&n));
+            seen[(n + 2) as usize] = true
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(rng.range(4, 4), 4);
+        assert_eq!(rng.range(4, -10), 4);
+        // The whole i32 range doesn't overflow
+        rng.range(i32::MIN, i32::MAX);
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.next_f32()))
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Scaling the pixel buffer onto the window ourselves, for the scaling modes Pixels
+// doesn't do. The viewport is set to wherever the buffer should end up, so this
+// covers the whole viewport with one triangle and samples the buffer across it.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3), like the post effects' triangle. Texture
+    // coordinates go down where clip space goes up, so v is flipped.
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+@group(0) @binding(0) var r_frame: texture_2d<f32>;
+@group(0) @binding(1) var r_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(r_frame, r_sampler, in.uv);
+}
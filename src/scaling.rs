@@ -0,0 +1,292 @@
+// Getting the pixel buffer onto the window at some size other than the one Pixels
+// picks. Pixels only ever scales by whole numbers (and letterboxes the rest), which
+// is IntegerOnly with square pixels. For anything else we do the scaling pass
+// ourselves: clear the window to the border color, set the viewport to where the
+// buffer goes, and draw the buffer's texture into it with nearest-neighbor sampling.
+//
+// Pixels that aren't square are just the buffer being shown wider (or taller) than
+// it is: the sums here are all done on that stretched size, and the viewport
+// squashes the square-pixeled texture into it. The short side of each pixel still
+// gets a whole-number scale with IntegerOnly; the long side can't, in general.
+
+use pixels::{wgpu, Pixels, PixelsContext};
+use crate::{Color, ScalingMode};
+
+pub(crate) struct Scaler {
+    mode: ScalingMode,
+    buffer_size: (u32, u32),
+    pixel_aspect: f32,
+    surface_size: (u32, u32),
+    clear_color: wgpu::Color,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Scaler {
+    /// Set up scaling a `buffer_size` pixel buffer onto a window `width` by
+    /// `height` (physical) pixels, or `None` if Pixels can do it by itself.
+    pub(crate) fn new(pixels: &Pixels, mode: ScalingMode, buffer_size: (u32, u32), pixel_aspect: f32, clear_color: Color, width: u32, height: u32) -> Option<Self> {
+        if mode == ScalingMode::IntegerOnly && stretch(pixel_aspect) == (1.0, 1.0) { return None }
+
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("scale.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("scale_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // The buffer's texture only changes if the buffer's resized, and then we're
+        // made again, so we can bind it once here. Nearest-neighbor keeps the pixels sharp, even if
+        // they don't all come out the same size.
+        let view = pixels.context().texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("scale_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..wgpu::SamplerDescriptor::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scale_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("scale_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // This draws wherever Pixels' own scaling renderer would, either the window
+        // or a post effect's texture, which are both the render texture format
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scale_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState { module: &module, entry_point: "vs_main", buffers: &[] },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: pixels.render_texture_format(),
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Some(Self {
+            mode,
+            buffer_size,
+            pixel_aspect,
+            surface_size: (width, height),
+            clear_color: clear_color.into(),
+            bind_group,
+            pipeline,
+        })
+    }
+
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.surface_size = (width, height)
+    }
+
+    fn render(&self, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("scale_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(self.clear_color), store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+        // A minimized window has nowhere to draw to
+        let Some((x, y, w, h)) = scaled_region(self.mode, self.buffer_size, self.pixel_aspect, self.surface_size) else { return };
+        if w < 1.0 || h < 1.0 { return }
+        pass.set_viewport(x, y, w, h, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Scale the pixel buffer onto `render_target`, with our scaler if there is one
+/// and Pixels' if not.
+pub(crate) fn render(scaler: Option<&Scaler>, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView, context: &PixelsContext) {
+    match scaler {
+        Some(scaler) => scaler.render(encoder, render_target),
+        None => context.scaling_renderer.render(encoder, render_target),
+    }
+}
+
+/// Where on a `surface` sized window a `buffer` sized pixel buffer goes, as (x, y,
+/// width, height) in physical pixels, with each pixel `pixel_aspect` times as wide
+/// as it's tall. `None` for `IntegerOnly` with square pixels, since that's Pixels'
+/// department.
+pub(crate) fn scaled_region(mode: ScalingMode, buffer: (u32, u32), pixel_aspect: f32, surface: (u32, u32)) -> Option<(f32, f32, f32, f32)> {
+    let (ax, ay) = stretch(pixel_aspect);
+    let (bw, bh) = (buffer.0.max(1) as f32 * ax, buffer.1.max(1) as f32 * ay);
+    let (sw, sh) = (surface.0 as f32, surface.1 as f32);
+    let scale = match mode {
+        ScalingMode::IntegerOnly if (ax, ay) == (1.0, 1.0) => return None,
+        ScalingMode::Stretch => return Some((0.0, 0.0, sw, sh)),
+        ScalingMode::Fit => (sw / bw).min(sh / bh),
+        // Like Pixels does it: never less than 1, so a window that's too small
+        // crops the buffer
+        ScalingMode::IntegerOnly => (sw / bw).min(sh / bh).floor().max(1.0),
+    };
+    let (w, h) = (bw * scale, bh * scale);
+    Some(((sw - w) / 2.0, (sh - h) / 2.0, w, h))
+}
+
+// How much to stretch the buffer each way for pixels `pixel_aspect` times as wide
+// as they're tall: whichever side's longer gets stretched, so the short side stays
+// a whole number of window pixels. Nonsense (zero, negative, NaN) is square.
+fn stretch(pixel_aspect: f32) -> (f32, f32) {
+    match pixel_aspect {
+        a if !(a.is_finite() && a > 0.0) => (1.0, 1.0),
+        a if a >= 1.0 => (a, 1.0),
+        a => (1.0, 1.0 / a),
+    }
+}
+
+/// Where on a `surface` sized window a `buffer` sized pixel buffer goes, like
+/// `scaled_region`, but for every mode, and rounded to whole pixels. For
+/// `IntegerOnly` this is the same sum Pixels does: the biggest whole scale that
+/// fits (but never less than 1, so a window too small for the buffer crops it, and
+/// the offset goes negative).
+pub(crate) fn viewport(mode: ScalingMode, buffer: (u32, u32), pixel_aspect: f32, surface: (u32, u32)) -> (i32, i32, u32, u32) {
+    match scaled_region(mode, buffer, pixel_aspect, surface) {
+        Some((x, y, w, h)) => (x.round() as i32, y.round() as i32, w.round() as u32, h.round() as u32),
+        None => {
+            let (bw, bh) = (buffer.0.max(1), buffer.1.max(1));
+            let scale = (surface.0 / bw).min(surface.1 / bh).max(1);
+            let (w, h) = (bw * scale, bh * scale);
+            ((surface.0 as i64 - w as i64).div_euclid(2) as i32, (surface.1 as i64 - h as i64).div_euclid(2) as i32, w, h)
+        }
+    }
+}
+
+// The biggest buffer ResolutionPolicy::MatchWindow makes. It's the most wgpu
+// allows a texture to be by default, and bigger than any screen you'd use this on.
+const MAX_MATCHED_SIDE: u32 = 8192;
+
+/// The size of buffer that `ResolutionPolicy::MatchWindow` wants for a window
+/// `surface` physical pixels in size, at a hidpi `scale_factor`.
+pub(crate) fn matched_buffer_size(surface: (u32, u32), scale_factor: f64) -> (u32, u32) {
+    let scale_factor = if scale_factor.is_finite() && scale_factor > 0.0 { scale_factor } else { 1.0 };
+    let side = |physical: u32| ((physical as f64 / scale_factor).round() as u32).clamp(1, MAX_MATCHED_SIDE);
+    (side(surface.0), side(surface.1))
+}
+
+/// Where the buffer is on the window, as (x, y, width, height) in physical pixels,
+/// for every mode: `scaled_region` where we do the scaling, and Pixels' whole-pixel
+/// `viewport` where Pixels does. This is what mouse positions get mapped through,
+/// so it has to be worked out from sizes alone: Pixels itself is busy being drawn
+/// into while the app's `draw` runs.
+pub(crate) fn pixel_region(mode: ScalingMode, buffer: (u32, u32), pixel_aspect: f32, surface: (u32, u32)) -> (f32, f32, f32, f32) {
+    scaled_region(mode, buffer, pixel_aspect, surface).unwrap_or_else(|| {
+        let (x, y, w, h) = viewport(mode, buffer, pixel_aspect, surface);
+        (x as f32, y as f32, w as f32, h as f32)
+    })
+}
+
+/// Where the middle of pixel (x, y) of the buffer is in the window, with the buffer
+/// in `region` of it. Taking that back through `window_pos_to_pixel` gives (x, y).
+pub(crate) fn pixel_to_window_pos(region: (f32, f32, f32, f32), buffer: (u32, u32), (x, y): (i32, i32)) -> (f32, f32) {
+    let (rx, ry, rw, rh) = region;
+    let (bw, bh) = (buffer.0.max(1) as f32, buffer.1.max(1) as f32);
+    (rx + (x as f32 + 0.5) * rw / bw, ry + (y as f32 + 0.5) * rh / bh)
+}
+
+/// Which pixel of the buffer is at `pos` in the window, if it's in `region` at all.
+pub(crate) fn window_pos_to_pixel(region: (f32, f32, f32, f32), buffer: (u32, u32), pos: (f64, f64)) -> Option<(u32, u32)> {
+    let (x, y, w, h) = region;
+    if w <= 0.0 || h <= 0.0 { return None }
+    let px = ((pos.0 as f32 - x) / w * buffer.0 as f32).floor();
+    let py = ((pos.1 as f32 - y) / h * buffer.1 as f32).floor();
+    if px < 0.0 || py < 0.0 || px >= buffer.0 as f32 || py >= buffer.1 as f32 { return None }
+    Some((px as u32, py as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every pixel of the buffer, through to the window and back, in every mode, for
+    // windows that fit it exactly, fit it with a border, and crop it
+    #[test]
+    fn pixel_to_window_and_back() {
+        let buffer = (16, 9);
+        for mode in [ScalingMode::IntegerOnly, ScalingMode::Fit, ScalingMode::Stretch] {
+            for aspect in [1.0, 1.25, 0.8] {
+                for surface in [(16, 9), (50, 40), (333, 200), (10, 6)] {
+                    let region = pixel_region(mode, buffer, aspect, surface);
+                    for y in 0..9 {
+                        for x in 0..16 {
+                            let (wx, wy) = pixel_to_window_pos(region, buffer, (x, y));
+                            let back = window_pos_to_pixel(region, buffer, (wx as f64, wy as f64));
+                            assert_eq!(back, Some((x as u32, y as u32)), "{:?} {} {:?}", mode, aspect, surface)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_only_region_matches_pixels() {
+        // 3x scale, centered, with the leftover split evenly
+        assert_eq!(pixel_region(ScalingMode::IntegerOnly, (10, 10), 1.0, (35, 32)), (2.0, 1.0, 30.0, 30.0));
+        // Too small: scale 1, cropped, so the offset goes negative
+        assert_eq!(pixel_region(ScalingMode::IntegerOnly, (10, 10), 1.0, (7, 10)), (-2.0, 0.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn outside_the_region_is_no_pixel() {
+        let region = pixel_region(ScalingMode::IntegerOnly, (10, 10), 1.0, (40, 30));
+        assert_eq!(window_pos_to_pixel(region, (10, 10), (4.0, 15.0)), None);
+        assert_eq!(window_pos_to_pixel(region, (10, 10), (5.0, 0.0)), Some((0, 0)));
+        assert_eq!(window_pos_to_pixel(region, (10, 10), (35.0, 15.0)), None);
+    }
+
+    #[test]
+    fn matched_buffer_sizes() {
+        assert_eq!(matched_buffer_size((800, 600), 1.0), (800, 600));
+        assert_eq!(matched_buffer_size((1600, 1200), 2.0), (800, 600));
+        // 1.5x rounds to the nearest whole pixel: 333.3 and 66.7
+        assert_eq!(matched_buffer_size((500, 100), 1.5), (333, 67));
+        // A minimized window is zero by zero, but the buffer never is
+        assert_eq!(matched_buffer_size((0, 0), 1.0), (1, 1));
+        assert_eq!(matched_buffer_size((100_000, 20), 1.0), (MAX_MATCHED_SIDE, 20));
+        // A nonsense scale factor counts as none
+        assert_eq!(matched_buffer_size((640, 480), 0.0), (640, 480));
+        assert_eq!(matched_buffer_size((640, 480), f64::NAN), (640, 480));
+    }
+}
@@ -0,0 +1,42 @@
+// The scanline effect. The scaled-up picture has already been drawn into a texture
+// the size of the window, so this just draws one triangle that covers the whole
+// window and works out each output pixel from the one under it.
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // (-1, -1), (3, -1), (-1, 3): a triangle big enough to cover the whole screen
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@group(0) @binding(0) var r_scaled: texture_2d<f32>;
+
+// How bright the darkened rows are
+const SCANLINE: f32 = 0.6;
+
+// How much of each neighbor bleeds into a pixel
+const BLEED: f32 = 0.15;
+
+fn darken(pos: vec2<i32>, color: vec4<f32>) -> vec4<f32> {
+    if (pos.y & 1) == 1 {
+        return vec4<f32>(color.rgb * SCANLINE, color.a);
+    }
+    return color;
+}
+
+@fragment
+fn fs_scanlines(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(position.xy);
+    return darken(pos, textureLoad(r_scaled, pos, 0));
+}
+
+@fragment
+fn fs_scanlines_bleed(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(position.xy);
+    let right = i32(textureDimensions(r_scaled).x) - 1;
+    let left_color = textureLoad(r_scaled, vec2<i32>(max(pos.x - 1, 0), pos.y), 0);
+    let right_color = textureLoad(r_scaled, vec2<i32>(min(pos.x + 1, right), pos.y), 0);
+    let color = textureLoad(r_scaled, pos, 0) * (1.0 - 2.0 * BLEED) + (left_color + right_color) * BLEED;
+    return darken(pos, color);
+}
@@ -0,0 +1,61 @@
+// Writing a frame out to a PNG. The frame is already RGBA bytes, which is exactly
+// what the PNG encoder wants, so this is mostly about errors.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use image::ImageEncoder;
+use image::codecs::png::PngEncoder;
+
+/// Things that can go wrong saving a screenshot.
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The file couldn't be created or written
+    Io(std::io::Error),
+    /// The frame couldn't be encoded as a PNG
+    Encode(image::ImageError),
+    /// There's no frame to save right now: there's no pixel buffer, or it's in the
+    /// middle of being drawn on
+    Unavailable,
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::Io(err) => write!(f, "couldn't write screenshot: {}", err),
+            ScreenshotError::Encode(err) => write!(f, "couldn't encode screenshot: {}", err),
+            ScreenshotError::Unavailable => write!(f, "the frame isn't available right now"),
+        }
+    }
+}
+
+impl Error for ScreenshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScreenshotError::Io(err) => Some(err),
+            ScreenshotError::Encode(err) => Some(err),
+            ScreenshotError::Unavailable => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ScreenshotError {
+    fn from(err: std::io::Error) -> Self {
+        ScreenshotError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for ScreenshotError {
+    fn from(err: image::ImageError) -> Self {
+        ScreenshotError::Encode(err)
+    }
+}
+
+// Save a width by height RGBA frame to a PNG file
+pub(crate) fn save_png(frame: &[u8], width: u32, height: u32, path: &Path) -> Result<(), ScreenshotError> {
+    let file = BufWriter::new(File::create(path)?);
+    PngEncoder::new(file).write_image(frame, width, height, image::ExtendedColorType::Rgba8)?;
+    Ok(())
+}
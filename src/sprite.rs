@@ -0,0 +1,320 @@
+// A Sprite is an image we own, as opposed to the frame, which Pixels owns. It's
+// stored the same way the frame is (rows of RGBA bytes, top to bottom) so drawing
+// one is mostly a matter of copying rows around.
+
+use std::error::Error;
+use std::fmt;
+use crate::Color;
+
+/// Owned RGBA pixels, `width` by `height`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sprite {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    // Whether every pixel has full alpha, which lets blitting skip transparency
+    opaque: bool,
+}
+
+/// Things that can go wrong making a sprite.
+#[derive(Debug)]
+pub enum SpriteError {
+    /// The image couldn't be decoded: it's corrupt, or not a PNG
+    Decode(image::ImageError),
+    /// The pixel data isn't `width * height * 4` bytes long
+    WrongSize { expected: usize, actual: usize },
+}
+
+impl fmt::Display for SpriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteError::Decode(err) => write!(f, "couldn't decode image: {}", err),
+            SpriteError::WrongSize { expected, actual } => {
+                write!(f, "expected {} bytes of pixel data, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl Error for SpriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpriteError::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for SpriteError {
+    fn from(err: image::ImageError) -> Self {
+        SpriteError::Decode(err)
+    }
+}
+
+impl Sprite {
+    /// Make a sprite out of raw RGBA bytes, which have to be exactly
+    /// `width * height * 4` long.
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Result<Self, SpriteError> {
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(SpriteError::WrongSize { expected, actual: data.len() })
+        }
+        Ok(Self::from_parts(width, height, data))
+    }
+
+    /// Decode a PNG, like one brought in with `include_bytes!`. Whatever color type
+    /// the PNG is (grayscale, RGB, paletted...) it gets converted to RGBA.
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, SpriteError> {
+        let img = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?.into_rgba8();
+        let (width, height) = img.dimensions();
+        Ok(Self::from_parts(width, height, img.into_raw()))
+    }
+
+    // Every constructor comes through here, so opaque is always right
+    fn from_parts(width: u32, height: u32, data: Vec<u8>) -> Self {
+        let opaque = data.chunks_exact(4).all(|px| px[3] == 0xff);
+        Self { width, height, data, opaque }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The raw RGBA bytes, a row at a time from the top
+    pub fn pixels(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Whether there are no transparent (or translucent) pixels at all
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    /// One pixel, or `None` if it's outside the sprite
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height { return None }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let mut px = [0u8; 4];
+        px.copy_from_slice(&self.data[i..i + 4]);
+        Some(px.into())
+    }
+
+    /// Copy a `w` by `h` rectangle, with its top-left corner at (x, y), out into a
+    /// new sprite of its own. Whatever part of the rectangle isn't on this sprite is
+    /// cut off, so the new one can be smaller than asked for (or even empty).
+    pub fn sub_sprite(&self, x: u32, y: u32, w: u32, h: u32) -> Sprite {
+        let (x, y) = (x.min(self.width), y.min(self.height));
+        let (w, h) = (w.min(self.width - x), h.min(self.height - y));
+        let (stride, len) = (self.width as usize * 4, w as usize * 4);
+        let mut data = Vec::with_capacity(len * h as usize);
+        for row in y as usize..(y + h) as usize {
+            let start = row * stride + x as usize * 4;
+            data.extend_from_slice(&self.data[start..start + len])
+        }
+        Self::from_parts(w, h, data)
+    }
+
+    /// A copy of this sprite with every pixel's color swapped for the nearest one in
+    /// `palette` (nearest as in the smallest squared distance in RGB). The palette's
+    /// alphas are ignored: each pixel keeps its own. With an empty palette, there's
+    /// nothing to swap to, so it's just a copy (and so is an empty sprite).
+    pub fn quantize_to_palette(&self, palette: &[Color]) -> Sprite {
+        self.quantize(palette, false)
+    }
+
+    /// Like `quantize_to_palette`, but with Floyd-Steinberg dithering: the
+    /// difference between each pixel and the color it got is spread onto the pixels
+    /// right and below it, so a gradient turns into a mix of the palette colors
+    /// instead of bands. Fully transparent pixels don't take part in that.
+    pub fn quantize_to_palette_dithered(&self, palette: &[Color]) -> Sprite {
+        self.quantize(palette, true)
+    }
+
+    fn quantize(&self, palette: &[Color], dither: bool) -> Sprite {
+        if palette.is_empty() || self.width == 0 || self.height == 0 { return self.clone() }
+        let width = self.width as usize;
+        let mut data = self.data.clone();
+        // The error still to be added to each pixel on this row and the next, in RGB,
+        // with a pixel's worth of room either side so the edges don't need checks
+        let mut errors = vec![[0f32; 3]; width + 2];
+        let mut next_errors = vec![[0f32; 3]; width + 2];
+
+        for row in data.chunks_exact_mut(width * 4) {
+            for (x, px) in row.chunks_exact_mut(4).enumerate() {
+                let err = if dither && px[3] != 0 { errors[x + 1] } else { [0.0; 3] };
+                let want = [0, 1, 2].map(|c| (px[c] as f32 + err[c]).clamp(0.0, 255.0));
+                let got = nearest(palette, want);
+                if dither && px[3] != 0 {
+                    let diff = [want[0] - got.r as f32, want[1] - got.g as f32, want[2] - got.b as f32];
+                    for c in 0..3 {
+                        errors[x + 2][c] += diff[c] * 7.0 / 16.0;
+                        next_errors[x][c] += diff[c] * 3.0 / 16.0;
+                        next_errors[x + 1][c] += diff[c] * 5.0 / 16.0;
+                        next_errors[x + 2][c] += diff[c] / 16.0;
+                    }
+                }
+                px[..3].copy_from_slice(&[got.r, got.g, got.b])
+            }
+            std::mem::swap(&mut errors, &mut next_errors);
+            next_errors.fill([0.0; 3])
+        }
+        Self::from_parts(self.width, self.height, data)
+    }
+}
+
+// The palette color closest to an RGB color; the palette can't be empty
+fn nearest(palette: &[Color], [r, g, b]: [f32; 3]) -> Color {
+    let distance = |c: &&Color| {
+        let (dr, dg, db) = (c.r as f32 - r, c.g as f32 - g, c.b as f32 - b);
+        dr * dr + dg * dg + db * db
+    };
+    *palette.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))).unwrap()
+}
+
+/// A sprite sheet: one image cut up into a grid of same-sized frames, like the
+/// frames of an animation. Frames are numbered left to right, then top to bottom.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    columns: u32,
+    rows: u32,
+    frames: Vec<Sprite>,
+}
+
+impl SpriteSheet {
+    /// Cut `sprite` up into frames `frame_width` by `frame_height`, starting from
+    /// the top left. If the sheet isn't an exact number of frames across (or down),
+    /// the bit left over on the right (or bottom) is ignored.
+    pub fn new(sprite: &Sprite, frame_width: u32, frame_height: u32) -> Self {
+        let columns = sprite.width().checked_div(frame_width).unwrap_or(0);
+        let rows = sprite.height().checked_div(frame_height).unwrap_or(0);
+        let frames = (0..rows)
+            .flat_map(|row| (0..columns).map(move |col| (col, row)))
+            .map(|(col, row)| sprite.sub_sprite(col * frame_width, row * frame_height, frame_width, frame_height))
+            .collect();
+        Self { columns, rows, frames }
+    }
+
+    /// How many frames across the sheet is
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// How many frames down the sheet is
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The frame in column `col` and row `row`, or `None` if that's off the sheet.
+    pub fn frame(&self, col: u32, row: u32) -> Option<&Sprite> {
+        if col >= self.columns || row >= self.rows { return None }
+        self.frames.get((row * self.columns + col) as usize)
+    }
+
+    /// Frame number `index`, counting across each row and then down, or `None` if
+    /// there aren't that many.
+    pub fn frame_at(&self, index: usize) -> Option<&Sprite> {
+        self.frames.get(index)
+    }
+}
+
+// Tests all over the crate want little sprites made up a pixel at a time
+#[cfg(test)]
+impl Sprite {
+    /// A `w` by `h` sprite with each pixel filled in by `f(x, y)`
+    pub(crate) fn from_fn(w: u32, h: u32, f: impl Fn(u32, u32) -> Color) -> Sprite {
+        let data = (0..h).flat_map(|y| (0..w).map(move |x| (x, y))).flat_map(|(x, y)| f(x, y).as_bytes());
+        Sprite::new(w, h, data.collect()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every pixel of a sprite, as colors
+    fn colors(sprite: &Sprite) -> Vec<Color> {
+        sprite.pixels().chunks_exact(4).map(|px| Color::rgba(px[0], px[1], px[2], px[3])).collect()
+    }
+
+    // A 2x2 PNG: red and half-transparent green on top, blue and transparent white
+    // underneath
+    const TINY_PNG: [u8; 78] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72, 0xb6, 0x0d,
+        0x24, 0x00, 0x00, 0x00, 0x15, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8, 0xcf, 0xc0, 0xf0,
+        0x1f, 0x08, 0x1b, 0x18, 0x80, 0x34, 0x08, 0x30, 0x00, 0x00, 0x43, 0xd3, 0x08, 0x79, 0x78, 0xce,
+        0x21, 0xcc, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn quantize_to_two_colors() {
+        let palette = [Color::BLACK, Color::WHITE];
+        let gradient = Sprite::from_fn(16, 4, |x, y| Color::rgb((x * 16) as u8, (y * 60) as u8, 0x80));
+        for quantized in [gradient.quantize_to_palette(&palette), gradient.quantize_to_palette_dithered(&palette)] {
+            assert!(colors(&quantized).iter().all(|c| palette.contains(c)));
+        }
+    }
+
+    #[test]
+    fn quantize_keeps_alpha() {
+        let faded = Sprite::from_fn(2, 1, |x, _| Color::rgba(0xf0, 0x10, 0x10, x as u8 * 100));
+        let quantized = faded.quantize_to_palette(&[Color::RED, Color::BLUE]);
+        assert_eq!(colors(&quantized), vec![Color::rgba(0xff, 0, 0, 0), Color::rgba(0xff, 0, 0, 100)]);
+    }
+
+    #[test]
+    fn quantize_empty_sprite() {
+        let empty = Sprite::new(0, 3, vec![]).unwrap();
+        assert_eq!(empty.quantize_to_palette(&[Color::BLACK]).pixels(), &[] as &[u8]);
+        assert_eq!(empty.quantize_to_palette_dithered(&[Color::BLACK]).height(), 3);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let sprite = Sprite::from_png_bytes(&TINY_PNG).unwrap();
+        assert_eq!((sprite.width(), sprite.height()), (2, 2));
+        assert_eq!(colors(&sprite), vec![Color::RED, Color::rgba(0, 0xff, 0, 0x80), Color::BLUE, Color::rgba(0xff, 0xff, 0xff, 0)]);
+        assert!(!sprite.is_opaque());
+
+        // And back out to a PNG, and in again
+        let img = image::RgbaImage::from_raw(2, 2, sprite.pixels().to_vec()).unwrap();
+        let mut png = std::io::Cursor::new(vec![]);
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        assert_eq!(Sprite::from_png_bytes(png.get_ref()).unwrap(), sprite);
+    }
+
+    #[test]
+    fn bad_sprites() {
+        assert!(matches!(Sprite::from_png_bytes(&TINY_PNG[..40]), Err(SpriteError::Decode(_))));
+        assert!(matches!(Sprite::new(2, 2, vec![0; 12]), Err(SpriteError::WrongSize { expected: 16, actual: 12 })));
+        assert!(Sprite::new(1, 1, vec![1, 2, 3, 0xff]).unwrap().is_opaque());
+    }
+
+    #[test]
+    fn sheet_frames() {
+        // Each pixel says where it is; 3x2 frames, with a column and a row left over
+        let sheet = Sprite::from_fn(7, 5, |x, y| Color::rgb(x as u8, y as u8, 0));
+        let frames = SpriteSheet::new(&sheet, 3, 2);
+        assert_eq!((frames.columns(), frames.rows(), frames.frame_count()), (2, 2, 4));
+        let frame = frames.frame(1, 1).unwrap();
+        assert_eq!((frame.width(), frame.height()), (3, 2));
+        assert_eq!(colors(frame), [(3, 2), (4, 2), (5, 2), (3, 3), (4, 3), (5, 3)].map(|(x, y)| Color::rgb(x, y, 0)).to_vec());
+        assert_eq!(frames.frame_at(3), Some(frame));
+        assert_eq!(frames.frame(2, 0), None);
+        assert_eq!(frames.frame_at(4), None);
+
+        // The embedded PNG, cut into single pixels
+        let png = SpriteSheet::new(&Sprite::from_png_bytes(&TINY_PNG).unwrap(), 1, 1);
+        assert_eq!(png.frame(0, 1).unwrap().get_pixel(0, 0), Some(Color::BLUE));
+        assert_eq!(Sprite::from_png_bytes(&TINY_PNG).unwrap().sub_sprite(1, 0, 5, 5).height(), 2);
+        assert_eq!(SpriteSheet::new(&sheet, 0, 2).frame_count(), 0);
+    }
+}
@@ -0,0 +1,235 @@
+// A stack of game screens: the title screen, the game, a pause menu over the game,
+// and so on. The stack is itself an App, so run_app runs it like anything else,
+// and it hands the updates and draws on to the states in it.
+//
+// States don't change the stack themselves. Each update says what it wants to
+// happen (push something, pop itself...) and the stack does all of that once the
+// updates are over, so no state's ever in the middle of an update while it's
+// being popped, and everything drawn in a frame is what was there after the last
+// update.
+
+use std::time::Duration;
+use crate::{App, Canvas, Context};
+
+/// One screen's worth of the game, to go in a `StateStack`. It's like an `App`,
+/// except that `update` says how the stack should change.
+pub trait State {
+    /// Advance this state one tick, like `App::update`, and say what should happen
+    /// to the stack afterwards.
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> Transition;
+
+    /// Draw this state, like `App::draw`. States under it have already drawn, if
+    /// they're showing; see `draws_below`.
+    fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, alpha: f32);
+
+    /// Whether the state under this one keeps updating while this one's on top of
+    /// it. By default it doesn't: a pause menu stops the game.
+    fn updates_below(&self) -> bool {
+        false
+    }
+
+    /// Whether the state under this one still gets drawn, before this one is. By
+    /// default it does, so a state that only draws part of the screen (a menu in a
+    /// box, or something translucent) shows over what's under it. A state that
+    /// covers everything can say no, and save drawing what'd be hidden anyway.
+    fn draws_below(&self) -> bool {
+        true
+    }
+}
+
+/// What a state wants done to the stack, after this update.
+pub enum Transition {
+    /// Nothing: carry on
+    None,
+    /// Put a new state on top
+    Push(Box<dyn State>),
+    /// Take the top state off, uncovering whatever's under it
+    Pop,
+    /// Swap the top state for a new one
+    Replace(Box<dyn State>),
+}
+
+/// A stack of `State`s, run as an `App`. Only the top state is updated, unless it
+/// says the one under it should be too (and so on down); all the states are drawn,
+/// bottom first, unless one says the ones under it shouldn't be. When the stack's
+/// empty, the app quits.
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+}
+
+impl StateStack {
+    /// A stack with just the one state in it.
+    pub fn new(first: impl State + 'static) -> Self {
+        Self { states: vec![Box::new(first)] }
+    }
+
+    /// Put a state on top, from outside any update (within one, return a
+    /// `Transition` instead).
+    pub fn push(&mut self, state: impl State + 'static) {
+        self.states.push(Box::new(state))
+    }
+
+    /// Take the top state off, and hand it back.
+    pub fn pop(&mut self) -> Option<Box<dyn State>> {
+        self.states.pop()
+    }
+
+    /// Swap the top state for `state`, handing back the old one. On an empty stack,
+    /// this is the same as `push`.
+    pub fn replace(&mut self, state: impl State + 'static) -> Option<Box<dyn State>> {
+        let old = self.states.pop();
+        self.states.push(Box::new(state));
+        old
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(state) => self.states.push(state),
+            Transition::Pop => { self.states.pop(); }
+            Transition::Replace(state) => {
+                self.states.pop();
+                self.states.push(state)
+            }
+        }
+    }
+}
+
+impl App for StateStack {
+    // The top state updates, then the one under it if it lets it, and so on. Their
+    // transitions are done after, in the same order: the top one's first.
+    fn update(&mut self, ctx: &mut Context, dt: Duration) {
+        let mut transitions = vec![];
+        for state in self.states.iter_mut().rev() {
+            transitions.push(state.update(ctx, dt));
+            if !state.updates_below() { break }
+        }
+        for transition in transitions {
+            self.apply(transition)
+        }
+        if self.states.is_empty() {
+            ctx.quit()
+        }
+    }
+
+    fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, alpha: f32) {
+        // Find the lowest state that's showing, and draw up from there
+        let bottom = self.states.iter().rposition(|state| !state.draws_below()).unwrap_or(0);
+        for state in &mut self.states[bottom..] {
+            state.draw(ctx, canvas, alpha)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use crate::app::render_headless;
+    use crate::WindowConfig;
+    use super::*;
+
+    // What happened, in order: (name, "update" or "draw")
+    type Log = Rc<RefCell<Vec<(&'static str, &'static str)>>>;
+
+    // A state that writes down its updates and draws, and does what its script says
+    // on each of its updates
+    struct Scripted {
+        name: &'static str,
+        log: Log,
+        script: VecDeque<Transition>,
+        updates_below: bool,
+        draws_below: bool,
+    }
+
+    impl Scripted {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Self { name, log: log.clone(), script: VecDeque::new(), updates_below: false, draws_below: true }
+        }
+
+        fn then(mut self, transition: Transition) -> Self {
+            self.script.push_back(transition);
+            self
+        }
+    }
+
+    impl State for Scripted {
+        fn update(&mut self, _ctx: &mut Context, _dt: Duration) -> Transition {
+            self.log.borrow_mut().push((self.name, "update"));
+            self.script.pop_front().unwrap_or(Transition::None)
+        }
+
+        fn draw(&mut self, _ctx: &Context, _canvas: &mut Canvas, _alpha: f32) {
+            self.log.borrow_mut().push((self.name, "draw"))
+        }
+
+        fn updates_below(&self) -> bool {
+            self.updates_below
+        }
+
+        fn draws_below(&self) -> bool {
+            self.draws_below
+        }
+    }
+
+    fn run(stack: StateStack, frames: usize) -> usize {
+        let config = WindowConfig { pixel_size: (4, 4), ..WindowConfig::default() };
+        render_headless(config, frames, stack).len()
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let log: Log = Rc::default();
+        // The game pushes a pause menu on its first update; the menu pops itself on
+        // its second
+        let menu = Scripted::new("menu", &log).then(Transition::None).then(Transition::Pop);
+        let game = Scripted::new("game", &log).then(Transition::Push(Box::new(menu)));
+        run(StateStack::new(game), 3);
+        assert_eq!(*log.borrow(), vec![
+            ("game", "update"), ("game", "draw"), ("menu", "draw"),
+            ("menu", "update"), ("game", "draw"), ("menu", "draw"),
+            ("menu", "update"), ("game", "draw"),
+        ]);
+    }
+
+    #[test]
+    fn updates_and_draws_below() {
+        let log: Log = Rc::default();
+        let mut stack = StateStack::new(Scripted::new("bottom", &log));
+        stack.push(Scripted { updates_below: true, ..Scripted::new("middle", &log) });
+        stack.push(Scripted { draws_below: false, ..Scripted::new("cover", &log) });
+        stack.push(Scripted { updates_below: true, ..Scripted::new("top", &log) });
+        run(stack, 1);
+        // Updates go down until one stops them; draws start from the lowest one
+        // that's showing
+        assert_eq!(*log.borrow(), vec![
+            ("top", "update"), ("cover", "update"),
+            ("cover", "draw"), ("top", "draw"),
+        ]);
+    }
+
+    #[test]
+    fn replace_and_empty_quits() {
+        let log: Log = Rc::default();
+        let second = Scripted::new("second", &log).then(Transition::Pop);
+        let first = Scripted::new("first", &log).then(Transition::Replace(Box::new(second)));
+        // The pop empties the stack, which quits before that update is drawn
+        assert_eq!(run(StateStack::new(first), 5), 1);
+        assert_eq!(*log.borrow(), vec![("first", "update"), ("second", "draw"), ("second", "update")]);
+
+        let mut stack = StateStack::default();
+        assert!(stack.is_empty() && stack.pop().is_none());
+        assert!(stack.replace(Scripted::new("a", &log)).is_none());
+        assert_eq!(stack.len(), 1);
+    }
+}
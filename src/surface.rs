@@ -0,0 +1,1472 @@
+// The things every kind of drawable image has in common, so that code which draws
+// doesn't have to care whether it's drawing on the screen or on a texture. A surface
+// only has to be able to set and read pixels; every shape is worked out here in
+// terms of those, plus fill_row for horizontal runs (which is most of filling
+// anything). A surface with a quicker way to do something, like Canvas clearing its
+// whole frame at once, can override that method.
+
+use std::ops::Range;
+use crate::{font, Color, Vec2f, Vec2i};
+
+// The 4x4 Bayer matrix: the order the sixteen pixels of a tile switch over in as a
+// dither goes from one color to the other. Each step turns on the pixel furthest
+// from the ones already on, so every level is as even a mix as it can be.
+const BAYER: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+/// Something with pixels that can be drawn on: the screen's `Canvas`, or a
+/// `RenderTexture`. Write drawing helpers generic over `S: Surface` (rather than
+/// taking a `&mut dyn Surface`) and they work on either, with no dynamic dispatch
+/// for every pixel.
+pub trait Surface {
+    fn width(&self) -> u32;
+
+    fn height(&self) -> u32;
+
+    /// Set one pixel. Coordinates off the surface are quietly ignored, so it's fine
+    /// to draw things that hang partly off the edge.
+    fn set_pixel(&mut self, x: i32, y: i32, color: Color);
+
+    /// Read one pixel, or `None` if it's off the surface.
+    fn get_pixel(&self, x: i32, y: i32) -> Option<Color>;
+
+    /// The part of the surface drawing can actually change, as (left, top, right,
+    /// bottom) with the right and bottom exclusive, in the same coordinates the
+    /// drawing methods take. Shapes use this to skip the parts that can't show up.
+    /// It's the whole surface unless the surface says otherwise (a `Canvas` with a
+    /// clip rectangle or a camera does).
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        (0, 0, self.width().min(i32::MAX as u32) as i32, self.height().min(i32::MAX as u32) as i32)
+    }
+
+    /// Fill row `y` from `x0` up to but not including `x1`. Everything that fills
+    /// an area comes through here, so it's the one to make fast.
+    fn fill_row(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+        let (left, top, right, bottom) = self.bounds();
+        if y < top || y >= bottom { return }
+        for x in x0.max(left)..x1.min(right) {
+            self.set_pixel(x, y, color)
+        }
+    }
+
+    /// Fill the entire surface with one color (on a `Canvas`, that's the whole frame,
+    /// whatever the clip rectangle and camera are). Pixels doesn't clear the frame
+    /// for us (its clear color is only for the border around it), so call this at
+    /// the top of drawing anything that moves.
+    fn clear(&mut self, color: Color) {
+        let (w, h) = (self.width().min(i32::MAX as u32) as i32, self.height().min(i32::MAX as u32) as i32);
+        for y in 0..h {
+            self.fill_row(0, w, y, color)
+        }
+    }
+
+    /// Draw a straight line from (x0, y0) to (x1, y1), including both ends. This is
+    /// plain integer Bresenham, so it works in every direction and steep lines don't
+    /// have gaps. Whatever part of the line is off the surface just isn't drawn.
+    fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        // If both ends are off the same side, none of it can be visible, so don't
+        // bother walking it
+        let (left, top, right, bottom) = self.bounds();
+        if (x0 < left && x1 < left) || (y0 < top && y1 < top) || (x0 >= right && x1 >= right) || (y0 >= bottom && y1 >= bottom) {
+            return
+        }
+
+        // Done in i64 so that lines between far-apart points can't overflow
+        let (mut x, mut y) = (x0 as i64, y0 as i64);
+        let (x1, y1) = (x1 as i64, y1 as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.set_pixel(x as i32, y as i32, color);
+            if x == x1 && y == y1 { break }
+            let e2 = err * 2;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Fill a `w` by `h` rectangle whose top-left corner is (x, y). Only the part
+    /// that's actually on the surface gets drawn; a zero-sized rectangle draws nothing.
+    fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+        // Done in i64 so a rectangle hanging off the far edge can't overflow
+        let (_, top, _, bottom) = self.bounds();
+        let x1 = (x as i64 + w as i64).min(i32::MAX as i64) as i32;
+        let y0 = (y as i64).max(top as i64);
+        let y1 = (y as i64 + h as i64).min(bottom as i64);
+        for row in y0..y1 {
+            self.fill_row(x, x1, row as i32, color)
+        }
+    }
+
+    /// Draw the one-pixel outline of a `w` by `h` rectangle whose top-left corner is
+    /// (x, y): the same pixels `fill_rect` would draw around its edge.
+    fn draw_rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+        if w == 0 || h == 0 { return }
+        let (w, h) = (w.min(i32::MAX as u32) as i32, h.min(i32::MAX as u32) as i32);
+        // The top and bottom get the corners, the sides get what's in between, so
+        // nothing's drawn twice
+        self.hline(x, y, w, color);
+        if h > 1 {
+            self.hline(x, y.saturating_add(h - 1), w, color)
+        }
+        self.vline(x, y.saturating_add(1), h - 2, color);
+        if w > 1 {
+            self.vline(x.saturating_add(w - 1), y.saturating_add(1), h - 2, color)
+        }
+    }
+
+    /// Fill a `w` by `h` rectangle with an ordered dither of two colors: `level`
+    /// sixteenths of its pixels are `color_b` and the rest are `color_a`, spread
+    /// out as evenly as a 4x4 pattern can (so 8 is a checkerboard). Levels above 16
+    /// are all `color_b`. The pattern is lined up with (0, 0), not with the
+    /// rectangle, so dithered rectangles next to each other join up seamlessly.
+    #[allow(clippy::too_many_arguments)]
+    fn fill_rect_dither(&mut self, x: i32, y: i32, w: u32, h: u32, color_a: Color, color_b: Color, level: u8) {
+        let (left, top, right, bottom) = self.bounds();
+        let (x0, y0) = (x.max(left), y.max(top));
+        let x1 = (x as i64 + w as i64).min(right as i64) as i32;
+        let y1 = (y as i64 + h as i64).min(bottom as i64) as i32;
+        for py in y0..y1 {
+            let row = &BAYER[py.rem_euclid(4) as usize];
+            for px in x0..x1 {
+                let color = if row[px.rem_euclid(4) as usize] < level { color_b } else { color_a };
+                self.set_pixel(px, py, color)
+            }
+        }
+    }
+
+    /// Fill a `w` by `h` rectangle like `fill_rect`, but shading from `top` on its
+    /// first row to `bottom` on its last, one color per row.
+    fn gradient_rect(&mut self, x: i32, y: i32, w: u32, h: u32, top: Color, bottom: Color) {
+        let x1 = (x as i64 + w as i64).min(i32::MAX as i64) as i32;
+        let (_, clip_top, _, clip_bottom) = self.bounds();
+        let y0 = (y as i64).max(clip_top as i64);
+        let y1 = (y as i64 + h as i64).min(clip_bottom as i64);
+        // Rows are spread from 0 to 1 across the whole rectangle, not just the
+        // visible part, so clipping doesn't squash the gradient
+        let steps = h.saturating_sub(1).max(1) as f32;
+        for row in y0..y1 {
+            let color = Color::lerp(top, bottom, (row - y as i64) as f32 / steps);
+            self.fill_row(x, x1, row as i32, color)
+        }
+    }
+
+    /// Draw a line `thickness` pixels wide from (x0, y0) to (x1, y1). It's filled as
+    /// a rectangle, square across at each end, covering the same length
+    /// `draw_line` would; a thickness of 0 or 1 is just `draw_line`. A line from a
+    /// point to itself is a square `thickness` across.
+    fn draw_thick_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, thickness: u32, color: Color) {
+        if thickness <= 1 {
+            return self.draw_line(x0, y0, x1, y1, color)
+        }
+
+        // From the middle of the first pixel to the middle of the last, and a unit
+        // vector along that. With no length there's no direction, so pick one.
+        let (ax, ay) = (x0 as f64 + 0.5, y0 as f64 + 0.5);
+        let (bx, by) = (x1 as f64 + 0.5, y1 as f64 + 0.5);
+        let len = (bx - ax).hypot(by - ay);
+        let (dx, dy) = if len == 0.0 { (1.0, 0.0) } else { ((bx - ax) / len, (by - ay) / len) };
+
+        // Half a pixel past each end, so the ends cover their pixels like the
+        // sides do (or, for a point, as far as the sides go), and half the
+        // thickness out to either side
+        let half = thickness as f64 / 2.0;
+        let past = if len == 0.0 { half } else { 0.5 };
+        let (ex, ey) = (dx * past, dy * past);
+        let (nx, ny) = (-dy * half, dx * half);
+        let corners = [
+            (ax - ex + nx, ay - ey + ny),
+            (bx + ex + nx, by + ey + ny),
+            (bx + ex - nx, by + ey - ny),
+            (ax - ex - nx, ay - ey - ny),
+        ];
+        fill_polygon_f64(self, &corners, color)
+    }
+
+    /// Draw a horizontal line `len` pixels long, going right from (x, y). A length
+    /// of zero or less draws nothing. This is a lot quicker than `draw_line`.
+    fn hline(&mut self, x: i32, y: i32, len: i32, color: Color) {
+        if len <= 0 { return }
+        self.fill_row(x, x.saturating_add(len), y, color)
+    }
+
+    /// Draw a vertical line `len` pixels long, going down from (x, y). A length of
+    /// zero or less draws nothing.
+    fn vline(&mut self, x: i32, y: i32, len: i32, color: Color) {
+        if len <= 0 { return }
+        let (_, top, _, bottom) = self.bounds();
+        for row in y.max(top)..y.saturating_add(len).min(bottom) {
+            self.set_pixel(x, row, color)
+        }
+    }
+
+    /// Paint-bucket fill: change the pixel at (x, y), and every pixel connected to
+    /// it (up, down, left or right) that's the same color, to `color`. The fill
+    /// doesn't leave `bounds`. Starting outside them, or on a pixel that's already
+    /// `color`, does nothing.
+    fn flood_fill(&mut self, x: i32, y: i32, color: Color) {
+        let (left, top, right, bottom) = self.bounds();
+        if x < left || x >= right || y < top || y >= bottom {
+            return
+        }
+        let Some(target) = self.get_pixel(x, y) else { return };
+        if target == color {
+            return
+        }
+
+        // A scanline fill: pop a seed, fill the whole run of target pixels it's in,
+        // then push one seed for each run touching it in the rows above and below.
+        // Filled pixels stop being the target color, so nothing gets visited twice.
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if self.get_pixel(x, y) != Some(target) {
+                continue
+            }
+            let (mut x0, mut x1) = (x, x + 1);
+            while x0 > left && self.get_pixel(x0 - 1, y) == Some(target) {
+                x0 -= 1
+            }
+            while x1 < right && self.get_pixel(x1, y) == Some(target) {
+                x1 += 1
+            }
+            self.fill_row(x0, x1, y, color);
+
+            for row in [y.saturating_sub(1), y.saturating_add(1)] {
+                if row < top || row >= bottom {
+                    continue
+                }
+                let mut in_run = false;
+                for col in x0..x1 {
+                    let here = self.get_pixel(col, row) == Some(target);
+                    if here && !in_run {
+                        stack.push((col, row))
+                    }
+                    in_run = here
+                }
+            }
+        }
+    }
+
+    /// Draw the outline of a circle centered on (cx, cy). This is the midpoint circle
+    /// algorithm, so it's symmetric in all eight octants, and no pixel gets drawn
+    /// twice. A radius of zero is just the center pixel.
+    fn draw_circle(&mut self, cx: i32, cy: i32, radius: u32, color: Color) {
+        let bounds = self.bounds();
+        for_circle_octant(radius, bounds, &[cx], &[cy], |x, y| {
+            // Reflect the octant point around the center. Along the axes and the
+            // diagonals some reflections land on the same pixel, so skip those.
+            let points = [(x, y), (-x, y), (x, -y), (-x, -y), (y, x), (-y, x), (y, -x), (-y, -x)];
+            let count = if x == y { 4 } else { 8 };
+            for (i, &(dx, dy)) in points[..count].iter().enumerate() {
+                if !points[..i].contains(&(dx, dy)) {
+                    set_offset(self, (cx, cy), dx, dy, color)
+                }
+            }
+        })
+    }
+
+    /// Draw a solid disc centered on (cx, cy): every pixel inside or on the outline
+    /// `draw_circle` would draw, a horizontal span at a time.
+    fn fill_circle(&mut self, cx: i32, cy: i32, radius: u32, color: Color) {
+        let bounds = self.bounds();
+        for_circle_spans(radius, bounds, &[cy], |dy, half| fill_round_rows(self, (cx, cy), (cx, cy), dy, half, color))
+    }
+
+    /// Draw part of the outline `draw_circle` would: the pixels from `start_deg`
+    /// around to `end_deg`. Angles are in degrees, with 0 pointing right and going
+    /// clockwise on screen (so 90 is straight down), and the arc always goes
+    /// clockwise from start to end: 350 to 10 is twenty degrees across the right
+    /// side, not the other 340. An end a whole turn or more past the start is the
+    /// full circle; an end equal to the start draws nothing.
+    fn draw_arc(&mut self, cx: i32, cy: i32, radius: u32, start_deg: f32, end_deg: f32, color: Color) {
+        let Some(sweep) = ArcSweep::new(start_deg, end_deg) else { return };
+        let bounds = self.bounds();
+        for_circle_octant(radius, bounds, &[cx], &[cy], |x, y| {
+            let points = [(x, y), (-x, y), (x, -y), (-x, -y), (y, x), (-y, x), (y, -x), (-y, -x)];
+            let count = if x == y { 4 } else { 8 };
+            for (i, &(dx, dy)) in points[..count].iter().enumerate() {
+                if !points[..i].contains(&(dx, dy)) && sweep.contains(dx, dy) {
+                    set_offset(self, (cx, cy), dx, dy, color)
+                }
+            }
+        })
+    }
+
+    /// Fill the slice of the disc `fill_circle` would draw between two angles,
+    /// which work the same way they do for `draw_arc`. The center pixel is always
+    /// part of it.
+    fn fill_pie(&mut self, cx: i32, cy: i32, radius: u32, start_deg: f32, end_deg: f32, color: Color) {
+        let Some(sweep) = ArcSweep::new(start_deg, end_deg) else { return };
+        let bounds @ (left, top, right, bottom) = self.bounds();
+        let (cx, cy) = (cx as i64, cy as i64);
+        for_circle_spans(radius, bounds, &[cy as i32], |dy, half| {
+            let rows = [dy, -dy];
+            for &dy in &rows[..if dy == 0 { 1 } else { 2 }] {
+                let y = cy + dy;
+                if y < top as i64 || y >= bottom as i64 { continue }
+                // Only look at the visible part of the span, and fill each run of
+                // pixels inside the slice as one row. (If none of it's visible, x0
+                // is past x1 and there's nothing to look at.)
+                let (x0, x1) = ((cx - half).max(left as i64) - cx, (cx + half + 1).min(right as i64) - cx);
+                let mut run = None;
+                for dx in x0..=x1 {
+                    let inside = dx < x1 && sweep.contains(dx, dy);
+                    match (inside, run) {
+                        (true, None) => run = Some(dx),
+                        (false, Some(start)) => {
+                            // Inside the bounds, so these all fit in an i32
+                            self.fill_row((cx + start) as i32, (cx + dx) as i32, y as i32, color);
+                            run = None
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        })
+    }
+
+    /// Fill a `w` by `h` rectangle whose top-left corner is (x, y), with its corners
+    /// rounded off to quarter circles `radius` pixels across. The corners can't be
+    /// rounder than half the rectangle will fit (half of one less than the short
+    /// side, since the middle row or column is shared), so a big radius makes a
+    /// capsule shape. A radius of zero is a plain `fill_rect`.
+    fn fill_round_rect(&mut self, x: i32, y: i32, w: u32, h: u32, radius: u32, color: Color) {
+        let Some((left, top, right, bottom, radius)) = round_rect_corners(x, y, w, h, radius) else {
+            return self.fill_rect(x, y, w, h, color)
+        };
+        // The rounded ends, then the straight part in between
+        let bounds = self.bounds();
+        for_circle_spans(radius, bounds, &[top, bottom], |dy, half| fill_round_rows(self, (left, top), (right, bottom), dy, half, color));
+        if bottom - top > 1 {
+            self.fill_rect(x, top + 1, w, (bottom - top - 1) as u32, color)
+        }
+    }
+
+    /// Draw the one-pixel outline of the shape `fill_round_rect` fills.
+    fn draw_round_rect(&mut self, x: i32, y: i32, w: u32, h: u32, radius: u32, color: Color) {
+        let Some((left, top, right, bottom, radius)) = round_rect_corners(x, y, w, h, radius) else {
+            return self.draw_rect(x, y, w, h, color)
+        };
+        let r = radius as i32;
+        let bounds = self.bounds();
+        for_circle_octant(radius, bounds, &[left, right], &[top, bottom], |px, py| {
+            // Reflect each point of the circle around, like draw_circle, but then
+            // around whichever corner's center it belongs to. Each corner gets a
+            // quarter of the circle, one end included and the other not, so the
+            // points where the arcs meet the sides (or each other) are drawn once.
+            let points = [(px, py), (-px, py), (px, -py), (-px, -py), (py, px), (-py, px), (py, -px), (-py, -px)];
+            let count = if px == py { 4 } else { 8 };
+            for (i, &(dx, dy)) in points[..count].iter().enumerate() {
+                if points[..i].contains(&(dx, dy)) { continue }
+                let cx = if dx > 0 || (dx == 0 && dy < 0) { right } else { left };
+                let cy = if dy > 0 || (dy == 0 && dx > 0) { bottom } else { top };
+                set_offset(self, (cx, cy), dx, dy, color)
+            }
+        });
+        // The straight sides, between the corner centers. Going around clockwise,
+        // each one leaves out the end the next corner already drew.
+        self.hline(left, top.saturating_sub(r), right - left, color);
+        self.vline(right.saturating_add(r), top, bottom - top, color);
+        self.hline(left.saturating_add(1), bottom.saturating_add(r), right - left, color);
+        self.vline(left.saturating_sub(r), top.saturating_add(1), bottom - top, color);
+    }
+
+    /// Draw the outline of an axis-aligned ellipse centered on (cx, cy), `rx` pixels
+    /// to either side and `ry` above and below. This is the midpoint ellipse
+    /// algorithm, which comes out the same as `draw_circle` when the radii match. If
+    /// one radius is zero it's a line, and if both are it's the center pixel.
+    /// Otherwise, radii past `i32::MAX` are taken as `i32::MAX`.
+    fn draw_ellipse(&mut self, cx: i32, cy: i32, rx: u32, ry: u32, color: Color) {
+        if rx == 0 || ry == 0 {
+            return self.fill_ellipse(cx, cy, rx, ry, color)
+        }
+        let quadrant = EllipseQuadrant::new(rx.min(i32::MAX as u32), ry.min(i32::MAX as u32));
+        let (left, top, right, bottom) = self.bounds();
+        // Same as the circle: reflect, but not onto the same pixel twice
+        let mut reflect = |x: i64, y: i64| {
+            let points = [(x, y), (-x, y), (x, -y), (-x, -y)];
+            for (i, &(dx, dy)) in points.iter().enumerate() {
+                if !points[..i].contains(&(dx, dy)) {
+                    set_offset(self, (cx, cy), dx, dy, color)
+                }
+            }
+        };
+        // The steep part has a point on each row, and the flat part on each column, so
+        // only the rows and columns that are on the surface need looking at
+        for_offsets(&mut [visible_offsets(cy, top, bottom)], quadrant.steep_rows - 1, |y| reflect(quadrant.x(y), y));
+        for_offsets(&mut [visible_offsets(cx, left, right)], quadrant.flat_columns - 1, |x| reflect(x, quadrant.y(x)));
+    }
+
+    /// Draw a solid ellipse centered on (cx, cy): every pixel inside or on the
+    /// outline `draw_ellipse` would draw, a horizontal span at a time. Like
+    /// `draw_ellipse`, that's with radii past `i32::MAX` taken as `i32::MAX`, unless
+    /// one of them is zero.
+    fn fill_ellipse(&mut self, cx: i32, cy: i32, rx: u32, ry: u32, color: Color) {
+        if rx == 0 || ry == 0 {
+            // Just a line (or a point). In i64, since the radii can be bigger than
+            // an i32 goes; whatever's past the ends of an i32 can't be drawn anyway
+            let span = |c: i32, r: u32| {
+                let (from, to) = ((c as i64 - r as i64).max(i32::MIN as i64), (c as i64 + r as i64 + 1).min(i32::MAX as i64));
+                (from as i32, (to - from) as u32)
+            };
+            let ((x, w), (y, h)) = (span(cx, rx), span(cy, ry));
+            return self.fill_rect(x, y, w, h, color)
+        }
+        let quadrant = EllipseQuadrant::new(rx.min(i32::MAX as u32), ry.min(i32::MAX as u32));
+        let (_, top, _, bottom) = self.bounds();
+        for_offsets(&mut [visible_offsets(cy, top, bottom)], quadrant.ry as i64, |y| {
+            if let Some(half) = quadrant.half(y) {
+                fill_round_rows(self, (cx, cy), (cx, cy), y, half, color)
+            }
+        })
+    }
+
+    /// Draw lines joining each point to the next, like a string of `draw_line`s. A
+    /// single point just draws that pixel, and no points draws nothing. The points
+    /// can be `Vec2i`s or (x, y) tuples.
+    fn draw_polyline(&mut self, points: &[impl Into<Vec2i> + Copy], color: Color) {
+        let points: Vec<Vec2i> = points.iter().map(|&p| p.into()).collect();
+        if let [p] = points[..] {
+            return self.set_pixel(p.x, p.y, color)
+        }
+        for pair in points.windows(2) {
+            self.draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, color)
+        }
+    }
+
+    /// Draw the outline of a polygon: `draw_polyline`, and then a line from the last
+    /// point back to the first, to close it up.
+    fn draw_polygon(&mut self, points: &[impl Into<Vec2i> + Copy], color: Color) {
+        self.draw_polyline(points, color);
+        if let (Some(&first), Some(&last)) = (points.first(), points.last()) {
+            let (first, last) = (first.into(), last.into());
+            if points.len() > 2 {
+                self.draw_line(last.x, last.y, first.x, first.y, color)
+            }
+        }
+    }
+
+    /// Draw a quadratic Bézier curve from `p0` to `p2`, bent toward `p1`. The curve
+    /// is drawn as straight lines, as few as it takes to stay within a quarter pixel
+    /// of the real curve, so a short or gentle curve is only a line or two.
+    fn draw_bezier_quad(&mut self, p0: impl Into<Vec2i>, p1: impl Into<Vec2i>, p2: impl Into<Vec2i>, color: Color) {
+        // A quadratic is a cubic with both its middle points two thirds of the way
+        // to the quadratic's
+        let [p0, p1, p2] = [p0.into(), p1.into(), p2.into()].map(Vec2f::from);
+        let c1 = p0 + (p1 - p0) * (2.0 / 3.0);
+        let c2 = p2 + (p1 - p2) * (2.0 / 3.0);
+        self.draw_polyline(&flatten_cubic([p0, c1, c2, p2]), color)
+    }
+
+    /// Draw a cubic Bézier curve from `p0` to `p3`, leaving `p0` heading toward `p1`
+    /// and arriving at `p3` from the direction of `p2`. Like `draw_bezier_quad`,
+    /// it's made of as few straight lines as look right.
+    fn draw_bezier_cubic(&mut self, p0: impl Into<Vec2i>, p1: impl Into<Vec2i>, p2: impl Into<Vec2i>, p3: impl Into<Vec2i>, color: Color) {
+        let points = [p0.into(), p1.into(), p2.into(), p3.into()].map(Vec2f::from);
+        self.draw_polyline(&flatten_cubic(points), color)
+    }
+
+    /// Fill a polygon with the even-odd rule: a pixel is inside if a line from it out
+    /// to infinity crosses the edges an odd number of times. That makes
+    /// self-intersecting shapes (like a pentagram drawn in one stroke) come out with
+    /// holes in a predictable way. The polygon is closed automatically. With fewer
+    /// than three points there isn't an inside, so it draws the point or line.
+    /// The points can be `Vec2i`s or (x, y) tuples.
+    fn fill_polygon(&mut self, points: &[impl Into<Vec2i> + Copy], color: Color) {
+        let points: Vec<Vec2i> = points.iter().map(|&p| p.into()).collect();
+        match points[..] {
+            [] => return,
+            [p] => return self.set_pixel(p.x, p.y, color),
+            [a, b] => return self.draw_line(a.x, a.y, b.x, b.y, color),
+            _ => {}
+        }
+        let points: Vec<(f64, f64)> = points.iter().map(|p| (p.x as f64, p.y as f64)).collect();
+        fill_polygon_f64(self, &points, color)
+    }
+
+    /// Fill a triangle. This is quicker than `fill_polygon` with three points, and
+    /// follows the same rule for which pixels are in: those whose middles are inside
+    /// the triangle, counting the top and left edges but not the bottom and right. So
+    /// two triangles that share an edge don't overlap, and don't leave a gap. A
+    /// triangle with no area draws nothing.
+    fn fill_triangle(&mut self, a: impl Into<Vec2i>, b: impl Into<Vec2i>, c: impl Into<Vec2i>, color: Color) {
+        let mut v = [a.into(), b.into(), c.into()].map(|p: Vec2i| (p.x, p.y));
+        v.sort_unstable_by_key(|p| p.1);
+        let [(x0, y0), (x1, y1), (x2, y2)] = v.map(|(x, y)| (x as f64, y as f64));
+        let edge_x = |xa: f64, ya: f64, xb: f64, yb: f64, sy: f64| xa + (sy - ya) * (xb - xa) / (yb - ya);
+
+        let (_, clip_top, _, clip_bottom) = self.bounds();
+        let top = (v[0].1).max(clip_top);
+        let bottom = (v[2].1).min(clip_bottom);
+        for y in top..bottom {
+            let sy = y as f64 + 0.5;
+            if sy < y0 || sy >= y2 { continue }
+            // The long edge runs the whole height; above the middle vertex the other
+            // side is the top half's short edge (the flat-bottomed part), below it
+            // it's the bottom half's (the flat-topped part).
+            let long = edge_x(x0, y0, x2, y2, sy);
+            let short = if sy < y1 { edge_x(x0, y0, x1, y1, sy) } else { edge_x(x1, y1, x2, y2, sy) };
+            let start = (long.min(short) - 0.5).ceil() as i32;
+            let end = (long.max(short) - 0.5).ceil() as i32;
+            // Straight to fill_row, since the span can be wider than an i32 goes
+            self.fill_row(start, end, y, color)
+        }
+    }
+
+    /// Draw the outline of a triangle
+    fn draw_triangle(&mut self, a: impl Into<Vec2i>, b: impl Into<Vec2i>, c: impl Into<Vec2i>, color: Color) {
+        let (a, b, c) = (a.into(), b.into(), c.into());
+        self.draw_line(a.x, a.y, b.x, b.y, color);
+        self.draw_line(b.x, b.y, c.x, c.y, color);
+        self.draw_line(c.x, c.y, a.x, a.y, color);
+    }
+
+    /// Draw text with the built-in font, with the top-left of the first character at
+    /// (x, y). A `\n` moves down to the start of the next line. Only the glyphs are
+    /// drawn; the space around them is left alone. See `font::measure_text` for how
+    /// much room it'll take.
+    fn draw_text(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        self.draw_text_ex(x, y, text, color, None, 1)
+    }
+
+    /// Draw text like `draw_text`, but with every pixel of the font blown up to a
+    /// `scale` by `scale` square, and, if there's a `bg` color, a box of it behind
+    /// the text first. The box is exactly `font::measure_text` times the scale, so
+    /// it's snug around the glyphs. A scale of zero draws nothing.
+    fn draw_text_ex(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Option<Color>, scale: u32) {
+        if scale == 0 { return }
+        if let Some(bg) = bg {
+            let (w, h) = font::measure_text(text);
+            self.fill_rect(x, y, w.saturating_mul(scale), h.saturating_mul(scale), bg)
+        }
+
+        let s = scale.min(i32::MAX as u32) as i32;
+        let (mut cx, mut cy) = (x, y);
+        for c in text.chars() {
+            if c == '\n' {
+                cx = x;
+                cy = cy.saturating_add((font::LINE_HEIGHT as i32).saturating_mul(s));
+                continue
+            }
+            for (col, bits) in font::glyph(c).into_iter().enumerate() {
+                for row in 0..font::GLYPH_HEIGHT {
+                    if bits & (1 << row) == 0 { continue }
+                    let (px, py) = (cx.saturating_add((col as i32).saturating_mul(s)), cy.saturating_add((row as i32).saturating_mul(s)));
+                    if scale == 1 {
+                        self.set_pixel(px, py, fg)
+                    } else {
+                        self.fill_rect(px, py, scale, scale, fg)
+                    }
+                }
+            }
+            cx = cx.saturating_add((font::ADVANCE as i32).saturating_mul(s));
+        }
+    }
+}
+
+// The even-odd polygon fill behind fill_polygon, for corners that don't have to be
+// on whole pixels. (Pixel (x, y) is the square from (x, y) to (x + 1, y + 1), so its
+// middle is at (x + 0.5, y + 0.5).)
+fn fill_polygon_f64<S: Surface + ?Sized>(surface: &mut S, points: &[(f64, f64)], color: Color) {
+    if points.len() < 3 { return }
+    let (_, clip_top, _, clip_bottom) = surface.bounds();
+    let top = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let bottom = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    if !top.is_finite() || !bottom.is_finite() { return }
+    let top = (top.floor() as i64).max(clip_top as i64);
+    let bottom = (bottom.ceil() as i64).min(clip_bottom as i64);
+    let mut crossings = Vec::with_capacity(points.len());
+
+    for y in top..bottom {
+        // We test each row through the middle of its pixels. Each edge counts as
+        // covering from its top end up to but not including its bottom end, so a
+        // vertex shared by two edges counts once, and flat edges never count at
+        // all (the edges either side of them take care of it).
+        let sy = y as f64 + 0.5;
+        crossings.clear();
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            if (y0 <= sy && sy < y1) || (y1 <= sy && sy < y0) {
+                crossings.push(x0 + (sy - y0) * (x1 - x0) / (y1 - y0))
+            }
+        }
+        crossings.sort_unstable_by(|a, b| a.total_cmp(b));
+
+        // Same goes for columns: a pixel's in a span if its middle is
+        for span in crossings.chunks_exact(2) {
+            let start = (span[0] - 0.5).ceil().clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+            let end = (span[1] - 0.5).ceil().clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+            surface.fill_row(start, end, y as i32, color)
+        }
+    }
+}
+
+// How far a flattened curve can be from the real thing, in pixels
+const FLATNESS: f32 = 0.25;
+
+// Past this many halvings the pieces are 1/65536th of the curve, which is plenty for
+// anything that fits on a screen. It also stops NaNs or huge numbers going forever.
+const MAX_SUBDIVISIONS: u32 = 16;
+
+// Turn a cubic Bézier into the pixels of the ends of straight lines that follow it
+// closely enough. Where it's flat enough (both middle control points close to the
+// line between the ends) one line will do; otherwise it's cut in half with de
+// Casteljau's construction and each half tried again, so the number of lines goes
+// with how long and how curvy it is.
+fn flatten_cubic(points: [Vec2f; 4]) -> Vec<Vec2i> {
+    fn subdivide(p: [Vec2f; 4], depth: u32, out: &mut Vec<Vec2i>) {
+        if depth == MAX_SUBDIVISIONS || is_flat(p) {
+            let end = (p[3] + Vec2f::new(0.5, 0.5)).floor();
+            if out.last() != Some(&end) {
+                out.push(end)
+            }
+            return
+        }
+        let mid = |a: Vec2f, b: Vec2f| (a + b) * 0.5;
+        let (ab, bc, cd) = (mid(p[0], p[1]), mid(p[1], p[2]), mid(p[2], p[3]));
+        let (abc, bcd) = (mid(ab, bc), mid(bc, cd));
+        let middle = mid(abc, bcd);
+        subdivide([p[0], ab, abc, middle], depth + 1, out);
+        subdivide([middle, bcd, cd, p[3]], depth + 1, out)
+    }
+
+    // How far the middle points are from the line between the ends (or from the
+    // start, if the ends are the same point)
+    fn is_flat(p: [Vec2f; 4]) -> bool {
+        let chord = p[3] - p[0];
+        let len = chord.length();
+        let distance = |q: Vec2f| {
+            let d = q - p[0];
+            if len == 0.0 { d.length() } else { (d.x * chord.y - d.y * chord.x).abs() / len }
+        };
+        distance(p[1]) <= FLATNESS && distance(p[2]) <= FLATNESS
+    }
+
+    let mut out = vec![(points[0] + Vec2f::new(0.5, 0.5)).floor()];
+    subdivide(points, 0, &mut out);
+    out
+}
+
+// Set the pixel (dx, dy) from (cx, cy), if that's somewhere an i32 can reach. The
+// offsets are i64s because a big circle's can be; anything past the end of an i32
+// is off the surface anyway.
+fn set_offset<S: Surface + ?Sized>(surface: &mut S, (cx, cy): (i32, i32), dx: i64, dy: i64, color: Color) {
+    if let (Ok(x), Ok(y)) = (i32::try_from(cx as i64 + dx), i32::try_from(cy as i64 + dy)) {
+        surface.set_pixel(x, y, color)
+    }
+}
+
+// Fill the spans `dy` rows out from a rounded shape's corner centers (for a circle,
+// all one point): from `half` left of the left centers to `half` right of the right
+// ones, on the row dy above the top and dy below the bottom. If that's the same row,
+// it's only filled once. The ends are worked out in i64, since a big circle's go
+// past where an i32 does, and then cut down to the part inside the bounds.
+fn fill_round_rows<S: Surface + ?Sized>(surface: &mut S, top_left: (i32, i32), bottom_right: (i32, i32), dy: i64, half: i64, color: Color) {
+    let (left, top) = top_left;
+    let (right, bottom) = bottom_right;
+    let (min_x, _, max_x, _) = surface.bounds();
+    let (x0, x1) = ((left as i64 - half).max(min_x as i64), (right as i64 + half + 1).min(max_x as i64));
+    if x0 >= x1 { return }
+    let rows = [bottom as i64 + dy, top as i64 - dy];
+    for &y in &rows[..if rows[0] == rows[1] { 1 } else { 2 }] {
+        if let Ok(y) = i32::try_from(y) {
+            surface.fill_row(x0 as i32, x1 as i32, y, color)
+        }
+    }
+}
+
+// The distances d from c that put c + d or c - d somewhere in lo..hi. That's always
+// one run of them: from zero if c is in there itself, and otherwise from however far
+// it is to the near end.
+fn visible_offsets(c: i32, lo: i32, hi: i32) -> Range<i64> {
+    let (c, lo, hi) = (c as i64, lo as i64, hi as i64);
+    if lo >= hi {
+        0..0
+    } else if c < lo {
+        lo - c..hi - c
+    } else if c >= hi {
+        c - hi + 1..c - lo + 1
+    } else {
+        0..(hi - c).max(c - lo + 1)
+    }
+}
+
+// Call f with every distance that's in any of the ranges, from zero up to and
+// including max, in order and only once each, even where the ranges overlap.
+fn for_offsets(ranges: &mut [Range<i64>], max: i64, mut f: impl FnMut(i64)) {
+    ranges.sort_by_key(|r| r.start);
+    let mut next = 0;
+    for r in ranges.iter() {
+        for d in r.start.max(next)..r.end.min(max.saturating_add(1)) {
+            f(d)
+        }
+        next = next.max(r.end)
+    }
+}
+
+// One octant of a midpoint circle, from (radius, 0) up to the diagonal. Rather than
+// walking around it from the start, this works out the x on any row directly, so a
+// circle only costs as much as the part of it that's on the surface, however big
+// it is. Everything's doubled to stay in integers, and squared in i128, since a u32
+// radius squared doesn't fit in an i64.
+struct CircleOctant {
+    radius: i64,
+    // The last row of the octant, where it reaches the diagonal
+    end: i64,
+}
+
+impl CircleOctant {
+    fn new(radius: u32) -> Self {
+        let mut octant = Self { radius: radius as i64, end: 0 };
+        // x only goes down as y goes up, so the end is where they cross
+        let (mut lo, mut hi) = (0, octant.radius);
+        while lo < hi {
+            let mid = (lo + hi + 1) / 2;
+            if octant.x(mid) >= mid { lo = mid } else { hi = mid - 1 }
+        }
+        octant.end = lo;
+        octant
+    }
+
+    // The x on row y: the furthest out one whose midpoint (x - 1/2, y) is inside the
+    // circle, which is what the midpoint algorithm's walk would land on
+    fn x(&self, y: i64) -> i64 {
+        let inside = 4 * (self.radius as i128 * self.radius as i128 - y as i128 * y as i128);
+        if inside < 1 { return 0 }
+        self.radius.min(((inside - 1).isqrt() as i64 + 1) / 2)
+    }
+
+    // Half the width of the filled circle's rows dy above and below the center, for
+    // dy up to the radius. Near the middle that's the octant's x on that row; further
+    // out, the rows are the octant's columns turned on their side, and the widest is
+    // the last row of the octant that's still at least dy out.
+    fn half(&self, dy: i64) -> i64 {
+        if dy <= self.end { return self.x(dy) }
+        let inside = 4 * self.radius as i128 * self.radius as i128 - (2 * dy as i128 - 1).pow(2);
+        self.end.min(((inside - 1) / 4).isqrt() as i64)
+    }
+}
+
+// Walk the octant of a midpoint circle of the given radius, calling f with each
+// (x, y) offset from the center, but only the points that might show up inside
+// `bounds` once they're reflected around any of the centers at the given columns
+// and rows. A point y out lands on the rows y above and below a center, and on the
+// columns y either side of it, so those are the only y's worth looking at. x never
+// gets smaller than y.
+fn for_circle_octant(radius: u32, bounds: (i32, i32, i32, i32), columns: &[i32], rows: &[i32], mut f: impl FnMut(i64, i64)) {
+    let (left, top, right, bottom) = bounds;
+    let octant = CircleOctant::new(radius);
+    let mut near: Vec<_> = columns.iter().map(|&c| visible_offsets(c, left, right))
+        .chain(rows.iter().map(|&c| visible_offsets(c, top, bottom)))
+        .collect();
+    for_offsets(&mut near, octant.end, |y| f(octant.x(y), y))
+}
+
+// The spans that fill a midpoint circle: calls f once for each dy from 0 up to the
+// radius, with the half-width of the rows dy above and below the center. Like the
+// octant walk, it skips the dy's that don't put a row inside `bounds` from any of
+// the given center rows.
+fn for_circle_spans(radius: u32, bounds: (i32, i32, i32, i32), rows: &[i32], mut f: impl FnMut(i64, i64)) {
+    let (_, top, _, bottom) = bounds;
+    let octant = CircleOctant::new(radius);
+    let mut near: Vec<_> = rows.iter().map(|&c| visible_offsets(c, top, bottom)).collect();
+    for_offsets(&mut near, octant.radius, |dy| f(dy, octant.half(dy)))
+}
+
+// Which way from the center an arc or pie slice covers: the start angle and how far
+// clockwise it goes from there, both in degrees. None if that's nowhere at all.
+#[derive(Copy, Clone)]
+struct ArcSweep {
+    start: f64,
+    sweep: f64,
+}
+
+impl ArcSweep {
+    fn new(start_deg: f32, end_deg: f32) -> Option<Self> {
+        let (start, end) = (start_deg as f64, end_deg as f64);
+        if !start.is_finite() || !end.is_finite() { return None }
+        let sweep = if end - start >= 360.0 { 360.0 } else { (end - start).rem_euclid(360.0) };
+        if sweep == 0.0 { return None }
+        Some(Self { start: start.rem_euclid(360.0), sweep })
+    }
+
+    // Whether the pixel (dx, dy) from the center is in the arc. (The center itself
+    // doesn't have an angle, so it's in all of them.)
+    fn contains(&self, dx: i64, dy: i64) -> bool {
+        if self.sweep >= 360.0 || (dx == 0 && dy == 0) { return true }
+        // y goes down the screen, so this is clockwise already
+        let angle = (dy as f64).atan2(dx as f64).to_degrees();
+        (angle - self.start).rem_euclid(360.0) <= self.sweep
+    }
+}
+
+// Where the corners of a rounded rectangle go: the left and top of the top-left
+// quarter circle's center, the right and bottom of the bottom-right one's, and the
+// radius after shrinking it to fit. None if there's no rounding to do (or no
+// rectangle), and it's just a rectangle.
+fn round_rect_corners(x: i32, y: i32, w: u32, h: u32, radius: u32) -> Option<(i32, i32, i32, i32, u32)> {
+    if w == 0 || h == 0 { return None }
+    let (w, h) = (w.min(i32::MAX as u32) as i32, h.min(i32::MAX as u32) as i32);
+    let radius = radius.min((w.min(h) as u32 - 1) / 2);
+    if radius == 0 { return None }
+    let r = radius as i32;
+    Some((x.saturating_add(r), y.saturating_add(r), x.saturating_add(w - 1 - r), y.saturating_add(h - 1 - r), radius))
+}
+
+// One quadrant of a midpoint ellipse, from (rx, 0) around to (0, ry); x never goes
+// up and y never goes down. Both radii have to be at least one, and no more than
+// i32::MAX, which keeps the sums below inside an i128. While the curve is steep it
+// goes a row at a time, with the x whose midpoint (x - 1/2, y) is inside; once it's
+// flatter, a column at a time, with y picked by (x, y - 1/2). Everything's doubled
+// to stay in integers. Like the circle, each row or column's point is worked out
+// directly rather than by walking there, so only the ones that show need looking
+// at. With equal radii these are the same pixels as CircleOctant's. (Steepness is
+// measured half a pixel out from x, which is where the curve really crosses the
+// row. Measuring at x itself calls a tall skinny ellipse flat near the top, and the
+// columns skip rows.)
+struct EllipseQuadrant {
+    rx: i128,
+    ry: i128,
+    // The steep part is rows 0 up to this, and the flat part columns 0 up to this
+    steep_rows: i64,
+    flat_columns: i64,
+}
+
+impl EllipseQuadrant {
+    fn new(rx: u32, ry: u32) -> Self {
+        let mut quadrant = Self { rx: rx as i128, ry: ry as i128, steep_rows: 0, flat_columns: 0 };
+        // The slope only gets flatter going up the rows, so look for the first one
+        // where it's under one. (The first row never is.)
+        let flat = |q: &Self, y: i64| q.ry * q.ry * (2 * q.x(y) as i128 + 1) < 2 * q.rx * q.rx * y as i128;
+        let (mut lo, mut hi) = (1, ry as i64 + 1);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if flat(&quadrant, mid) { hi = mid } else { lo = mid + 1 }
+        }
+        quadrant.steep_rows = lo;
+        // Then the flat part is every column left of where that stopped
+        quadrant.flat_columns = quadrant.x(lo - 1);
+        quadrant
+    }
+
+    // The x on row y of the steep part
+    fn x(&self, y: i64) -> i64 {
+        let (rx2, ry2, y) = (self.rx * self.rx, self.ry * self.ry, y as i128);
+        let inside = 4 * rx2 * (ry2 - y * y);
+        if inside < 1 { return 0 }
+        (self.rx as i64).min((((inside - 1) / ry2).isqrt() as i64 + 1) / 2)
+    }
+
+    // The y in column x of the flat part: the lowest one whose midpoint (x, y + 1/2)
+    // is on or outside the curve
+    fn y(&self, x: i64) -> i64 {
+        let (rx2, ry2, x) = (self.rx * self.rx, self.ry * self.ry, x as i128);
+        let inside = 4 * ry2 * (rx2 - x * x);
+        if inside < 1 { return 0 }
+        // (2y + 1) squared has to be at least inside / rx2
+        let least = (inside + rx2 - 1) / rx2;
+        let mut root = least.isqrt();
+        if root * root < least { root += 1 }
+        (self.ry as i64).min(root as i64 / 2)
+    }
+
+    // Half the width of the filled ellipse's rows y above and below the center: the
+    // first point either part has on that row, which is the widest. None if no
+    // point lands on the row, so there's no span there.
+    fn half(&self, y: i64) -> Option<i64> {
+        if y < self.steep_rows { return Some(self.x(y)) }
+        if self.flat_columns == 0 { return None }
+        // y only goes up as x goes down, so look for the furthest out column that's
+        // as far up as y
+        let (mut lo, mut hi) = (0, self.flat_columns - 1);
+        while lo < hi {
+            let mid = (lo + hi + 1) / 2;
+            if self.y(mid) >= y { lo = mid } else { hi = mid - 1 }
+        }
+        (self.y(lo) == y).then_some(lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use super::*;
+    use crate::Canvas;
+
+    // Draw on a w by h canvas that starts out transparent, and collect the pixels
+    // that got drawn on
+    fn lit(w: u32, h: u32, draw: impl FnOnce(&mut Canvas)) -> BTreeSet<(i32, i32)> {
+        let mut frame = vec![0; w as usize * h as usize * 4];
+        let mut canvas = Canvas::new(&mut frame, w, h);
+        draw(&mut canvas);
+        (0..h as i32).flat_map(|y| (0..w as i32).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas.get_pixel(x, y) != Some(Color::TRANSPARENT))
+            .collect()
+    }
+
+    fn set(points: &[(i32, i32)]) -> BTreeSet<(i32, i32)> {
+        points.iter().copied().collect()
+    }
+
+    // Everything in a set, flipped left to right and top to bottom around (cx, cy)
+    fn mirrored(pixels: &BTreeSet<(i32, i32)>, (cx, cy): (i32, i32)) -> [BTreeSet<(i32, i32)>; 2] {
+        [
+            pixels.iter().map(|&(x, y)| (2 * cx - x, y)).collect(),
+            pixels.iter().map(|&(x, y)| (x, 2 * cy - y)).collect(),
+        ]
+    }
+
+    // The least a Surface can be: the four required methods, and nothing else, so
+    // everything drawn on it goes through the default methods
+    struct Grid {
+        width: u32,
+        height: u32,
+        pixels: Vec<Color>,
+    }
+
+    impl Surface for Grid {
+        fn width(&self) -> u32 {
+            self.width
+        }
+
+        fn height(&self) -> u32 {
+            self.height
+        }
+
+        fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+            if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+                self.pixels[(y as u32 * self.width + x as u32) as usize] = color
+            }
+        }
+
+        fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+            if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+                Some(self.pixels[(y as u32 * self.width + x as u32) as usize])
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn line_shallow_and_steep() {
+        let shallow = set(&[(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2)]);
+        assert_eq!(lit(8, 8, |c| c.draw_line(0, 0, 5, 2, Color::WHITE)), shallow);
+        // Backwards is the same pixels
+        assert_eq!(lit(8, 8, |c| c.draw_line(5, 2, 0, 0, Color::WHITE)), shallow);
+        let steep = set(&[(1, 0), (1, 1), (2, 2), (2, 3), (3, 4), (3, 5)]);
+        assert_eq!(lit(8, 8, |c| c.draw_line(1, 0, 3, 5, Color::WHITE)), steep);
+    }
+
+    #[test]
+    fn line_every_octant() {
+        // Out from the middle in all eight octants: each line has one pixel per step
+        // along its long side, with no gaps, and both ends
+        for (x1, y1) in [(9, 7), (7, 9), (3, 9), (1, 7), (1, 3), (3, 1), (7, 1), (9, 3)] {
+            let pixels = lit(11, 11, |c| c.draw_line(5, 5, x1, y1, Color::WHITE));
+            let long = (x1 - 5).abs().max((y1 - 5).abs());
+            assert_eq!(pixels.len() as i32, long + 1, "to ({}, {})", x1, y1);
+            assert!(pixels.contains(&(5, 5)) && pixels.contains(&(x1, y1)));
+            for &(x, y) in &pixels {
+                let touching = pixels.iter().any(|&(ox, oy)| (ox, oy) != (x, y) && (ox - x).abs() <= 1 && (oy - y).abs() <= 1);
+                assert!(touching, "gap at ({}, {})", x, y)
+            }
+        }
+    }
+
+    #[test]
+    fn line_straight_and_single_pixel() {
+        assert_eq!(lit(5, 5, |c| c.draw_line(1, 2, 3, 2, Color::WHITE)), set(&[(1, 2), (2, 2), (3, 2)]));
+        assert_eq!(lit(5, 5, |c| c.draw_line(4, 3, 4, 1, Color::WHITE)), set(&[(4, 1), (4, 2), (4, 3)]));
+        assert_eq!(lit(5, 5, |c| c.draw_line(2, 2, 2, 2, Color::WHITE)), set(&[(2, 2)]));
+    }
+
+    #[test]
+    fn line_clipped() {
+        assert_eq!(lit(4, 4, |c| c.draw_line(-10, -10, 20, 20, Color::WHITE)), set(&[(0, 0), (1, 1), (2, 2), (3, 3)]));
+        assert!(lit(4, 4, |c| c.draw_line(-5, 10, 10, 10, Color::WHITE)).is_empty());
+        assert!(lit(4, 4, |c| c.draw_line(i32::MIN, i32::MIN, i32::MIN + 3, i32::MAX, Color::WHITE)).is_empty());
+    }
+
+    #[test]
+    fn circle_pixels() {
+        assert_eq!(lit(5, 5, |c| c.draw_circle(2, 2, 0, Color::WHITE)), set(&[(2, 2)]));
+        assert_eq!(lit(5, 5, |c| c.draw_circle(2, 2, 1, Color::WHITE)), set(&[(1, 2), (3, 2), (2, 1), (2, 3)]));
+        let two = lit(5, 5, |c| c.draw_circle(2, 2, 2, Color::WHITE));
+        assert_eq!(two.len(), 12);
+        assert!(!two.contains(&(0, 0)) && two.contains(&(0, 1)) && two.contains(&(1, 0)));
+    }
+
+    #[test]
+    fn circle_symmetric() {
+        for radius in [3, 5, 6] {
+            let outline = lit(15, 15, |c| c.draw_circle(7, 7, radius, Color::WHITE));
+            let filled = lit(15, 15, |c| c.fill_circle(7, 7, radius, Color::WHITE));
+            for pixels in [&outline, &filled] {
+                assert_eq!(mirrored(pixels, (7, 7)), [pixels.clone(), pixels.clone()]);
+            }
+        }
+    }
+
+    #[test]
+    fn filled_circle_has_no_gaps() {
+        let outline = lit(15, 15, |c| c.draw_circle(7, 7, 6, Color::WHITE));
+        let filled = lit(15, 15, |c| c.fill_circle(7, 7, 6, Color::WHITE));
+        assert!(outline.is_subset(&filled));
+        // Every row from the outline's top to its bottom, filled in solid between its
+        // outline pixels
+        for y in 1..14 {
+            let row: Vec<i32> = outline.iter().filter(|p| p.1 == y).map(|p| p.0).collect();
+            let (min, max) = (*row.iter().min().unwrap(), *row.iter().max().unwrap());
+            assert!((min..=max).all(|x| filled.contains(&(x, y))), "row {}", y);
+            assert_eq!(filled.iter().filter(|p| p.1 == y).count() as i32, max - min + 1)
+        }
+    }
+
+    #[test]
+    fn circle_clipped() {
+        // Hanging off the top left corner, only the part that's on shows
+        let whole = lit(20, 20, |c| c.draw_circle(10, 10, 5, Color::WHITE));
+        let corner = lit(8, 8, |c| c.draw_circle(3, 3, 5, Color::WHITE));
+        let expected: BTreeSet<_> = whole.iter().map(|&(x, y)| (x - 7, y - 7)).filter(|&(x, y)| x >= 0 && y >= 0 && x < 8 && y < 8).collect();
+        assert_eq!(corner, expected);
+    }
+
+    #[test]
+    fn circles_at_the_far_edges() {
+        // Nothing's on the canvas, and nothing overflows working that out
+        for (cx, cy) in [(i32::MAX, 0), (0, i32::MAX), (i32::MIN, 0), (0, i32::MIN)] {
+            assert!(lit(4, 4, |c| {
+                c.draw_circle(cx, cy, 2, Color::WHITE);
+                c.fill_circle(cx, cy, 2, Color::WHITE);
+                c.draw_arc(cx, cy, 2, 0.0, 270.0, Color::WHITE);
+                c.fill_pie(cx, cy, 2, 0.0, 270.0, Color::WHITE);
+            }).is_empty());
+        }
+    }
+
+    #[test]
+    fn huge_circles() {
+        // Only the bit that's on the canvas gets worked out, so these are quick, and
+        // none of their arithmetic overflows
+        assert_eq!(lit(8, 6, |c| c.fill_circle(0, 0, 1_500_000_000, Color::WHITE)).len(), 48);
+        assert_eq!(lit(8, 6, |c| c.fill_circle(0, 0, u32::MAX, Color::WHITE)).len(), 48);
+        assert!(lit(8, 6, |c| c.draw_circle(0, 0, u32::MAX, Color::WHITE)).is_empty());
+
+        // Just the top of one, which is flat this close up: the outline is all of
+        // row 3, and the disc is everything from there down
+        let (r, cy) = (1_500_000_000, 1_500_000_003);
+        let row: Vec<_> = (0..8).map(|x| (x, 3)).collect();
+        assert_eq!(lit(8, 6, |c| c.draw_circle(4, cy, r, Color::WHITE)), set(&row));
+        assert_eq!(lit(8, 6, |c| c.draw_arc(4, cy, r, 180.0, 360.0, Color::WHITE)), set(&row));
+        assert!(lit(8, 6, |c| c.draw_arc(4, cy, r, 0.0, 180.0, Color::WHITE)).is_empty());
+        let below: BTreeSet<_> = (0..8).flat_map(|x| (3..6).map(move |y| (x, y))).collect();
+        assert_eq!(lit(8, 6, |c| c.fill_circle(4, cy, r, Color::WHITE)), below);
+
+        // Pie slices too, one quarter of the disc being the whole canvas and the other
+        // side of the center being everything left of it
+        assert_eq!(lit(8, 6, |c| c.fill_pie(0, 0, u32::MAX, 0.0, 90.0, Color::WHITE)).len(), 48);
+        assert_eq!(lit(8, 6, |c| c.fill_pie(3, 0, 1_500_000_000, 90.0, 180.0, Color::WHITE)).len(), 24);
+
+        // And rounded rectangles as big as they get, where the canvas is either well
+        // inside the corner's curve or well outside it
+        assert_eq!(lit(8, 6, |c| c.fill_round_rect(-5, -5, u32::MAX, u32::MAX, 3, Color::WHITE)).len(), 48);
+        assert!(lit(8, 6, |c| {
+            c.fill_round_rect(-5, -5, u32::MAX, u32::MAX, u32::MAX, Color::WHITE);
+            c.draw_round_rect(-5, -5, u32::MAX, u32::MAX, u32::MAX, Color::WHITE);
+        }).is_empty());
+        let edge: Vec<_> = (0..8).map(|x| (x, 2)).collect();
+        assert_eq!(lit(8, 6, |c| c.draw_round_rect(-1_000, 2, u32::MAX, 100, 3, Color::WHITE)), set(&edge));
+    }
+
+    #[test]
+    fn ellipse_symmetric() {
+        for (rx, ry) in [(6, 3), (2, 7), (5, 4)] {
+            let outline = lit(17, 17, |c| c.draw_ellipse(8, 8, rx, ry, Color::WHITE));
+            let filled = lit(17, 17, |c| c.fill_ellipse(8, 8, rx, ry, Color::WHITE));
+            assert!(outline.is_subset(&filled));
+            for pixels in [&outline, &filled] {
+                assert_eq!(mirrored(pixels, (8, 8)), [pixels.clone(), pixels.clone()], "{}x{}", rx, ry);
+                assert!(pixels.contains(&(8 - rx as i32, 8)) && pixels.contains(&(8, 8 + ry as i32)));
+            }
+        }
+    }
+
+    #[test]
+    fn ellipse_with_equal_radii_is_a_circle() {
+        for radius in 0..8 {
+            assert_eq!(lit(17, 17, |c| c.draw_ellipse(8, 8, radius, radius, Color::WHITE)), lit(17, 17, |c| c.draw_circle(8, 8, radius, Color::WHITE)));
+            assert_eq!(lit(17, 17, |c| c.fill_ellipse(8, 8, radius, radius, Color::WHITE)), lit(17, 17, |c| c.fill_circle(8, 8, radius, Color::WHITE)));
+        }
+    }
+
+    #[test]
+    fn huge_ellipses() {
+        // Like the circles, these only work out the part that's on the canvas
+        assert!(lit(8, 6, |c| c.draw_ellipse(0, 0, 40_000, 40_000, Color::WHITE)).is_empty());
+        assert_eq!(lit(8, 6, |c| c.fill_ellipse(0, 0, 40_000, 40_000, Color::WHITE)).len(), 48);
+        assert_eq!(lit(8, 6, |c| c.fill_ellipse(0, 0, u32::MAX, u32::MAX, Color::WHITE)).len(), 48);
+        assert_eq!(lit(8, 6, |c| c.draw_ellipse(4, 40_003, 40_000, 40_000, Color::WHITE)), lit(8, 6, |c| c.draw_circle(4, 40_003, 40_000, Color::WHITE)));
+        assert_eq!(lit(8, 6, |c| c.fill_ellipse(4, 40_003, 40_000, 40_000, Color::WHITE)), lit(8, 6, |c| c.fill_circle(4, 40_003, 40_000, Color::WHITE)));
+
+        // A long flat one's bottom edge is a straight line this close in
+        let row: Vec<_> = (0..8).map(|x| (x, 5)).collect();
+        assert_eq!(lit(8, 6, |c| c.draw_ellipse(4, 0, u32::MAX, 5, Color::WHITE)), set(&row));
+    }
+
+    #[test]
+    fn flat_ellipses() {
+        assert_eq!(lit(7, 7, |c| c.draw_ellipse(3, 3, 2, 0, Color::WHITE)), set(&[(1, 3), (2, 3), (3, 3), (4, 3), (5, 3)]));
+        assert_eq!(lit(7, 7, |c| c.fill_ellipse(3, 3, 0, 1, Color::WHITE)), set(&[(3, 2), (3, 3), (3, 4)]));
+        assert_eq!(lit(7, 7, |c| c.draw_ellipse(3, 3, 0, 0, Color::WHITE)), set(&[(3, 3)]));
+        // Radii too big for an i32 are still just a line, as far as it goes
+        let column: Vec<_> = (0..7).map(|y| (5, y)).collect();
+        assert_eq!(lit(7, 7, |c| c.fill_ellipse(5, 5, 0, u32::MAX, Color::WHITE)), set(&column));
+        assert_eq!(lit(7, 7, |c| c.draw_ellipse(i32::MIN, 2, u32::MAX, 0, Color::WHITE)), set(&[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (5, 2), (6, 2)]));
+    }
+
+    #[test]
+    fn polygon_triangle() {
+        let pixels = lit(12, 12, |c| c.fill_polygon(&[(0, 0), (10, 0), (0, 10)], Color::WHITE));
+        for inside in [(0, 0), (1, 1), (8, 0), (3, 5), (0, 8)] {
+            assert!(pixels.contains(&inside), "{:?}", inside)
+        }
+        for outside in [(9, 0), (5, 5), (8, 8), (0, 9), (11, 11)] {
+            assert!(!pixels.contains(&outside), "{:?}", outside)
+        }
+        // The same pixels fill_triangle picks
+        assert_eq!(pixels, lit(12, 12, |c| c.fill_triangle((0, 0), (10, 0), (0, 10), Color::WHITE)));
+    }
+
+    #[test]
+    fn polygon_concave_star() {
+        let star = [(12, 2), (14, 9), (22, 9), (16, 13), (18, 20), (12, 16), (6, 20), (8, 13), (2, 9), (10, 9)];
+        let pixels = lit(24, 24, |c| c.fill_polygon(&star, Color::WHITE));
+        for inside in [(12, 12), (12, 4), (18, 10), (7, 18), (17, 18)] {
+            assert!(pixels.contains(&inside), "{:?}", inside)
+        }
+        // Off the tips, and in the notches between them
+        for outside in [(21, 10), (16, 6), (12, 18), (4, 14), (12, 1)] {
+            assert!(!pixels.contains(&outside), "{:?}", outside)
+        }
+
+        // Drawn in one stroke, even-odd leaves the middle out
+        let pentagram = [(12, 2), (18, 20), (2, 9), (22, 9), (6, 20)];
+        let pixels = lit(24, 24, |c| c.fill_polygon(&pentagram, Color::WHITE));
+        assert!(pixels.contains(&(12, 4)) && !pixels.contains(&(12, 12)));
+    }
+
+    #[test]
+    fn polygon_clipped() {
+        let whole = lit(30, 30, |c| c.fill_polygon(&[(10, 5), (25, 20), (5, 25)], Color::WHITE));
+        let part = lit(12, 12, |c| c.fill_polygon(&[(0, -5), (15, 10), (-5, 15)], Color::WHITE));
+        let expected: BTreeSet<_> = whole.iter().map(|&(x, y)| (x - 10, y - 10)).filter(|&(x, y)| x >= 0 && y >= 0 && x < 12 && y < 12).collect();
+        assert_eq!(part, expected);
+    }
+
+    #[test]
+    fn triangles_sharing_an_edge() {
+        // Two halves of a square: no pixel in both, and none missing
+        let a = lit(12, 12, |c| c.fill_triangle((1, 1), (11, 1), (1, 11), Color::WHITE));
+        let b = lit(12, 12, |c| c.fill_triangle((11, 1), (11, 11), (1, 11), Color::WHITE));
+        assert!(a.is_disjoint(&b));
+        let square: BTreeSet<_> = (1..11).flat_map(|y| (1..11).map(move |x| (x, y))).collect();
+        assert_eq!(a.union(&b).copied().collect::<BTreeSet<_>>(), square);
+        // The order of the corners doesn't matter
+        assert_eq!(a, lit(12, 12, |c| c.fill_triangle((1, 11), (1, 1), (11, 1), Color::WHITE)));
+    }
+
+    #[test]
+    fn degenerate_triangles() {
+        // No area, so nothing drawn: a point, a flat line, a slanted line
+        for [a, b, c] in [[(3, 3); 3], [(0, 4), (5, 4), (9, 4)], [(0, 0), (4, 4), (8, 8)]] {
+            assert!(lit(10, 10, |canvas| canvas.fill_triangle(a, b, c, Color::WHITE)).is_empty(), "{:?}", [a, b, c]);
+        }
+        // Way off to the sides, and huge
+        assert!(lit(4, 4, |c| c.fill_triangle((i32::MIN, i32::MIN), (i32::MAX, i32::MIN), (i32::MIN, i32::MIN + 2), Color::WHITE)).is_empty());
+        assert_eq!(lit(4, 4, |c| c.fill_triangle((-1000, -1000), (3000, -1000), (-1000, 3000), Color::WHITE)).len(), 16);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_the_border() {
+        let mut frame = vec![0; 10 * 10 * 4];
+        let mut canvas = Canvas::new(&mut frame, 10, 10);
+        canvas.draw_rect(2, 2, 6, 5, Color::WHITE);
+        // A diagonal gap isn't a way out: only up, down, left and right connect
+        canvas.set_pixel(7, 2, Color::TRANSPARENT);
+        canvas.set_pixel(8, 1, Color::WHITE);
+        canvas.flood_fill(4, 4, Color::RED);
+
+        for y in 0..10 {
+            for x in 0..10 {
+                let expected = if (3..7).contains(&x) && (3..6).contains(&y) {
+                    Color::RED
+                } else if (x, y) == (8, 1) || ((x == 2 || x == 7) && (2..7).contains(&y) && (x, y) != (7, 2)) || ((y == 2 || y == 6) && (2..8).contains(&x) && (x, y) != (7, 2)) {
+                    Color::WHITE
+                } else {
+                    Color::TRANSPARENT
+                };
+                assert_eq!(canvas.get_pixel(x, y), Some(expected), "({}, {})", x, y)
+            }
+        }
+
+        // Filling with the color that's already there, or off the canvas, does nothing
+        canvas.flood_fill(4, 4, Color::RED);
+        canvas.flood_fill(-1, 4, Color::BLUE);
+        assert_eq!(canvas.get_pixel(0, 4), Some(Color::TRANSPARENT));
+        // And outside, it goes everywhere the border doesn't
+        canvas.flood_fill(0, 0, Color::BLUE);
+        assert_eq!(canvas.get_pixel(9, 9), Some(Color::BLUE));
+        assert_eq!(canvas.get_pixel(7, 2), Some(Color::BLUE));
+        assert_eq!(canvas.get_pixel(4, 4), Some(Color::RED));
+    }
+
+    #[test]
+    fn round_rect_corners() {
+        let outline = lit(12, 10, |c| c.draw_round_rect(1, 1, 10, 8, 3, Color::WHITE));
+        let filled = lit(12, 10, |c| c.fill_round_rect(1, 1, 10, 8, 3, Color::WHITE));
+        for pixels in [&outline, &filled] {
+            // The very corners are cut off
+            for corner in [(1, 1), (10, 1), (1, 8), (10, 8), (2, 1), (1, 2), (9, 8)] {
+                assert!(!pixels.contains(&corner), "{:?}", corner)
+            }
+            // But the straight edges are all there, and so are the curves
+            for x in 3..9 {
+                assert!(pixels.contains(&(x, 1)) && pixels.contains(&(x, 8)))
+            }
+            for y in 3..7 {
+                assert!(pixels.contains(&(1, y)) && pixels.contains(&(10, y)))
+            }
+            assert!(pixels.contains(&(2, 2)) && pixels.contains(&(9, 7)));
+        }
+        assert!(outline.is_subset(&filled));
+        assert!(!outline.contains(&(5, 4)) && filled.contains(&(5, 4)));
+        assert_eq!(filled.len(), 10 * 8 - 4 * 3);
+
+        // No radius is a plain rectangle
+        assert_eq!(lit(12, 10, |c| c.fill_round_rect(1, 1, 10, 8, 0, Color::WHITE)).len(), 80);
+    }
+
+    #[test]
+    fn default_methods_on_a_plain_surface() {
+        // Everything here, on a Grid and on a Canvas (which has its own fill_row,
+        // clear and bounds), comes out the same
+        fn draw<S: Surface>(s: &mut S) {
+            s.clear(Color::GRAY);
+            s.fill_rect(-3, 2, 8, 4, Color::RED);
+            s.draw_rect(10, 1, 6, 5, Color::GREEN);
+            s.draw_line(0, 15, 15, 9, Color::BLUE);
+            s.fill_circle(12, 12, 3, Color::WHITE);
+            s.draw_ellipse(4, 10, 3, 2, Color::YELLOW);
+            s.fill_triangle((1, 1), (6, 0), (3, 5), Color::CYAN);
+            s.draw_text(0, 7, "Hi", Color::MAGENTA);
+            s.flood_fill(11, 3, Color::BLUE);
+        }
+        let mut grid = Grid { width: 16, height: 16, pixels: vec![Color::TRANSPARENT; 256] };
+        draw(&mut grid);
+        let mut frame = vec![0; 16 * 16 * 4];
+        let mut canvas = Canvas::new(&mut frame, 16, 16);
+        draw(&mut canvas);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(grid.get_pixel(x, y), canvas.get_pixel(x, y), "({}, {})", x, y)
+            }
+        }
+        assert_eq!(grid.get_pixel(0, 3), Some(Color::RED));
+        assert_eq!(grid.get_pixel(12, 3), Some(Color::BLUE));
+        assert_eq!(grid.get_pixel(15, 15), Some(Color::GRAY));
+        assert_eq!(grid.bounds(), (0, 0, 16, 16));
+    }
+
+    #[test]
+    fn quarter_arc() {
+        let circle = lit(15, 15, |c| c.draw_circle(7, 7, 5, Color::WHITE));
+        let arc = lit(15, 15, |c| c.draw_arc(7, 7, 5, 0.0, 90.0, Color::WHITE));
+        // The bottom right quarter (90 is down), ends included
+        let quarter: BTreeSet<_> = circle.iter().copied().filter(|&(x, y)| x >= 7 && y >= 7).collect();
+        assert_eq!(arc, quarter);
+        assert!(arc.contains(&(12, 7)) && arc.contains(&(7, 12)));
+
+        let pie = lit(15, 15, |c| c.fill_pie(7, 7, 5, 0.0, 90.0, Color::WHITE));
+        let disc = lit(15, 15, |c| c.fill_circle(7, 7, 5, Color::WHITE));
+        assert_eq!(pie, disc.iter().copied().filter(|&(x, y)| x >= 7 && y >= 7).collect());
+        // Going the other way round from 90 to 0 is the other three quarters
+        let rest = lit(15, 15, |c| c.draw_arc(7, 7, 5, 90.0, 360.0, Color::WHITE));
+        assert_eq!(rest.union(&arc).count(), circle.len());
+        assert_eq!(lit(15, 15, |c| c.draw_arc(7, 7, 5, 90.0, 0.0, Color::WHITE)), rest);
+    }
+
+    #[test]
+    fn full_circle_arcs() {
+        let circle = lit(15, 15, |c| c.draw_circle(7, 7, 5, Color::WHITE));
+        let disc = lit(15, 15, |c| c.fill_circle(7, 7, 5, Color::WHITE));
+        for (start, end) in [(0.0, 360.0), (45.0, 405.0), (-90.0, 700.0)] {
+            assert_eq!(lit(15, 15, |c| c.draw_arc(7, 7, 5, start, end, Color::WHITE)), circle);
+            assert_eq!(lit(15, 15, |c| c.fill_pie(7, 7, 5, start, end, Color::WHITE)), disc);
+        }
+        assert!(lit(15, 15, |c| c.draw_arc(7, 7, 5, 30.0, 30.0, Color::WHITE)).is_empty());
+        assert!(lit(15, 15, |c| c.fill_pie(7, 7, 5, 30.0, 30.0, Color::WHITE)).is_empty());
+    }
+
+    #[test]
+    fn text_background_and_scale() {
+        assert_eq!(font::measure_text("Hi"), (11, 7));
+        assert_eq!(font::measure_text("ab\ncde"), (17, 15));
+        assert_eq!(font::measure_text(""), (0, 0));
+
+        // The box is exactly the measured size times the scale, and the text's inside
+        let mut frame = vec![0; 30 * 20 * 4];
+        let mut canvas = Canvas::new(&mut frame, 30, 20);
+        canvas.draw_text_ex(3, 2, "Hi", Color::WHITE, Some(Color::BLUE), 2);
+        let (mut ink, mut box_pixels) = (0, 0);
+        for y in 0..20 {
+            for x in 0..30 {
+                let inside = (3..25).contains(&x) && (2..16).contains(&y);
+                match canvas.get_pixel(x, y).unwrap() {
+                    Color::WHITE if inside => ink += 1,
+                    Color::BLUE if inside => box_pixels += 1,
+                    Color::TRANSPARENT if !inside => {}
+                    other => panic!("{:?} at ({}, {})", other, x, y),
+                }
+            }
+        }
+        // Every pixel of the font is a 2x2 square
+        assert_eq!(ink % 4, 0);
+        assert_eq!(ink + box_pixels, 22 * 14);
+        let small = lit(30, 20, |c| c.draw_text(3, 2, "Hi", Color::WHITE));
+        assert_eq!(small.len() * 4, ink);
+
+        // Scale zero draws nothing at all, box included
+        assert!(lit(30, 20, |c| c.draw_text_ex(0, 0, "Hi", Color::WHITE, Some(Color::BLUE), 0)).is_empty());
+
+        // A scale too big for the glyph offsets just runs off the edge instead of overflowing
+        assert_eq!(lit(8, 6, |c| c.draw_text_ex(0, 0, "Hi\nA", Color::WHITE, Some(Color::BLUE), u32::MAX)).len(), 48);
+        assert_eq!(lit(8, 6, |c| c.draw_text_ex(-(1 << 30) - 3, 0, "Hi\nA", Color::WHITE, None, 1 << 30)).len(), 0);
+    }
+
+    #[test]
+    fn thick_lines() {
+        // Straight across, it's a rectangle the thickness across
+        let flat = lit(12, 12, |c| c.draw_thick_line(2, 5, 10, 5, 3, Color::WHITE));
+        assert_eq!(flat, (4..7).flat_map(|y| (2..11).map(move |x| (x, y))).collect());
+
+        // On the diagonal, about as wide as asked for. Straight across it, pixels are
+        // root two apart, so four wide is three of them; along a row it's about four
+        // times root two
+        let diagonal = lit(32, 32, |c| c.draw_thick_line(5, 5, 25, 25, 4, Color::WHITE));
+        let across = diagonal.iter().filter(|&&(x, y)| x + y == 30).count();
+        let row = diagonal.iter().filter(|&&(_, y)| y == 15).count();
+        assert_eq!(across, 3);
+        assert!((5..=6).contains(&row), "{} across one row", row);
+        let area = (20.0 * 2f64.sqrt() + 1.0) * 4.0;
+        assert!((diagonal.len() as f64 - area).abs() < area * 0.15, "{} pixels for about {}", diagonal.len(), area);
+        let middle: BTreeSet<_> = (0..=20).map(|i| (5 + i, 5 + i)).collect();
+        assert!(middle.is_subset(&diagonal));
+
+        // Thin ones are draw_line, and off the edge is cut off
+        assert_eq!(lit(12, 12, |c| c.draw_thick_line(0, 0, 9, 4, 1, Color::WHITE)), lit(12, 12, |c| c.draw_line(0, 0, 9, 4, Color::WHITE)));
+        let clipped = lit(12, 12, |c| c.draw_thick_line(-20, 6, 40, 6, 2, Color::WHITE));
+        assert_eq!(clipped, (5..7).flat_map(|y| (0..12).map(move |x| (x, y))).collect());
+    }
+
+    #[test]
+    fn dither_levels() {
+        // Level 8 is a checkerboard: half and half, and no two neighbors the same
+        let b = lit(8, 8, |c| c.fill_rect_dither(0, 0, 8, 8, Color::TRANSPARENT, Color::WHITE, 8));
+        assert_eq!(b.len(), 32);
+        for y in 0..8 {
+            for x in 0..7 {
+                assert_ne!(b.contains(&(x, y)), b.contains(&(x + 1, y)));
+                assert_ne!(b.contains(&(y, x)), b.contains(&(y, x + 1)));
+            }
+        }
+        // Every other level is that many sixteenths, in each 4x4 block
+        for level in 0..=17 {
+            let b = lit(8, 8, |c| c.fill_rect_dither(0, 0, 8, 8, Color::TRANSPARENT, Color::WHITE, level));
+            let block = b.iter().filter(|&&(x, y)| x >= 4 && y < 4).count();
+            assert_eq!(block, level.min(16) as usize, "level {}", level);
+            assert_eq!(b.len(), block * 4)
+        }
+        // The pattern belongs to the surface, not the rectangle
+        let whole = lit(8, 8, |c| c.fill_rect_dither(0, 0, 8, 8, Color::TRANSPARENT, Color::WHITE, 5));
+        let part = lit(8, 8, |c| c.fill_rect_dither(3, 1, 4, 5, Color::TRANSPARENT, Color::WHITE, 5));
+        assert_eq!(part, whole.iter().copied().filter(|&(x, y)| (3..7).contains(&x) && (1..6).contains(&y)).collect());
+    }
+
+    #[test]
+    fn closed_triangle_path() {
+        let (a, b, c) = ((1, 1), (12, 4), (5, 10));
+        let by_lines = lit(14, 12, |canvas| {
+            canvas.draw_line(a.0, a.1, b.0, b.1, Color::WHITE);
+            canvas.draw_line(b.0, b.1, c.0, c.1, Color::WHITE);
+            canvas.draw_line(c.0, c.1, a.0, a.1, Color::WHITE)
+        });
+        assert_eq!(lit(14, 12, |canvas| canvas.draw_polygon(&[a, b, c], Color::WHITE)), by_lines);
+        // Left open, it's missing the last side
+        let open = lit(14, 12, |canvas| canvas.draw_polyline(&[a, b, c], Color::WHITE));
+        assert!(open.is_subset(&by_lines) && open.len() < by_lines.len());
+
+        assert_eq!(lit(4, 4, |canvas| canvas.draw_polyline(&[(2, 3)], Color::WHITE)), set(&[(2, 3)]));
+        assert!(lit(4, 4, |canvas| canvas.draw_polyline(&[] as &[(i32, i32)], Color::WHITE)).is_empty());
+    }
+
+    #[test]
+    fn degenerate_and_gentle_beziers() {
+        assert_eq!(lit(8, 8, |canvas| canvas.draw_bezier_cubic((3, 4), (3, 4), (3, 4), (3, 4), Color::WHITE)), set(&[(3, 4)]));
+        assert_eq!(lit(8, 8, |canvas| canvas.draw_bezier_quad((3, 4), (3, 4), (3, 4), Color::WHITE)), set(&[(3, 4)]));
+
+        // Nearly straight: a fifth of a pixel off over 40 is one line, and a pixel off
+        // is still only a few
+        let gentle = [(0.0, 0.0), (13.0, 0.2), (27.0, 0.2), (40.0, 0.0)].map(|(x, y)| Vec2f::new(x, y));
+        assert_eq!(flatten_cubic(gentle), vec![Vec2i::new(0, 0), Vec2i::new(40, 0)]);
+        let bent = [(0, 0), (13, 1), (27, 1), (40, 0)].map(|(x, y)| Vec2f::new(x as f32, y as f32));
+        assert!(flatten_cubic(bent).len() <= 5);
+        // And a proper curve takes plenty more
+        let curvy = [(0, 0), (0, 40), (40, 40), (40, 0)].map(|(x, y)| Vec2f::new(x as f32, y as f32));
+        assert!(flatten_cubic(curvy).len() > 8);
+        // The ends are where they should be
+        let points = flatten_cubic(curvy);
+        assert_eq!((points[0], points[points.len() - 1]), (Vec2i::new(0, 0), Vec2i::new(40, 0)))
+    }
+
+    #[test]
+    fn very_wide_triangle() {
+        // Over two billion pixels across at the top, so only the middle's showing
+        let pixels = lit(8, 12, |c| c.fill_triangle((-1_200_000_000, 0), (1_200_000_000, 0), (0, 10), Color::WHITE));
+        let expected: BTreeSet<_> = (0..10).flat_map(|y| (0..8).map(move |x| (x, y))).collect();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn round_rects_at_the_far_edge() {
+        fn round_rect(c: &mut Canvas, filled: bool, (x, y, w, h): (i32, i32, u32, u32), radius: u32) {
+            if filled { c.fill_round_rect(x, y, w, h, radius, Color::WHITE) } else { c.draw_round_rect(x, y, w, h, radius, Color::WHITE) }
+        }
+        // Near i32::MAX, with the camera there to see it: the same as the
+        // rectangle's corner nearer home. (Its far sides would be past i32::MAX.)
+        let far = i32::MAX - 30;
+        for filled in [false, true] {
+            let pixels = lit(10, 10, |c| {
+                c.set_camera(far, far);
+                round_rect(c, filled, (far + 2, far + 2, 100, 100), 3);
+                c.set_camera(0, 0)
+            });
+            assert_eq!(pixels, lit(10, 10, |c| round_rect(c, filled, (2, 2, 100, 100), 3)));
+        }
+    }
+}
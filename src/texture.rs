@@ -0,0 +1,74 @@
+// A RenderTexture is an off-screen frame: it owns a buffer laid out just like the
+// one Pixels gives us, so a Canvas can draw on it the same way. Draw something
+// that doesn't change (a UI panel, a level's background) into one once, and then
+// every frame it's one blit instead of all the drawing.
+
+use crate::{Canvas, Color, Surface};
+
+/// An owned RGBA image, `width` by `height`, that can be drawn on and then copied
+/// onto the screen with `Canvas::blit_texture`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RenderTexture {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl RenderTexture {
+    /// A new texture, all transparent.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, data: vec![0; width as usize * height as usize * 4] }
+    }
+
+    /// The raw RGBA bytes, a row at a time from the top
+    pub fn pixels(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// A Canvas for drawing on the texture, with everything a Canvas can do
+    /// (clipping, the camera, blitting sprites).
+    pub fn canvas(&mut self) -> Canvas<'_> {
+        Canvas::new(&mut self.data, self.width, self.height)
+    }
+
+    // Where a pixel's bytes start, if it's on the texture
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height { return None }
+        Some((y as usize * self.width as usize + x as usize) * 4)
+    }
+}
+
+impl Surface for RenderTexture {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.data[i..i + 4].copy_from_slice(&color.as_bytes())
+        }
+    }
+
+    fn fill_row(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+        if y < 0 || y as u32 >= self.height { return }
+        let (x0, x1) = (x0.max(0) as usize, (x1.max(0) as u32).min(self.width) as usize);
+        if x0 >= x1 { return }
+        let row = y as usize * self.width as usize;
+        let bytes = color.as_bytes();
+        for px in self.data[(row + x0) * 4..(row + x1) * 4].chunks_exact_mut(4) {
+            px.copy_from_slice(&bytes)
+        }
+    }
+
+    fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+        self.index(x, y).map(|i| {
+            let mut px = [0u8; 4];
+            px.copy_from_slice(&self.data[i..i + 4]);
+            px.into()
+        })
+    }
+}
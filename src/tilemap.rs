@@ -0,0 +1,74 @@
+// A TileMap is a grid of tile numbers, each one a frame of a sprite sheet. Most of
+// a 2D level is one of these, and most of it is off the screen at any moment, so the
+// drawing (Canvas::draw_tilemap) only looks at the tiles that can be seen.
+
+use crate::SpriteSheet;
+
+/// A `width` by `height` grid of tiles, each `tile_width` by `tile_height` pixels
+/// and drawn from a frame of `atlas`.
+#[derive(Clone, Debug)]
+pub struct TileMap {
+    width: u32,
+    height: u32,
+    tile_size: (u32, u32),
+    atlas: SpriteSheet,
+    // One frame number per tile, a row at a time from the top; EMPTY for none
+    tiles: Vec<u16>,
+}
+
+impl TileMap {
+    /// The tile number for "nothing here": empty tiles aren't drawn, so whatever's
+    /// under them shows through.
+    pub const EMPTY: u16 = u16::MAX;
+
+    /// An empty map, `width` by `height` tiles. The tiles are `tile_width` by
+    /// `tile_height` pixels apart, which should be the size of the atlas's frames.
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32, atlas: SpriteSheet) -> Self {
+        let tiles = vec![Self::EMPTY; width as usize * height as usize];
+        Self { width, height, tile_size: (tile_width, tile_height), atlas, tiles }
+    }
+
+    /// How many tiles across the map is
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// How many tiles down the map is
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// How big each tile is, in pixels, as (width, height)
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.tile_size
+    }
+
+    pub fn atlas(&self) -> &SpriteSheet {
+        &self.atlas
+    }
+
+    /// The tile at column `x` and row `y`: a frame number in the atlas, or `EMPTY`.
+    /// `None` if that's off the map.
+    pub fn get(&self, x: u32, y: u32) -> Option<u16> {
+        self.index(x, y).map(|i| self.tiles[i])
+    }
+
+    /// Change the tile at column `x` and row `y`. Off the map, this does nothing.
+    /// A frame number the atlas doesn't have is allowed, but draws nothing.
+    pub fn set(&mut self, x: u32, y: u32, tile: u16) {
+        if let Some(i) = self.index(x, y) {
+            self.tiles[i] = tile
+        }
+    }
+
+    /// All the tiles, a row at a time from the top, for filling the map in from a
+    /// level file or the like.
+    pub fn tiles_mut(&mut self) -> &mut [u16] {
+        &mut self.tiles
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height { return None }
+        Some(y as usize * self.width as usize + x as usize)
+    }
+}
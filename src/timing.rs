@@ -0,0 +1,194 @@
+// Keeping time for the runner. The fixed timestep: updates happen at a steady rate no matter how fast frames
+// are being drawn. Every frame, the real time that's passed goes into an
+// accumulator, and then we run one update for each whole step's worth of time in
+// there, carrying the remainder on to the next frame.
+
+use std::hint;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::Context;
+
+// If we've fallen this many updates behind in one frame, give up on catching up.
+// Otherwise a slow update makes the next frame have more updates to do, which makes
+// it slower, and so on until we're doing nothing but updates ("the spiral of death").
+const MAX_CATCH_UP: u32 = 5;
+
+pub(crate) struct FixedStep {
+    step: Duration,
+    accumulator: Duration,
+    last: Instant,
+}
+
+impl FixedStep {
+    pub(crate) fn new(tick_rate_hz: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / tick_rate_hz.max(1),
+            accumulator: Duration::ZERO,
+            last: Instant::now(),
+        }
+    }
+
+    // How long each update is
+    pub(crate) fn step(&self) -> Duration {
+        self.step
+    }
+
+    // How far we are from the last update to the next one, from 0 up to but not
+    // including 1. The accumulator's always less than a step after advance, but a
+    // hair under a step can still round up to 1 as an f32, so that's clamped off.
+    pub(crate) fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f32() / self.step.as_secs_f32()).clamp(0.0, 1.0 - f32::EPSILON / 2.0)
+    }
+
+    // Start counting from now, forgetting any time that's already gone by
+    pub(crate) fn reset(&mut self, now: Instant) {
+        self.last = now;
+        self.accumulator = Duration::ZERO
+    }
+
+    // Account for the time since the last call, and say how many updates to run
+    pub(crate) fn advance(&mut self, now: Instant) -> u32 {
+        self.accumulator += (now - self.last).min(Context::MAX_DT);
+        self.last = now;
+
+        let mut updates = 0;
+        while self.accumulator >= self.step && updates < MAX_CATCH_UP {
+            self.accumulator -= self.step;
+            updates += 1
+        }
+        if updates == MAX_CATCH_UP {
+            // Whatever whole steps we couldn't get to are just dropped: the world
+            // runs slow for a moment instead of grinding to a halt. The part of a
+            // step left over stays, so alpha carries on from where it was.
+            let left = self.accumulator.as_nanos() % self.step.as_nanos().max(1);
+            self.accumulator = Duration::from_nanos(left as u64)
+        }
+        updates
+    }
+}
+
+// Sleeping can overshoot by a millisecond or two, depending on the OS, so we sleep
+// until this long before the next frame's due and spin for the rest
+const SPIN_TIME: Duration = Duration::from_millis(2);
+
+pub(crate) struct FrameLimiter {
+    interval: Duration,
+    next: Instant,
+}
+
+impl FrameLimiter {
+    // A limiter for max_fps frames a second, or None if there's no limit
+    pub(crate) fn new(max_fps: Option<u32>) -> Option<Self> {
+        let fps = max_fps.filter(|&fps| fps > 0)?;
+        Some(Self { interval: Duration::from_secs(1) / fps, next: Instant::now() })
+    }
+
+    // Wait until it's time for the next frame
+    pub(crate) fn wait(&mut self) {
+        let now = Instant::now();
+        if self.next > now + SPIN_TIME {
+            thread::sleep(self.next - now - SPIN_TIME)
+        }
+        while Instant::now() < self.next {
+            hint::spin_loop()
+        }
+
+        // The one after is due an interval after this one was, not after now, so
+        // the frames stay evenly spaced even if we're woken a bit late. If we've
+        // fallen behind by more than a frame, though (a slow frame, or we were
+        // paused), there's no point rushing to catch up, so count from now.
+        let now = Instant::now();
+        self.next += self.interval;
+        if self.next < now {
+            self.next = now + self.interval
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn updates_for_each_whole_step() {
+        // 100 updates a second is 10 ms a step
+        let start = Instant::now();
+        let mut fixed = FixedStep::new(100);
+        fixed.reset(start);
+        assert_eq!(fixed.step(), MS * 10);
+        // One step and a bit more, then the bit more and the rest of a step, then
+        // half a step, which isn't enough to do anything yet
+        assert_eq!(fixed.advance(start + MS * 16), 1);
+        assert!((fixed.alpha() - 0.6).abs() < 1e-4);
+        assert_eq!(fixed.advance(start + MS * 20), 1);
+        assert_eq!(fixed.alpha(), 0.0);
+        assert_eq!(fixed.advance(start + MS * 25), 0);
+        assert!((fixed.alpha() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn stalls_are_capped() {
+        // A one second stall is clamped to MAX_DT, which is 25 steps, but only
+        // MAX_CATCH_UP of those get run
+        let start = Instant::now();
+        let mut fixed = FixedStep::new(100);
+        fixed.reset(start);
+        assert_eq!(fixed.advance(start + Duration::from_secs(1)), MAX_CATCH_UP);
+        // The steps it couldn't get to are dropped whole, so it's still only 0.0..1.0
+        // of the way to the next one; what's left of the step carries on
+        assert_eq!(fixed.alpha(), 0.0);
+        assert_eq!(fixed.advance(start + Duration::from_secs(1) + MS * 7), 0);
+        assert!((fixed.alpha() - 0.7).abs() < 1e-4);
+        // And even a hair under a whole step doesn't round up to one
+        fixed.accumulator = fixed.step - Duration::from_nanos(1);
+        assert!(fixed.alpha() < 1.0);
+
+        // At two updates a second, the clamp alone is the difference: a second's
+        // stall only counts as half a step
+        let mut slow = FixedStep::new(2);
+        slow.reset(start);
+        assert_eq!(slow.advance(start + Duration::from_secs(1)), 0);
+        assert!((slow.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(slow.advance(start + Duration::from_secs(2)), 1);
+        assert_eq!(slow.alpha(), 0.0);
+    }
+
+    #[test]
+    fn reset_forgets_the_time() {
+        let start = Instant::now();
+        let mut fixed = FixedStep::new(100);
+        fixed.reset(start);
+        fixed.advance(start + MS * 15);
+        assert!(fixed.alpha() > 0.0);
+        // Time up to the reset doesn't count, and neither does the leftover
+        fixed.reset(start + MS * 100);
+        assert_eq!(fixed.alpha(), 0.0);
+        assert_eq!(fixed.advance(start + MS * 100), 0);
+        assert_eq!(fixed.advance(start + MS * 110), 1);
+        // A tick rate of zero is taken as one a second instead of dividing by it
+        assert_eq!(FixedStep::new(0).step(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn frame_limiter_paces_frames() {
+        assert!(FrameLimiter::new(None).is_none());
+        assert!(FrameLimiter::new(Some(0)).is_none());
+
+        // The first frame's due straight away, and each after that an interval on
+        let mut limiter = FrameLimiter::new(Some(200)).unwrap();
+        assert_eq!(limiter.interval, MS * 5);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.wait()
+        }
+        assert!(start.elapsed() >= MS * 10);
+
+        // Falling well behind doesn't make it rush through the frames it missed
+        thread::sleep(MS * 20);
+        let late = Instant::now();
+        limiter.wait();
+        assert!(limiter.next > late);
+    }
+}
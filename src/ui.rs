@@ -0,0 +1,131 @@
+// Bare-bones immediate-mode widgets, for debug UIs. There's no layout: every widget
+// gets told exactly where it goes, every update, and says right then whether it
+// was clicked.
+//
+// Widgets are asked for in update, since that's where the clicks are, but they can
+// only be drawn in draw. So the context keeps a list of what was asked for, and the
+// runner draws it over the top of the app's own drawing. The list is started afresh
+// each update: with several updates in one frame, only the last one's widgets get
+// drawn, and with none, the last ones stay up.
+
+use crate::{font, Canvas, Color, Origin, Surface};
+
+const FACE: Color = Color::rgb(0x40, 0x40, 0x50);
+const HOVER: Color = Color::rgb(0x60, 0x60, 0x78);
+const PRESSED: Color = Color::rgb(0x28, 0x28, 0x30);
+const BORDER: Color = Color::rgb(0xa0, 0xa0, 0xb0);
+const LABEL: Color = Color::WHITE;
+
+#[derive(Default, Debug)]
+pub(crate) struct Widgets {
+    buttons: Vec<Button>,
+}
+
+#[derive(Debug)]
+struct Button {
+    rect: (i32, i32, u32, u32),
+    label: String,
+    hovered: bool,
+    pressed: bool,
+}
+
+impl Widgets {
+    pub(crate) fn clear(&mut self) {
+        self.buttons.clear()
+    }
+
+    pub(crate) fn button(&mut self, rect: (i32, i32, u32, u32), label: &str, hovered: bool, pressed: bool) {
+        self.buttons.push(Button { rect, label: label.to_string(), hovered, pressed })
+    }
+
+    // Draw them all, on the canvas as such: whatever camera, origin, clip or blend
+    // mode the app left set, the widgets go where the mouse was
+    pub(crate) fn draw(&self, canvas: &mut Canvas) {
+        if self.buttons.is_empty() { return }
+        canvas.set_camera(0, 0);
+        canvas.set_origin(Origin::TopLeft);
+        canvas.set_clip(None);
+        canvas.set_blend_mode(Default::default());
+
+        for button in &self.buttons {
+            let (x, y, w, h) = button.rect;
+            let face = match (button.hovered, button.pressed) {
+                (_, true) => PRESSED,
+                (true, false) => HOVER,
+                (false, false) => FACE,
+            };
+            canvas.fill_rect(x, y, w, h, face);
+            canvas.draw_rect(x, y, w, h, BORDER);
+
+            // The label goes in the middle, and anything hanging out of the
+            // button gets cut off
+            let (tw, th) = font::measure_text(&button.label);
+            canvas.set_clip(Some(button.rect));
+            let tx = (x as i64 + (w as i64 - tw as i64) / 2).clamp(i32::MIN as i64, i32::MAX as i64);
+            let ty = (y as i64 + (h as i64 - th as i64) / 2).clamp(i32::MIN as i64, i32::MAX as i64);
+            canvas.draw_text(tx as i32, ty as i32, &button.label, LABEL);
+            canvas.set_clip(None)
+        }
+    }
+}
+
+// Whether a pixel is in a rectangle
+pub(crate) fn contains(rect: (i32, i32, u32, u32), (px, py): (i32, i32)) -> bool {
+    let (x, y, w, h) = rect;
+    let (px, py) = (px as i64, py as i64);
+    px >= x as i64 && py >= y as i64 && px < x as i64 + w as i64 && py < y as i64 + h as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use winit::event::{ElementState, MouseButton};
+    use crate::app::render_headless_with_input;
+    use crate::{App, Context, WindowConfig};
+    use super::*;
+
+    const RECT: (i32, i32, u32, u32) = (10, 10, 30, 12);
+
+    // An app with one button, that writes down whether it was clicked each update
+    struct OneButton(Rc<RefCell<Vec<bool>>>);
+
+    impl App for OneButton {
+        fn update(&mut self, ctx: &mut Context, _dt: Duration) {
+            let clicked = ctx.button(RECT, "OK");
+            self.0.borrow_mut().push(clicked)
+        }
+
+        fn draw(&mut self, _ctx: &Context, canvas: &mut Canvas, _alpha: f32) {
+            canvas.clear(Color::BLACK)
+        }
+    }
+
+    #[test]
+    fn button_clicks() {
+        let clicks = Rc::new(RefCell::new(vec![]));
+        let config = WindowConfig { pixel_size: (64, 32), ..WindowConfig::default() };
+        let frames = render_headless_with_input(config, 5, OneButton(clicks.clone()), |frame, input| match frame {
+            // Over the button, then click it, let go, and click outside it
+            0 => input.set_mouse_pos(Some((20, 15))),
+            1 => input.handle_mouse_button(MouseButton::Left, ElementState::Pressed),
+            2 => input.handle_mouse_button(MouseButton::Left, ElementState::Released),
+            3 => input.set_mouse_pos(Some((2, 2))),
+            _ => input.handle_mouse_button(MouseButton::Left, ElementState::Pressed),
+        });
+        assert_eq!(*clicks.borrow(), vec![false, true, false, false, false]);
+
+        let face = |frame: usize| frames[frame].get_pixel(11, 11);
+        assert_eq!(frames[0].get_pixel(10, 10), Some(BORDER));
+        assert_eq!(frames[0].get_pixel(9, 10), Some(Color::BLACK));
+        assert_eq!([face(0), face(1), face(2), face(3), face(4)], [HOVER, PRESSED, HOVER, FACE, FACE].map(Some));
+    }
+
+    #[test]
+    fn contains_edges() {
+        assert!(contains(RECT, (10, 10)) && contains(RECT, (39, 21)));
+        assert!(!contains(RECT, (40, 15)) && !contains(RECT, (15, 22)) && !contains(RECT, (9, 15)));
+        assert!(!contains((i32::MAX, 0, u32::MAX, 1), (i32::MIN, 0)));
+    }
+}
@@ -0,0 +1,165 @@
+// Little 2D vectors, for positions and velocities, so they can be added up and
+// passed around as one thing instead of two. Anything that takes a point takes
+// `impl Into<Vec2i>`, so a plain (x, y) tuple still works everywhere a Vec2i does.
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A point or offset in whole pixels.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Vec2i {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A point or offset in fractions of pixels, for things that move smoothly.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Vec2f {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2i {
+    pub const ZERO: Vec2i = Vec2i::new(0, 0);
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// The length squared, which is enough for comparing distances without a
+    /// square root. It's an i64 so it can't overflow.
+    pub const fn length_sq(self) -> i64 {
+        self.x as i64 * self.x as i64 + self.y as i64 * self.y as i64
+    }
+
+    /// The "taxicab" length: how far it is going only along rows and columns.
+    pub const fn manhattan(self) -> i64 {
+        (self.x as i64).abs() + (self.y as i64).abs()
+    }
+}
+
+impl Vec2f {
+    pub const ZERO: Vec2f = Vec2f::new(0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn length_sq(self) -> f32 {
+        self.x * self.x + self.y * self.y
+    }
+
+    /// The pixel this point is in (rounding down, not toward zero, so -0.5 is in
+    /// pixel -1). Out-of-range values saturate.
+    pub fn floor(self) -> Vec2i {
+        Vec2i::new(self.x.floor() as i32, self.y.floor() as i32)
+    }
+}
+
+// The same operators for both, each spelled out once by this
+macro_rules! vec_ops {
+    ($vec:ident, $scalar:ty) => {
+        impl Add for $vec {
+            type Output = $vec;
+            fn add(self, other: $vec) -> $vec {
+                $vec::new(self.x + other.x, self.y + other.y)
+            }
+        }
+
+        impl Sub for $vec {
+            type Output = $vec;
+            fn sub(self, other: $vec) -> $vec {
+                $vec::new(self.x - other.x, self.y - other.y)
+            }
+        }
+
+        impl Mul<$scalar> for $vec {
+            type Output = $vec;
+            fn mul(self, n: $scalar) -> $vec {
+                $vec::new(self.x * n, self.y * n)
+            }
+        }
+
+        impl Neg for $vec {
+            type Output = $vec;
+            fn neg(self) -> $vec {
+                $vec::new(-self.x, -self.y)
+            }
+        }
+
+        impl AddAssign for $vec {
+            fn add_assign(&mut self, other: $vec) {
+                *self = *self + other
+            }
+        }
+
+        impl SubAssign for $vec {
+            fn sub_assign(&mut self, other: $vec) {
+                *self = *self - other
+            }
+        }
+
+        impl From<($scalar, $scalar)> for $vec {
+            fn from((x, y): ($scalar, $scalar)) -> Self {
+                $vec::new(x, y)
+            }
+        }
+
+        impl From<$vec> for ($scalar, $scalar) {
+            fn from(v: $vec) -> Self {
+                (v.x, v.y)
+            }
+        }
+    };
+}
+
+vec_ops!(Vec2i, i32);
+vec_ops!(Vec2f, f32);
+
+impl From<Vec2i> for Vec2f {
+    fn from(v: Vec2i) -> Self {
+        Vec2f::new(v.x as f32, v.y as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vec2i_ops() {
+        let (a, b) = (Vec2i::new(3, -4), Vec2i::new(-1, 2));
+        assert_eq!(a + b, Vec2i::new(2, -2));
+        assert_eq!(a - b, Vec2i::new(4, -6));
+        assert_eq!(a * 3, Vec2i::new(9, -12));
+        assert_eq!(-a, Vec2i::new(-3, 4));
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+        c -= b;
+        assert_eq!(c, a);
+        assert_eq!(a - a, Vec2i::ZERO);
+    }
+
+    #[test]
+    fn vec2i_lengths() {
+        assert_eq!(Vec2i::new(3, -4).length_sq(), 25);
+        assert_eq!(Vec2i::new(3, -4).manhattan(), 7);
+        // Big enough to overflow an i32 if it weren't an i64
+        assert_eq!(Vec2i::new(i32::MAX, i32::MAX).length_sq(), 2 * i32::MAX as i64 * i32::MAX as i64);
+        assert_eq!(Vec2i::new(i32::MIN, 0).manhattan(), 1i64 << 31);
+    }
+
+    #[test]
+    fn tuples_and_conversions() {
+        let v: Vec2i = (5, 6).into();
+        assert_eq!(v, Vec2i::new(5, 6));
+        assert_eq!(<(i32, i32)>::from(v), (5, 6));
+        assert_eq!(Vec2f::from(v), Vec2f::new(5.0, 6.0));
+        assert_eq!(Vec2f::new(-0.5, 1.9).floor(), Vec2i::new(-1, 1));
+        assert_eq!(Vec2f::new(3.0, 4.0).length(), 5.0);
+    }
+}
@@ -0,0 +1,62 @@
+// Drawing with no window, through render_headless, and checking the frames byte
+// for byte.
+
+use std::time::Duration;
+use minimal_pixels::{render_headless, App, Canvas, Color, Context, Surface, WindowConfig};
+
+// The demo's yellow square, without the fps counter (which isn't the same from
+// one run to the next)
+struct Square;
+
+impl App for Square {
+    fn update(&mut self, _ctx: &mut Context, _dt: Duration) {}
+
+    fn draw(&mut self, _ctx: &Context, canvas: &mut Canvas, _alpha: f32) {
+        canvas.clear(Color::BLACK);
+        canvas.fill_rect(51, 51, 49, 49, Color::rgba(0xff, 0xff, 0x50, 0xff));
+    }
+}
+
+#[test]
+fn demo_square() {
+    let (width, height) = WindowConfig::default().pixel_size;
+    let mut expected = vec![0u8; width as usize * height as usize * 4];
+    for (i, px) in expected.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let inside = (51..100).contains(&x) && (51..100).contains(&y);
+        px.copy_from_slice(if inside { &[0xff, 0xff, 0x50, 0xff] } else { &[0, 0, 0, 0xff] })
+    }
+
+    let frames = render_headless(WindowConfig::default(), 3, Square);
+    assert_eq!(frames.len(), 3);
+    for frame in &frames {
+        assert_eq!((frame.width(), frame.height()), (width, height));
+        assert!(frame.pixels() == expected.as_slice())
+    }
+}
+
+// Counts its updates, leaves a trail of what it drew, and quits after three
+struct Trail(i32);
+
+impl App for Trail {
+    fn update(&mut self, ctx: &mut Context, _dt: Duration) {
+        self.0 += 1;
+        if self.0 > 3 { ctx.quit() }
+    }
+
+    fn draw(&mut self, _ctx: &Context, canvas: &mut Canvas, _alpha: f32) {
+        canvas.set_pixel(self.0, 0, Color::WHITE)
+    }
+}
+
+#[test]
+fn frames_carry_over_until_quit() {
+    let config = WindowConfig { pixel_size: (8, 1), ..WindowConfig::default() };
+    let frames = render_headless(config, 10, Trail(0));
+    // The update that quit isn't drawn
+    assert_eq!(frames.len(), 3);
+    let lit: Vec<Vec<i32>> = frames.iter()
+        .map(|frame| (0..8).filter(|&x| frame.get_pixel(x, 0) == Some(Color::WHITE)).collect())
+        .collect();
+    assert_eq!(lit, vec![vec![1], vec![1, 2], vec![1, 2, 3]]);
+}